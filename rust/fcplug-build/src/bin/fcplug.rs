@@ -0,0 +1,455 @@
+//! The `fcplug` command, which runs the code generation outside `build.rs`:
+//!
+//! ```shell
+//! fcplug init <DIR> [--thrift] [--go-mod-parent <PATH>]
+//! fcplug generate [OPTIONS]
+//! fcplug check [OPTIONS]
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode, Stdio};
+use std::{env, fs, io::Write};
+
+use fcplug_build::{generate_code, generate_code_in_memory, Config, FcplugError};
+
+const USAGE: &str = r#"Usage:
+    fcplug init <DIR> [--thrift] [--go-mod-parent <PATH>]
+        Scaffold a crate with an IDL, Cargo.toml, build.rs and the Go module
+    fcplug generate [OPTIONS]
+        Generate the code and build the Go C library, as build.rs does
+    fcplug check [OPTIONS]
+        Check that the committed generated files are up to date, exit with 1 if not,
+        which are the files with the fcplug header, but not the Go code of protoc or thriftgo
+
+Options:
+    --crate-dir <DIR>        The crate directory, default to the current directory
+    --idl <FILE>             The IDL file, default to the only .proto or .thrift file in the crate directory
+    --go-mod-parent <PATH>   The parent of the Go module path, default to the one in go.mod
+    --go-root <DIR>          The Go root directory, default to find from $GOROOT > $PATH
+    --profile <NAME>         The cargo profile of the Rust C library, default to dev
+    --goffi-cdylib           Build the GoFFI into a C dynamic library
+    --go-host                The Go program is the host process
+    --typed-rustffi          The RustFFI methods receive and return the objects
+    --go-log-bridge          Bridge the logs between Rust tracing and Go slog
+    --go-offline             Never run the Go commands which may download the modules
+
+The options override [package.metadata.fcplug] in Cargo.toml and the FCPLUG_* environment variables.
+"#;
+
+/// The pilota required by the generated code, which is the one of the fcplug workspace, checked by the tests
+const PILOTA_VERSION: &str = "0.7.0";
+
+/// The header of the files which are owned by fcplug, others such as `mod.rs` are owned by the user.
+const GENERATED_HEADER: &str = "// Code generated by fcplug. DO NOT EDIT.";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("init") => init(&args[1..]),
+        Some("generate") => generate(&args[1..]),
+        Some("check") => check(&args[1..]),
+        Some("-h" | "--help" | "help") => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        _ => Err(format!("missing or unknown command\n\n{USAGE}")),
+    };
+    match result {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// The command line arguments, `--flag` or `--key <value>`
+#[derive(Default)]
+struct Args {
+    positional: Vec<String>,
+    flags: Vec<String>,
+    values: BTreeMap<String, String>,
+}
+
+impl Args {
+    fn parse(args: &[String], value_keys: &[&str], flag_keys: &[&str]) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if value_keys.contains(&arg.as_str()) {
+                let value = iter.next().ok_or(format!("missing the value of {arg}"))?;
+                parsed.values.insert(arg.clone(), value.clone());
+            } else if flag_keys.contains(&arg.as_str()) {
+                parsed.flags.push(arg.clone());
+            } else if arg.starts_with("--") {
+                return Err(format!("unknown option {arg}\n\n{USAGE}"));
+            } else {
+                parsed.positional.push(arg.clone());
+            }
+        }
+        Ok(parsed)
+    }
+    fn value(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+    fn flag(&self, key: &str) -> bool {
+        self.flags.iter().any(|flag| flag == key)
+    }
+}
+
+fn init(args: &[String]) -> Result<ExitCode, String> {
+    let args = Args::parse(args, &["--go-mod-parent"], &["--thrift"])?;
+    let [dir] = args.positional.as_slice() else {
+        return Err(format!("init requires one crate directory\n\n{USAGE}"));
+    };
+    let dir = PathBuf::from(dir);
+    let crate_name = dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or(format!("invalid crate directory {}", dir.display()))?
+        .to_string();
+    // the same as the Go package name of the generator
+    let pkg_name = crate_name
+        .replace(['.', '-'], "_")
+        .trim_matches('_')
+        .to_string();
+    let go_mod_parent = args
+        .value("--go-mod-parent")
+        .unwrap_or("example.com")
+        .trim_end_matches('/');
+    let idl_file = if args.flag("--thrift") {
+        format!("{pkg_name}.thrift")
+    } else {
+        format!("{pkg_name}.proto")
+    };
+    let version = env!("CARGO_PKG_VERSION");
+
+    fs::create_dir_all(dir.join("src")).map_err(|e| format!("{}: {e}", dir.display()))?;
+    let files = [
+        (
+            "Cargo.toml",
+            format!(
+                r#"[package]
+name = "{crate_name}"
+version = "0.1.0"
+edition = "2021"
+
+[lib]
+crate-type = ["rlib", "staticlib"]
+
+[dependencies]
+fcplug = "{version}"
+pilota = "{PILOTA_VERSION}"
+serde = "1"
+serde_json = "1"
+
+[build-dependencies]
+fcplug-build = "{version}"
+
+[features]
+# mock the GoFFI in the Rust tests, without the Go toolchain
+goffi-mock = []
+"#
+            ),
+        ),
+        (
+            idl_file.as_str(),
+            if args.flag("--thrift") {
+                r#"struct Ping {
+    1: string msg,
+}
+
+struct Pong {
+    1: string msg,
+}
+
+// go call rust
+service RustFFI {
+    Pong echo_rs (1: Ping req),
+}
+
+// rust call go
+service GoFFI {
+    Pong echo_go (1: Ping req),
+}
+"#
+            } else {
+                r#"syntax = "proto3";
+
+message Ping {
+  string msg = 1;
+}
+
+message Pong {
+  string msg = 1;
+}
+
+// go call rust
+service RustFFI {
+  rpc echo_rs (Ping) returns (Pong) {}
+}
+
+// rust call go
+service GoFFI {
+  rpc echo_go (Ping) returns (Pong) {}
+}
+"#
+            }
+            .to_string(),
+        ),
+        (
+            "build.rs",
+            format!(
+                r#"use fcplug_build::{{generate_code, Config}};
+
+fn main() {{
+    generate_code(Config {{
+        idl_file: "./{idl_file}".into(),
+        go_mod_parent: "{go_mod_parent}".into(),
+        ..Default::default()
+    }})
+    .unwrap();
+}}
+"#
+            ),
+        ),
+        ("src/lib.rs", format!("pub mod {pkg_name}_ffi;\n")),
+        (
+            "go.mod",
+            format!("module {go_mod_parent}/{pkg_name}\n\ngo 1.18\n"),
+        ),
+    ];
+    for (name, content) in files {
+        let file = dir.join(name);
+        if file.exists() {
+            println!("skip existing {}", file.display());
+            continue;
+        }
+        fs::write(&file, content).map_err(|e| format!("{}: {e}", file.display()))?;
+        println!("created {}", file.display());
+    }
+    println!(
+        "run 'cargo build' in {} to generate the code",
+        dir.display()
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+const GEN_VALUE_KEYS: [&str; 5] = [
+    "--crate-dir",
+    "--idl",
+    "--go-mod-parent",
+    "--go-root",
+    "--profile",
+];
+const GEN_FLAG_KEYS: [&str; 5] = [
+    "--goffi-cdylib",
+    "--go-host",
+    "--typed-rustffi",
+    "--go-log-bridge",
+    "--go-offline",
+];
+
+fn generate(args: &[String]) -> Result<ExitCode, String> {
+    let args = Args::parse(args, &GEN_VALUE_KEYS, &GEN_FLAG_KEYS)?;
+    let config = gen_config(&args)?;
+    let artifacts = generate_code(config).map_err(|e| e.to_string())?;
+    println!("generated {}", artifacts.rust_gen_file.display());
+    for file in &artifacts.go_files {
+        println!("generated {}", file.display());
+    }
+    if let Some(file) = &artifacts.go_clib_file {
+        println!("built {}", file.display());
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn check(args: &[String]) -> Result<ExitCode, String> {
+    let args = Args::parse(args, &GEN_VALUE_KEYS, &GEN_FLAG_KEYS)?;
+    let config = gen_config(&args)?;
+    let crate_dir = config.target_crate_dir.clone().unwrap_or_default();
+    let go_root = args
+        .value("--go-root")
+        .map(Into::into)
+        .or_else(|| env::var_os("FCPLUG_GO_ROOT"))
+        .or_else(|| env::var_os("GOROOT"));
+    let gofmt_path = match go_root {
+        Some(go_root) => PathBuf::from(go_root).join("bin").join("gofmt"),
+        None => PathBuf::from("gofmt"),
+    };
+    let files = generate_code_in_memory(config).map_err(|e| e.to_string())?;
+    let mut stale = Vec::new();
+    for (path, content) in files {
+        if !content.starts_with(GENERATED_HEADER) {
+            continue;
+        }
+        let content = if path.extension().is_some_and(|ext| ext == "go") {
+            gofmt(&gofmt_path, &content)?
+        } else {
+            content
+        };
+        if fs::read_to_string(crate_dir.join(&path)).ok().as_ref() != Some(&content) {
+            stale.push(path);
+        }
+    }
+    if stale.is_empty() {
+        println!("the generated files are up to date");
+        return Ok(ExitCode::SUCCESS);
+    }
+    for path in &stale {
+        println!("stale: {}", path.display());
+    }
+    println!("run 'fcplug generate' or 'cargo build' to update them");
+    Ok(ExitCode::FAILURE)
+}
+
+/// The config of the crate, whose options are also read from `[package.metadata.fcplug]` in Cargo.toml
+/// and the `FCPLUG_*` environment variables, which are overridden by the command line options.
+fn gen_config(args: &Args) -> Result<Config, String> {
+    let crate_dir = match args.value("--crate-dir") {
+        Some(dir) => PathBuf::from(dir),
+        None => env::current_dir().map_err(|e| e.to_string())?,
+    };
+    let crate_dir = crate_dir
+        .canonicalize()
+        .map_err(|e| format!("{}: {e}", crate_dir.display()))?;
+    let mut overrides = Vec::new();
+    for (key, option) in [
+        ("--idl", "idl_file"),
+        ("--go-mod-parent", "go_mod_parent"),
+        ("--go-root", "go_root_path"),
+    ] {
+        if let Some(value) = args.value(key) {
+            overrides.push((option.to_string(), value.to_string()));
+        }
+    }
+    for (key, option) in [
+        ("--goffi-cdylib", "use_goffi_cdylib"),
+        ("--go-host", "go_host"),
+        ("--typed-rustffi", "typed_rustffi"),
+        ("--go-log-bridge", "go_log_bridge"),
+        ("--go-offline", "go_offline"),
+    ] {
+        if args.flag(key) {
+            overrides.push((option.to_string(), "true".to_string()));
+        }
+    }
+    Ok(Config {
+        idl_file: find_idl(&crate_dir).unwrap_or_default(),
+        go_mod_parent: go_mod_parent(&crate_dir).unwrap_or_default(),
+        artifact_dir: Some(artifact_dir(
+            &crate_dir,
+            args.value("--profile").unwrap_or("dev"),
+        )?),
+        target: Some(host_target()?),
+        target_crate_dir: Some(crate_dir),
+        overrides,
+        ..Default::default()
+    })
+}
+
+/// The artifact directory of the profile, `<target-dir>/<profile>` as cargo builds for the host
+fn artifact_dir(crate_dir: &Path, profile: &str) -> Result<PathBuf, String> {
+    let metadata = command_output(
+        Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()))
+            .current_dir(crate_dir)
+            .args(["metadata", "--format-version=1", "--no-deps"]),
+    )?;
+    let metadata: serde_json::Value = serde_json::from_str(&metadata).map_err(|e| e.to_string())?;
+    let manifest = crate_dir.join("Cargo.toml");
+    if !metadata["packages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .any(|package| {
+            package["manifest_path"]
+                .as_str()
+                .and_then(|path| Path::new(path).canonicalize().ok())
+                .is_some_and(|path| path == manifest)
+        })
+    {
+        return Err(format!("no package is found in {}", crate_dir.display()));
+    }
+    let target_dir = PathBuf::from(metadata["target_directory"].as_str().unwrap_or("target"));
+    let profile_dir = match profile {
+        "dev" | "test" => "debug",
+        "bench" => "release",
+        profile => profile,
+    };
+    Ok(target_dir.join(profile_dir))
+}
+
+/// The host target triple, for which cargo builds without `--target`
+fn host_target() -> Result<String, String> {
+    command_output(Command::new("rustc").arg("-vV"))?
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .map(str::to_string)
+        .ok_or("failed to get the host target from 'rustc -vV'".to_string())
+}
+
+fn find_idl(crate_dir: &Path) -> Option<PathBuf> {
+    let idl_files = fs::read_dir(crate_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == "proto" || ext == "thrift")
+        })
+        .collect::<Vec<PathBuf>>();
+    match idl_files.as_slice() {
+        [idl_file] => Some(idl_file.clone()),
+        _ => None,
+    }
+}
+
+/// The parent of the module path in go.mod, e.g. `github.com/andeya/fcplug/samples`
+fn go_mod_parent(crate_dir: &Path) -> Option<String> {
+    let gomod = fs::read_to_string(crate_dir.join("go.mod")).ok()?;
+    gomod
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("module "))
+        .and_then(|path| path.trim().rsplit_once('/'))
+        .map(|(parent, _)| parent.to_string())
+}
+
+/// Format the Go code as the generator does, since the in-memory output is not formatted
+fn gofmt(gofmt_path: &Path, code: &str) -> Result<String, String> {
+    let mut child = Command::new(gofmt_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("gofmt: {e}"))?;
+    child
+        .stdin
+        .take()
+        .unwrap_or_else(|| unreachable!())
+        .write_all(code.as_bytes())
+        .map_err(|e| format!("gofmt: {e}"))?;
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("gofmt: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "gofmt: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn command_output(cmd: &mut Command) -> Result<String, String> {
+    let program = cmd.get_program().to_string_lossy().to_string();
+    let output = cmd.output().map_err(|e| format!("{program}: {e}"))?;
+    if !output.status.success() {
+        return Err(FcplugError::Command {
+            program,
+            code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        }
+        .to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
@@ -1,18 +1,43 @@
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::OsString;
 use std::fs::OpenOptions;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::str::FromStr;
+use std::sync::Arc;
 use std::{env, fs};
 
 use pilota_build::ir::ItemKind;
 use pilota_build::parser::{Parser, ProtobufParser, ThriftParser};
+use pilota_build::IdentName;
+use pilota_thrift_parser as thrift_parser;
+use pilota_thrift_parser::parser::Parser as _;
+use regex::Regex;
 
+use crate::error::IoContext;
+use crate::generator::ServiceType;
 use crate::{
-    deal_output, exit_with_warning, os_arch::get_go_os_arch_from_env, GenMode, BUILD_MODE, GEN_MODE,
+    cargo_env, os_arch::get_go_os_arch, redirect_stdout, run_command, FcplugError, GenMode,
+    BUILD_MODE, GEN_MODE,
 };
 
 const CGOBIN: &'static str = "cgobin";
 
+/// The options overridable by `[package.metadata.fcplug]` in Cargo.toml and by the environment variables,
+/// the metadata key -> the environment variable. `go_object_path` is a table, which is only in Cargo.toml.
+const OVERRIDES: [(&str, &str); 11] = [
+    ("idl_file", "FCPLUG_IDL_FILE"),
+    ("go_root_path", "FCPLUG_GO_ROOT"),
+    ("go_mod_parent", "FCPLUG_GO_MOD_PARENT"),
+    ("use_goffi_cdylib", "FCPLUG_USE_GOFFI_CDYLIB"),
+    ("add_clib_to_git", "FCPLUG_ADD_CLIB_TO_GIT"),
+    ("rust_impl_type", "FCPLUG_RUST_IMPL_TYPE"),
+    ("go_host", "FCPLUG_GO_HOST"),
+    ("typed_rustffi", "FCPLUG_TYPED_RUSTFFI"),
+    ("go_log_bridge", "FCPLUG_GO_LOG_BRIDGE"),
+    ("go_requires", "FCPLUG_GO_REQUIRES"),
+    ("go_offline", "FCPLUG_GO_OFFLINE"),
+];
+
 #[derive(Default, Debug, Clone)]
 pub struct Config {
     pub idl_file: PathBuf,
@@ -20,11 +45,44 @@ pub struct Config {
     pub target_crate_dir: Option<PathBuf>,
     /// go command dir, default to find from $GOROOT > $PATH
     pub go_root_path: Option<PathBuf>,
-    pub go_mod_parent: &'static str,
+    /// The parent of the Go module path, e.g. `github.com/andeya/fcplug/samples`
+    pub go_mod_parent: String,
     /// If use_goffi_cdylib is true, go will be compiled into a c dynamic library.
     pub use_goffi_cdylib: bool,
     /// If add_clib_to_git is true, the c lib files will be automatically added to the git version management list.
     pub add_clib_to_git: bool,
+    /// The type implementing the RustFFI and GoFFI traits, default to the generated unit struct `FfiImpl`.
+    /// Its instance is registered by `fcplug::register_rust_ffi`, e.g. in `Lifecycle::init`,
+    /// the calls before the registration fail with `RC_UNAVAILABLE`, and abort the process if they return scalars.
+    pub rust_impl_type: Option<UnitLikeStructPath>,
+    /// The Go object implementing the GoFFI interfaces, which lives in the user's own package,
+    /// default to assign the `Global*` variables in `cgobin/clib_goffi_impl.go`.
+    pub go_object_path: Option<GoObjectPath>,
+    /// If go_host is true, the Go program is the host process, which registers the GoFFI implementation
+    /// by `Register*` of the generated Go package at startup, and Rust calls it by function pointers,
+    /// so the `cgobin` archive is neither built nor linked.
+    pub go_host: bool,
+    /// If typed_rustffi is true, the RustFFI methods receive the decoded arguments and return the objects,
+    /// e.g. `fn echo_rs(&self, req: Ping) -> ABIResult<Pong>`, which are decoded and encoded by the generated exports.
+    /// Otherwise they receive `RustFfiArg` and return `TBytes`, which avoids copying.
+    pub typed_rustffi: bool,
+    /// If go_log_bridge is true, the generated Go package bridges the logs between Rust `tracing` and Go `log/slog`
+    /// by `BridgeRustLog` and `NewRustLogHandler`, which requires Go 1.21.
+    pub go_log_bridge: bool,
+    /// The Go module requirements `module@version`, which pin the versions of the modules used by the generated code
+    /// or add more, e.g. `github.com/bytedance/sonic@v1.9.1`, and are enforced in go.mod by `go mod edit`.
+    pub go_requires: Vec<String>,
+    /// If go_offline is true, the Go commands which may download the modules, e.g. `go mod tidy`, are never run,
+    /// and `go build` runs with `GOPROXY=off`, so go.mod and go.sum should be complete, or the modules vendored.
+    pub go_offline: bool,
+    /// The directory of the crate artifacts in which the C libraries are built, i.e. `<target-dir>/[<triple>/]<profile>`,
+    /// default to locate it by `OUT_DIR` of the build script, so it is set by the callers out of `build.rs`.
+    pub artifact_dir: Option<PathBuf>,
+    /// The target triple of the C libraries, default to `TARGET` of the build script.
+    pub target: Option<String>,
+    /// The options overriding `[package.metadata.fcplug]` in Cargo.toml and the environment variables,
+    /// keyed by the metadata keys, e.g. `("go_host", "true")`, such as the options of the `fcplug` command.
+    pub overrides: Vec<(String, String)>,
 }
 
 #[derive(Debug, Clone)]
@@ -39,9 +97,127 @@ impl Default for IdlType {
         IdlType::Proto
     }
 }
-/// unit-like struct path, e.g. `::mycrate::Abc`
+
+impl Config {
+    /// Override the config by `[package.metadata.fcplug]` in Cargo.toml of the crate,
+    /// then by the environment variables, e.g. `FCPLUG_GO_ROOT`, and finally by `overrides`.
+    /// The relative `idl_file` in Cargo.toml is relative to the crate directory.
+    pub(crate) fn apply_overrides(&mut self, crate_dir: &Path) -> Result<(), FcplugError> {
+        self.apply_overrides_with(crate_dir, |env_key| env::var(env_key).ok())
+    }
+
+    /// `apply_overrides` reading the environment variables by `env_var`
+    fn apply_overrides_with(
+        &mut self,
+        crate_dir: &Path,
+        env_var: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), FcplugError> {
+        if let Some(manifest) = read_manifest(crate_dir)? {
+            let metadata = manifest
+                .get("package")
+                .and_then(|package| package.get("metadata"))
+                .and_then(|metadata| metadata.get("fcplug"))
+                .and_then(|fcplug| fcplug.as_table());
+            for (key, value) in metadata.into_iter().flatten() {
+                match (key.as_str(), value) {
+                    ("go_object_path", toml::Value::Table(table)) => {
+                        let field = |name: &str| {
+                            table.get(name).and_then(|v| v.as_str()).ok_or_else(|| {
+                                FcplugError::Config(format!(
+                                    "package.metadata.fcplug.go_object_path.{name} should be a string"
+                                ))
+                            })
+                        };
+                        self.go_object_path = Some(GoObjectPath {
+                            import: field("import")?.to_string(),
+                            object_ident: field("object_ident")?.to_string(),
+                        });
+                    }
+                    ("idl_file", toml::Value::String(idl_file)) => {
+                        self.idl_file = crate_dir.join(idl_file);
+                    }
+                    ("go_requires", toml::Value::Array(requires)) => {
+                        self.go_requires = requires
+                            .iter()
+                            .map(|require| {
+                                require.as_str().map(str::to_string).ok_or_else(|| {
+                                    FcplugError::Config(
+                                        "package.metadata.fcplug.go_requires should be strings"
+                                            .to_string(),
+                                    )
+                                })
+                            })
+                            .collect::<Result<_, _>>()?;
+                    }
+                    (key, toml::Value::String(value)) => self.set_option(key, value)?,
+                    (key, toml::Value::Boolean(value)) => {
+                        self.set_option(key, &value.to_string())?
+                    }
+                    (key, _) => {
+                        return Err(FcplugError::Config(format!(
+                            "unsupported package.metadata.fcplug.{key}"
+                        )))
+                    }
+                }
+            }
+        }
+        for (key, env_key) in OVERRIDES {
+            if let Some(value) = env_var(env_key) {
+                self.set_option(key, &value)?;
+            }
+        }
+        for (key, value) in self.overrides.clone() {
+            self.set_option(&key, &value)?;
+        }
+        if self.idl_file.as_os_str().is_empty() {
+            return Err(FcplugError::Config(
+                "idl_file is not configured".to_string(),
+            ));
+        }
+        if self.go_mod_parent.is_empty() {
+            return Err(FcplugError::Config(
+                "go_mod_parent is not configured".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn set_option(&mut self, key: &str, value: &str) -> Result<(), FcplugError> {
+        let flag = || match value {
+            "true" | "1" => Ok(true),
+            "false" | "0" | "" => Ok(false),
+            _ => Err(FcplugError::Config(format!(
+                "the fcplug option {key} should be a bool, got '{value}'"
+            ))),
+        };
+        match key {
+            "idl_file" => self.idl_file = PathBuf::from(value),
+            "go_root_path" => self.go_root_path = Some(PathBuf::from(value)),
+            "go_mod_parent" => self.go_mod_parent = value.to_string(),
+            "use_goffi_cdylib" => self.use_goffi_cdylib = flag()?,
+            "add_clib_to_git" => self.add_clib_to_git = flag()?,
+            "rust_impl_type" => self.rust_impl_type = Some(UnitLikeStructPath(value.to_string())),
+            "go_host" => self.go_host = flag()?,
+            "typed_rustffi" => self.typed_rustffi = flag()?,
+            "go_log_bridge" => self.go_log_bridge = flag()?,
+            // e.g. `FCPLUG_GO_REQUIRES=github.com/andeya/gust@v1.5.2,github.com/bytedance/sonic@v1.9.1`
+            "go_requires" => {
+                self.go_requires = value
+                    .split([',', ' '])
+                    .filter(|require| !require.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }
+            "go_offline" => self.go_offline = flag()?,
+            _ => return Err(FcplugError::Config(format!("unknown fcplug option {key}"))),
+        }
+        Ok(())
+    }
+}
+
+/// struct path, e.g. `::mycrate::Abc`
 #[derive(Debug, Clone)]
-pub struct UnitLikeStructPath(pub &'static str);
+pub struct UnitLikeStructPath(pub String);
 
 #[derive(Debug, Clone)]
 pub struct GoObjectPath {
@@ -51,9 +227,21 @@ pub struct GoObjectPath {
     pub object_ident: String,
 }
 
+impl GoObjectPath {
+    /// The package name of the object ident, e.g. `mypkg`
+    pub(crate) fn package_name(&self) -> Option<&str> {
+        self.object_ident
+            .split_once('.')
+            .map(|(pkg, _)| pkg)
+            .filter(|pkg| !pkg.is_empty())
+    }
+}
+
 #[derive(Default, Debug, Clone)]
 pub(crate) struct WorkConfig {
     config: Config,
+    /// The target triple, which is empty if unknown
+    pub(crate) target: String,
     pub(crate) go_buildmode: &'static str,
     pub(crate) rustc_link_kind_goffi: &'static str,
     pub(crate) idl_file: PathBuf,
@@ -72,7 +260,13 @@ pub(crate) struct WorkConfig {
     pub(crate) rust_mod_impl_file: PathBuf,
     pub(crate) rust_mod_gen_name: String,
     pub(crate) go_lib_file: PathBuf,
+    /// The fake of the lib code built with the `fcplug_fake` tag, which needs no Rust C library
+    pub(crate) go_fake_file: PathBuf,
+    /// The cgo link directives of the Rust C library, which are left out of the cgobin build by the `fcplug_cgobin` tag
+    pub(crate) go_link_file: PathBuf,
     pub(crate) clib_gen_dir: PathBuf,
+    /// The directory of the C libraries in the cgo directives, which is `clib_gen_dir` except in the in-memory mode
+    pub(crate) clib_link_dir: PathBuf,
     pub(crate) go_main_dir: PathBuf,
     pub(crate) go_main_file: PathBuf,
     pub(crate) go_main_impl_file: PathBuf,
@@ -82,16 +276,118 @@ pub(crate) struct WorkConfig {
     pub(crate) go_clib_header: PathBuf,
     pub(crate) has_goffi: bool,
     pub(crate) has_rustffi: bool,
+    /// Rust trait names of the services implemented by rust, e.g. `RustFfi`
+    pub(crate) rustffi_services: Vec<String>,
+    /// Rust trait names of the services implemented by go, e.g. `GoFfi`
+    pub(crate) goffi_services: Vec<String>,
+    /// IDL-declared error message names, service name -> method name -> error name
+    pub(crate) declared_errors: HashMap<String, HashMap<String, String>>,
+    /// The declared errors whose Go `Error()` method is generated by fcplug
+    pub(crate) go_error_impls: BTreeSet<String>,
     pub(crate) rust_mod_impl_name: String,
+    /// Whether the implementation type is specified by `Config::rust_impl_type`
+    pub(crate) has_rust_impl_type: bool,
+    pub(crate) go_object_path: Option<GoObjectPath>,
+    pub(crate) go_host: bool,
+    /// Whether the Go code is built into a C shared library, which contains the Rust C library
+    pub(crate) use_goffi_cdylib: bool,
+    pub(crate) typed_rustffi: bool,
+    pub(crate) go_log_bridge: bool,
+    pub(crate) go_offline: bool,
+    /// Whether the module is used by the go.work found by the Go commands,
+    /// so the workspace modules are resolved without `go mod tidy`
+    pub(crate) go_workspace: bool,
+    /// Whether the found go.work does not use the module, so the Go commands run with `GOWORK=off`
+    pub(crate) go_work_off: bool,
+    /// Whether the modules are vendored in the `vendor` directory of the crate
+    pub(crate) go_vendor: bool,
+    /// Whether the `goffi-mock` feature of the target crate is enabled,
+    /// which replaces the Go link by the Rust mock and skips the Go toolchain.
+    pub(crate) goffi_mock: bool,
+    /// The digest of the generator version, the config and the input files
     pub(crate) fingerprint: String,
+    /// The files read by the generation and the Go build, which excludes the generated Go files
+    pub(crate) input_files: Vec<PathBuf>,
     pub(crate) fingerprint_path: PathBuf,
+    /// Whether the code is generated into memory, which neither writes the crate files nor runs the Go tools,
+    /// and `target_out_dir` is the scratch directory of pilota and cbindgen.
+    pub(crate) in_memory: bool,
+    /// The owner of the scratch directory in the in-memory mode
+    scratch_dir: Option<Arc<ScratchDir>>,
+}
+
+/// The scratch directory of the in-memory generation, which is removed with its last owner,
+/// so it does not leak on the errors
+#[derive(Debug)]
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    fn new(pkg_name: &str) -> Result<ScratchDir, FcplugError> {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let dir = env::temp_dir().join(format!("fcplug-{pkg_name}-{}-{nanos}", std::process::id()));
+        fs::create_dir_all(&dir).with_path(&dir)?;
+        Ok(ScratchDir(dir))
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
 }
 
 impl WorkConfig {
-    pub(crate) fn new(config: Config) -> WorkConfig {
-        let mut c = WorkConfig::default();
-        c.config = config;
-        c.rust_mod_impl_name = "FfiImpl".to_string();
+    pub(crate) fn new(config: Config, in_memory: bool) -> Result<WorkConfig, FcplugError> {
+        let mut c = WorkConfig {
+            config,
+            in_memory,
+            ..Default::default()
+        };
+        let crate_dir = match &c.config.target_crate_dir {
+            Some(dir) => dir.clone(),
+            None => PathBuf::from(cargo_env("CARGO_MANIFEST_DIR")?),
+        };
+        c.config.apply_overrides(&crate_dir)?;
+        c.target = c
+            .config
+            .target
+            .clone()
+            .or_else(|| env::var("TARGET").ok())
+            .unwrap_or_default();
+        c.has_rust_impl_type = c.config.rust_impl_type.is_some();
+        c.rust_mod_impl_name = c
+            .config
+            .rust_impl_type
+            .as_ref()
+            .map_or("FfiImpl", |v| v.0.as_str())
+            .to_string();
+        c.go_object_path = c.config.go_object_path.clone();
+        c.go_host = c.config.go_host;
+        c.go_offline = c.config.go_offline;
+        if let Some(require) = c.config.go_requires.iter().find(|require| {
+            !require
+                .split_once('@')
+                .is_some_and(|(_, v)| v.starts_with('v'))
+        }) {
+            return Err(FcplugError::Config(format!(
+                "the Go requirement '{require}' should be 'module@version', e.g. 'github.com/bytedance/sonic@v1.9.1'"
+            )));
+        }
+        c.use_goffi_cdylib = c.config.use_goffi_cdylib;
+        c.typed_rustffi = c.config.typed_rustffi;
+        c.go_log_bridge = c.config.go_log_bridge;
+        c.goffi_mock = env::var_os("CARGO_FEATURE_GOFFI_MOCK").is_some();
+        if let Some(obj) = &c.go_object_path {
+            if obj.package_name().is_none() {
+                return Err(FcplugError::Config(format!(
+                    "GoObjectPath.object_ident '{}' should be qualified by the package name, e.g. 'mypkg.Abc'",
+                    obj.object_ident
+                )));
+            }
+        }
         c.go_buildmode = if c.config.use_goffi_cdylib {
             "c-shared"
         } else {
@@ -103,16 +399,33 @@ impl WorkConfig {
             "static"
         };
         c.idl_file = c.config.idl_file.clone();
-        c.idl_include_dir = c.idl_file.parent().unwrap().to_path_buf();
-        c.idl_type = Self::new_idl_type(&c.idl_file);
-        c.rust_clib_name_base = env::var("CARGO_PKG_NAME").unwrap().replace("-", "_");
+        c.idl_include_dir = c.idl_file.parent().unwrap_or(Path::new("")).to_path_buf();
+        c.idl_type = Self::new_idl_type(&c.idl_file)?;
+        c.pkg_dir = Self::new_pkg_dir(&c.config.target_crate_dir, in_memory)?;
+        c.gomod_file = c.pkg_dir.join("go.mod");
+        c.pkg_name = Self::new_pkg_name(&c.pkg_dir)?;
+        c.rust_clib_name_base = c.new_rust_clib_name_base()?;
         c.go_clib_name_base = "go_".to_string() + &c.rust_clib_name_base;
-        c.target_out_dir = Self::new_target_out_dir();
+        c.target_out_dir = if in_memory {
+            let scratch_dir = ScratchDir::new(&c.pkg_name)?;
+            let dir = scratch_dir.0.clone();
+            c.scratch_dir = Some(Arc::new(scratch_dir));
+            dir
+        } else {
+            let dir = c.new_artifact_dir()?;
+            // pilota writes the tidy IDL into it before the C libraries are built
+            fs::create_dir_all(&dir).with_path(&dir)?;
+            dir
+        };
         c.clib_gen_dir = c.target_out_dir.clone();
+        c.clib_link_dir = if in_memory {
+            // the output should not depend on the scratch directory
+            c.new_artifact_dir()
+                .unwrap_or_else(|_| c.pkg_dir.join("target").join(BUILD_MODE))
+        } else {
+            c.clib_gen_dir.clone()
+        };
         c.fingerprint_path = c.clib_gen_dir.join("fcplug.fingerprint");
-        c.pkg_dir = Self::new_pkg_dir(&c.config.target_crate_dir);
-        c.gomod_file = c.pkg_dir.join("go.mod");
-        c.pkg_name = Self::new_pkg_name(&c.pkg_dir);
         c.gomod_name = c.pkg_name.clone();
         c.gomod_path = format!(
             "{}/{}",
@@ -125,126 +438,179 @@ impl WorkConfig {
         c.rust_mod_gen_file = c.rust_mod_dir.join(format!("{file_name_base}.rs"));
         c.rust_mod_impl_file = c.rust_mod_dir.join("mod.rs");
         c.go_main_dir = c.pkg_dir.join(CGOBIN);
-        let go_file_suffix = match get_go_os_arch_from_env() {
+        let go_file_suffix = match get_go_os_arch(&c.target) {
             Ok((os, arch)) => {
                 format!("_{}_{}", os.as_ref(), arch.as_ref())
             }
             Err(err) => {
-                println!("cargo:warning={}", err);
+                if !in_memory {
+                    println!("cargo:warning={}", err);
+                }
                 String::new()
             }
         };
         c.go_lib_file = c
             .pkg_dir
             .join(format!("{file_name_base}{go_file_suffix}.go"));
+        c.go_fake_file = c.pkg_dir.join(format!("{file_name_base}_fake.go"));
+        c.go_link_file = c
+            .pkg_dir
+            .join(format!("{file_name_base}_link{go_file_suffix}.go"));
         c.go_main_file = c
             .go_main_dir
             .join(format!("clib_goffi_gen{go_file_suffix}.go"));
         c.go_main_impl_file = c.go_main_dir.join("clib_goffi_impl.go");
         c.set_rust_clib_paths();
         c.set_go_clib_paths();
-        c.check_go_mod_path();
-        c.set_fingerprint();
-        c.clean_idl();
-        let _ = c
-            .init_files()
-            .inspect_err(|e| exit_with_warning(-2, format!("failed init files to {e:?}")));
-        c.git_add();
-        c
-    }
-
-    fn new_idl_type(idl_file: &PathBuf) -> IdlType {
-        match idl_file.extension().unwrap().to_str().unwrap() {
-            "thrift" => match GEN_MODE {
-                GenMode::Codec => IdlType::Thrift,
-                GenMode::NoCodec => IdlType::ThriftNoCodec,
-            },
-            "proto" => match GEN_MODE {
-                GenMode::Codec => IdlType::Proto,
-                GenMode::NoCodec => IdlType::ProtoNoCodec,
-            },
-            x => {
-                println!("cargo:warning=unsupported idl file extension: {x}");
-                std::process::exit(404);
-            }
+        if in_memory {
+            c.clean_idl()?;
+            return Ok(c);
         }
+        c.check_go_mod_path()?;
+        c.set_go_modes(c.find_go_work());
+        c.input_files = c.new_input_files();
+        c.fingerprint = c.new_fingerprint(&c.input_files);
+        c.clean_idl()?;
+        c.init_files()?;
+        c.git_add()?;
+        Ok(c)
     }
 
-    fn new_target_out_dir() -> PathBuf {
-        let target_dir = env::var("CARGO_TARGET_DIR").map_or_else(
-            |_| {
-                PathBuf::from(env::var("CARGO_WORKSPACE_DIR").unwrap_or_else(|_| {
-                    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap_or_default());
-                    let mdir = env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
-                    if out_dir.starts_with(&mdir) {
-                        mdir
-                    } else {
-                        let mut p = PathBuf::new();
-                        let mut coms = Vec::new();
-                        let mut start = false;
-                        for x in out_dir.components().rev() {
-                            if !start && x.as_os_str() == "target" {
-                                start = true;
-                                continue;
-                            }
-                            if start {
-                                coms.insert(0, x);
-                            }
-                        }
-                        for x in coms {
-                            p = p.join(x);
-                        }
-                        p.to_str().unwrap().to_string()
-                    }
-                }))
-                .join("target")
+    fn new_idl_type(idl_file: &PathBuf) -> Result<IdlType, FcplugError> {
+        Ok(
+            match idl_file
+                .extension()
+                .and_then(|x| x.to_str())
+                .unwrap_or_default()
+            {
+                "thrift" => match GEN_MODE {
+                    GenMode::Codec => IdlType::Thrift,
+                    GenMode::NoCodec => IdlType::ThriftNoCodec,
+                },
+                "proto" => match GEN_MODE {
+                    GenMode::Codec => IdlType::Proto,
+                    GenMode::NoCodec => IdlType::ProtoNoCodec,
+                },
+                x => {
+                    return Err(FcplugError::Config(format!(
+                        "unsupported idl file extension: '{x}'"
+                    )))
+                }
             },
-            PathBuf::from,
-        );
-        let full_target_dir = target_dir.join(env::var("TARGET").unwrap());
-        if full_target_dir.is_dir()
-            && PathBuf::from(env::var("OUT_DIR").unwrap())
-                .canonicalize()
-                .unwrap()
-                .starts_with(full_target_dir.canonicalize().unwrap())
-        {
-            full_target_dir
-        } else {
-            target_dir
+        )
+    }
+
+    /// The directory of the crate artifacts, i.e. `<target-dir>/[<triple>/]<profile>`, which is `Config::artifact_dir`
+    /// or the ancestor of `OUT_DIR` at `<profile>/build/<package>-<hash>/out` in the cargo layout,
+    /// so the custom profiles, `--target` and `--target-dir` are all located as cargo does.
+    fn new_artifact_dir(&self) -> Result<PathBuf, FcplugError> {
+        match &self.config.artifact_dir {
+            Some(artifact_dir) => Ok(artifact_dir.clone()),
+            None => Self::artifact_dir_of(Path::new(&cargo_env("OUT_DIR")?)),
+        }
+    }
+
+    /// The artifact directory of the `OUT_DIR`
+    fn artifact_dir_of(out_dir: &Path) -> Result<PathBuf, FcplugError> {
+        let out_dir = out_dir.canonicalize().with_path(out_dir)?;
+        let mut ancestors = out_dir.ancestors();
+        match (
+            ancestors.next().and_then(Path::file_name),
+            ancestors.nth(1).and_then(Path::file_name),
+            ancestors.next(),
+        ) {
+            (Some(out), Some(build), Some(artifact_dir)) if out == "out" && build == "build" => {
+                Ok(artifact_dir.to_path_buf())
+            }
+            _ => Err(FcplugError::Config(format!(
+                "failed to locate the artifact directory, OUT_DIR '{}' is not in '<profile>/build/<package>/out'",
+                out_dir.display()
+            ))),
         }
-        .join(BUILD_MODE)
-        .canonicalize()
-        .unwrap()
     }
 
-    fn new_pkg_dir(target_crate_dir: &Option<PathBuf>) -> PathBuf {
-        if let Some(target_crate_dir) = target_crate_dir {
-            target_crate_dir.clone().canonicalize().unwrap()
+    fn new_pkg_dir(
+        target_crate_dir: &Option<PathBuf>,
+        in_memory: bool,
+    ) -> Result<PathBuf, FcplugError> {
+        let pkg_dir = if let Some(target_crate_dir) = target_crate_dir {
+            target_crate_dir.clone()
         } else {
-            PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap())
-                .canonicalize()
-                .unwrap()
+            PathBuf::from(cargo_env("CARGO_MANIFEST_DIR")?)
+        };
+        if in_memory {
+            // the crate directory only names the generated files, so it need not exist
+            return Ok(pkg_dir.canonicalize().unwrap_or(pkg_dir));
         }
+        pkg_dir.canonicalize().with_path(&pkg_dir)
     }
 
-    fn new_pkg_name(pkg_dir: &PathBuf) -> String {
-        pkg_dir
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap()
+    fn new_pkg_name(pkg_dir: &PathBuf) -> Result<String, FcplugError> {
+        let Some(dir_name) = pkg_dir.file_name().and_then(|name| name.to_str()) else {
+            return Err(FcplugError::Config(format!(
+                "the crate directory name should be UTF-8, dir={}",
+                pkg_dir.display()
+            )));
+        };
+        Ok(dir_name
             .replace(".", "_")
             .replace("-", "_")
             .trim_start_matches("_")
             .to_string()
             .trim_end_matches("_")
-            .to_string()
+            .to_string())
+    }
+
+    /// The library name of the crate, which is `[lib] name` or the package name in Cargo.toml
+    fn new_rust_clib_name_base(&self) -> Result<String, FcplugError> {
+        let manifest_file = self.pkg_dir.join("Cargo.toml");
+        let Some(manifest) = read_manifest(&self.pkg_dir)? else {
+            if self.in_memory {
+                // the crate directory only names the generated files
+                return Ok(self.pkg_name.clone());
+            }
+            return Err(FcplugError::Config(format!(
+                "{} does not exist",
+                manifest_file.display()
+            )));
+        };
+        let lib = manifest.get("lib");
+        let Some(name) = lib
+            .and_then(|lib| lib.get("name"))
+            .or_else(|| {
+                manifest
+                    .get("package")
+                    .and_then(|package| package.get("name"))
+            })
+            .and_then(|name| name.as_str())
+        else {
+            return Err(FcplugError::Config(format!(
+                "no [lib] name or [package] name in {}",
+                manifest_file.display()
+            )));
+        };
+        // the Go code links the C static library, except that the GoFFI is mocked
+        let has_staticlib = lib
+            .and_then(|lib| lib.get("crate-type").or_else(|| lib.get("crate_type")))
+            .and_then(|crate_types| crate_types.as_array())
+            .is_some_and(|crate_types| crate_types.iter().any(|t| t.as_str() == Some("staticlib")));
+        if !self.in_memory && !self.goffi_mock && !has_staticlib {
+            return Err(FcplugError::Config(format!(
+                "'staticlib' should be in [lib] crate-type of {}",
+                manifest_file.display()
+            )));
+        }
+        Ok(name.replace("-", "_"))
     }
 
     fn set_rust_clib_paths(&mut self) {
-        self.rust_clib_file = self
-            .clib_gen_dir
-            .join(format!("lib{}.a", self.rust_clib_name_base));
+        // the MSVC toolchain names the C static library `{name}.lib`
+        let rust_clib_file_name = if self.target.ends_with("-msvc") {
+            format!("{}.lib", self.rust_clib_name_base)
+        } else {
+            format!("lib{}.a", self.rust_clib_name_base)
+        };
+        self.rust_clib_file = self.clib_gen_dir.join(rust_clib_file_name);
         self.rust_clib_header = self
             .clib_gen_dir
             .join(format!("{}.h", self.rust_clib_name_base));
@@ -265,72 +631,153 @@ impl WorkConfig {
             .join(format!("{}.h", self.go_clib_name_base));
     }
 
-    fn git_add(&self) {
+    fn git_add(&self) -> Result<(), FcplugError> {
         if !self.config.add_clib_to_git {
-            return;
+            return Ok(());
         }
-        deal_output(
-            Command::new("git")
-                .arg("add")
-                .arg("-f")
-                .args([
-                    self.go_clib_header.display().to_string(),
-                    self.go_clib_file.display().to_string(),
-                    self.rust_clib_header.display().to_string(),
-                    self.rust_clib_file.display().to_string(),
-                    self.fingerprint_path.display().to_string(),
-                ])
-                .output(),
-        );
+        run_command(
+            Command::new("git").arg("add").arg("-f").args(
+                [
+                    &self.go_clib_header,
+                    &self.go_clib_file,
+                    &self.rust_clib_header,
+                    &self.rust_clib_file,
+                    &self.fingerprint_path,
+                ]
+                .into_iter()
+                .filter(|f| f.exists()),
+            ),
+        )
     }
 
-    fn set_fingerprint(&mut self) {
-        self.fingerprint = walkdir::WalkDir::new(&self.pkg_dir)
-            .into_iter()
-            .filter_map(|entry| entry.ok())
-            .filter(|entry| {
-                if entry
-                    .path()
-                    .extension()
-                    .map(|ext| ext == "go" || ext == "rs" || ext == "toml" || ext == "proto")
-                    .unwrap_or_default()
-                {
-                    if let Ok(metadata) = entry.metadata() {
-                        return metadata.is_file();
-                    }
-                };
-                return false;
-            })
-            .fold(String::new(), |acc, m| {
-                let digest = md5::compute(fs::read(m.path()).unwrap());
-                format!("{acc}|{digest:x}")
-            });
+    /// Whether the Rust C library is built from the current RustFFI,
+    /// which is not empty and not older than the Rust C header generated with it.
+    pub(crate) fn is_rust_clib_built(&self) -> bool {
+        let modified = |f: &Path| fs::metadata(f).and_then(|m| m.modified()).ok();
+        match fs::metadata(&self.rust_clib_file) {
+            Ok(meta) if meta.len() > 0 => {
+                modified(&self.rust_clib_file) >= modified(&self.rust_clib_header)
+            }
+            _ => false,
+        }
+    }
+
+    fn new_input_files(&self) -> Vec<PathBuf> {
+        let mut go_dirs = vec![self.pkg_dir.clone()];
+        if !self.go_host {
+            go_dirs.push(self.go_main_dir.clone());
+        }
+        // the package of the Go object in the module
+        if let Some(rest) = self
+            .go_object_path
+            .as_ref()
+            .and_then(|obj| obj.import.strip_prefix(&self.gomod_path))
+        {
+            go_dirs.push(self.pkg_dir.join(rest.trim_start_matches('/')));
+        }
+        // `idl_file` is replaced by the tidy one in the target directory
+        let mut input_files = vec![
+            self.config.idl_file.clone(),
+            self.pkg_dir.join("Cargo.toml"),
+            self.gomod_file.clone(),
+            self.pkg_dir.join("go.sum"),
+        ];
+        for dir in go_dirs {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            input_files.extend(
+                entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| {
+                        path.extension().is_some_and(|ext| ext == "go")
+                            && path.is_file()
+                            && !is_generated_go_file(path)
+                    }),
+            );
+        }
+        if self.go_workspace {
+            input_files.extend(self.find_go_work());
+        }
+        if self.go_vendor {
+            input_files.push(self.pkg_dir.join("vendor").join("modules.txt"));
+        }
+        // the C shared library contains the Rust C library
+        if self.use_goffi_cdylib {
+            input_files.push(self.rust_clib_file.clone());
+        }
+        input_files.sort();
+        input_files.dedup();
+        input_files
+    }
+
+    fn new_fingerprint(&self, input_files: &[PathBuf]) -> String {
+        let mut ctx = md5::Context::new();
+        ctx.consume(env!("CARGO_PKG_VERSION"));
+        ctx.consume(format!(
+            "|{:?}|{}|{}",
+            self.config, self.target, self.goffi_mock
+        ));
+        for file in input_files {
+            ctx.consume(format!("|{}|", file.display()));
+            // the missing file is an input too, e.g. go.sum
+            if let Ok(content) = fs::read(file) {
+                ctx.consume(md5::compute(content).0);
+            }
+        }
+        format!("{:x}", ctx.compute())
     }
-    pub(crate) fn update_fingerprint(&self) -> bool {
-        if fs::read_to_string(&self.fingerprint_path).unwrap_or_default() != self.fingerprint {
-            fs::write(&self.fingerprint_path, self.fingerprint.as_str()).unwrap();
-            return true;
+
+    /// Whether the fingerprint is the one of the last generation
+    pub(crate) fn is_fingerprint_unchanged(&self) -> bool {
+        fs::read_to_string(&self.fingerprint_path).is_ok_and(|fp| fp == self.fingerprint)
+    }
+
+    /// Save the fingerprint after the generation, which may change the inputs, e.g. `go.sum` by `go mod tidy`
+    pub(crate) fn save_fingerprint(&self) -> Result<(), FcplugError> {
+        let fingerprint = self.new_fingerprint(&self.new_input_files());
+        fs::write(&self.fingerprint_path, fingerprint).with_path(&self.fingerprint_path)
+    }
+
+    /// Run pilota, which prints the cargo directives to the stdout unconditionally.
+    /// Its `rerun-if-changed` directives are dropped, since they watch the tidy IDL in the target directory,
+    /// and the input files are watched by `rerun_if_changed` instead.
+    /// The other directives are kept in the build, and only the warnings are reported to the stderr in the in-memory mode.
+    pub(crate) fn run_pilota<R>(&self, f: impl FnOnce() -> R) -> Result<R, FcplugError> {
+        let stdout_file = self.target_out_dir.join("fcplug.pilota.stdout");
+        let (ret, printed) = redirect_stdout(&stdout_file, f)?;
+        let _ = fs::remove_file(&stdout_file);
+        for line in printed
+            .lines()
+            .filter(|line| !line.starts_with("cargo:rerun-if-changed="))
+        {
+            if !self.in_memory {
+                println!("{line}");
+            } else if let Some(msg) = line.strip_prefix("cargo:warning=") {
+                eprintln!("warning: {msg}");
+            }
         }
-        return false;
+        Ok(ret)
     }
 
-    fn clean_idl(&mut self) {
+    fn clean_idl(&mut self) -> Result<(), FcplugError> {
         let mut ret = match self.idl_type {
             IdlType::Proto | IdlType::ProtoNoCodec => {
                 let mut parser = ProtobufParser::default();
                 Parser::include_dirs(&mut parser, vec![self.idl_include_dir.clone()]);
                 Parser::input(&mut parser, &self.idl_file);
-                let (descs, ret) = parser.parse_and_typecheck();
+                let (descs, ret) = self.run_pilota(|| parser.parse_and_typecheck())?;
                 for desc in descs {
                     if desc.package.is_some() {
-                        exit_with_warning(-1, "IDL-Check: The 'package' should not be configured");
+                        return Err(FcplugError::IdlCheck(
+                            "The 'package' should not be configured".to_string(),
+                        ));
                     }
                     if let Some(opt) = desc.options.as_ref() {
                         if opt.go_package.is_some() {
-                            exit_with_warning(
-                                -1,
-                                "IDL-Check: The 'option go_package' should not be configured",
-                            );
+                            return Err(FcplugError::IdlCheck(
+                                "The 'option go_package' should not be configured".to_string(),
+                            ));
                         }
                     }
                 }
@@ -340,86 +787,190 @@ impl WorkConfig {
                 let mut parser = ThriftParser::default();
                 Parser::include_dirs(&mut parser, vec![self.idl_include_dir.clone()]);
                 Parser::input(&mut parser, &self.idl_file);
-                let ret = parser.parse();
-                ret
+                self.run_pilota(|| parser.parse())?
             }
         };
 
-        let file = ret.files.pop().unwrap();
+        let Some(file) = ret.files.pop() else {
+            return Err(FcplugError::IdlCheck(format!(
+                "No file is parsed from '{}'.",
+                self.idl_file.display()
+            )));
+        };
         if !file.uses.is_empty() {
-            match self.idl_type {
-                IdlType::Proto | IdlType::ProtoNoCodec => {
-                    exit_with_warning(-1, "IDL-Check: Does not support Protobuf 'import'.")
-                }
-                IdlType::Thrift | IdlType::ThriftNoCodec => {
-                    exit_with_warning(-1, "IDL-Check: Does not support Thrift 'include'.")
+            return Err(FcplugError::IdlCheck(
+                match self.idl_type {
+                    IdlType::Proto | IdlType::ProtoNoCodec => "Does not support Protobuf 'import'.",
+                    IdlType::Thrift | IdlType::ThriftNoCodec => {
+                        "Does not support Thrift 'include'."
+                    }
                 }
-            }
+                .to_string(),
+            ));
         }
 
+        let mut message_names = Vec::new();
+        let mut service_methods = HashMap::new();
+        // the GoFFI method -> the service, whose calls are all implemented on the same type
+        let mut goffi_methods = HashMap::new();
         for item in &file.items {
             match &item.kind {
-                ItemKind::Message(_) => {}
+                ItemKind::Message(message_item) => {
+                    message_names.push(message_item.name.to_string())
+                }
                 ItemKind::Service(service_item) => {
-                    match service_item.name.to_lowercase().as_str() {
-                        "goffi" => self.has_goffi = true,
-                        "rustffi" => self.has_rustffi = true,
-                        _ => exit_with_warning(
-                            -1,
-                            "IDL-Check: Protobuf Service name can only be: 'GoFFI', 'RustFFI'.",
-                        ),
+                    service_methods.insert(
+                        service_item.name.to_string(),
+                        service_item
+                            .methods
+                            .iter()
+                            .map(|m| m.name.to_string())
+                            .collect(),
+                    );
+                    let trait_name = (&**service_item.name).trait_ident().to_string();
+                    match ServiceType::from_name(&service_item.name) {
+                        Some(ServiceType::GoFfi) => {
+                            for method in &service_item.methods {
+                                let method_name = (&**method.name).fn_ident().to_string();
+                                if let Some(other) =
+                                    goffi_methods.insert(method_name.clone(), &service_item.name)
+                                {
+                                    return Err(FcplugError::Codegen(format!(
+                                        "GoFFI services '{other}' and '{}' both have the method '{method_name}', \
+                                        whose calls conflict on `{}`, rename one of them.",
+                                        service_item.name, self.rust_mod_impl_name
+                                    )));
+                                }
+                            }
+                            self.has_goffi = true;
+                            self.goffi_services.push(trait_name);
+                        }
+                        Some(ServiceType::RustFfi) => {
+                            self.has_rustffi = true;
+                            self.rustffi_services.push(trait_name);
+                        }
+                        None => {
+                            return Err(FcplugError::IdlCheck(format!(
+                                "Service name '{}' should end with 'GoFFI' or 'RustFFI'.",
+                                service_item.name
+                            )))
+                        }
                     }
                 }
-                _ => match self.idl_type {
-                    IdlType::Proto | IdlType::ProtoNoCodec => exit_with_warning(
-                        -1,
-                        format!(
-                            "IDL-Check: Protobuf Item '{}' not supported.",
-                            format!("{:?}", item)
-                                .trim_start_matches("Item { kind: ")
-                                .split_once("(")
-                                .unwrap()
-                                .0
-                                .to_lowercase()
-                        ),
-                    ),
-                    IdlType::Thrift | IdlType::ThriftNoCodec => exit_with_warning(
-                        -1,
-                        format!(
-                            "Thrift Item '{}' not supported.",
-                            format!("{:?}", item)
-                                .split_once("(")
-                                .unwrap()
-                                .0
-                                .to_lowercase()
-                        ),
-                    ),
-                },
+                _ => {
+                    let item = format!("{:?}", item);
+                    let kind = item
+                        .trim_start_matches("Item { kind: ")
+                        .split_once("(")
+                        .map_or(item.as_str(), |(kind, _)| kind)
+                        .to_lowercase();
+                    return Err(FcplugError::IdlCheck(match self.idl_type {
+                        IdlType::Proto | IdlType::ProtoNoCodec => {
+                            format!("Protobuf Item '{kind}' not supported.")
+                        }
+                        IdlType::Thrift | IdlType::ThriftNoCodec => {
+                            format!("Thrift Item '{kind}' not supported.")
+                        }
+                    }));
+                }
             }
         }
+        self.parse_declared_errors(&message_names, &service_methods)?;
         self.tidy_idl()
     }
 
-    fn tidy_idl(&mut self) {
-        let go_mod_name = &self.gomod_name;
-        match self.idl_type {
-            IdlType::Proto | IdlType::ProtoNoCodec => {
-                self.idl_file = self.target_out_dir.join(go_mod_name.clone() + ".proto");
-                fs::write(
-                    &self.idl_file,
-                    fs::read_to_string(&self.config.idl_file).unwrap()
-                        + &format!(
-                            "\noption go_package=\"./;{go_mod_name}\";\npackage {go_mod_name};\n"
-                        ),
-                )
-                .unwrap();
+    /// Parse the error message declared for each method:
+    /// the thrift `throws (1: MyError err)` clause, or the protobuf `// @throws(MyError)` comment annotation
+    /// placed on the line above the `rpc` line or at the end of it.
+    fn parse_declared_errors(
+        &mut self,
+        message_names: &[String],
+        service_methods: &HashMap<String, Vec<String>>,
+    ) -> Result<(), FcplugError> {
+        let idl = fs::read_to_string(&self.config.idl_file).with_path(&self.config.idl_file)?;
+        if let IdlType::Thrift | IdlType::ThriftNoCodec = self.idl_type {
+            if idl.contains("@throws") {
+                return Err(FcplugError::IdlCheck(
+                    "Thrift declares the errors by the 'throws' clause, not by the '@throws' annotation."
+                        .to_string(),
+                ));
             }
+        }
+        let (declared_errors, struct_names) = match self.idl_type {
+            IdlType::Proto | IdlType::ProtoNoCodec => (parse_proto_declared_errors(&idl)?, None),
             IdlType::Thrift | IdlType::ThriftNoCodec => {
-                self.idl_file = self.target_out_dir.join(go_mod_name.clone() + ".thrift");
-                fs::copy(&self.config.idl_file, &self.idl_file).unwrap();
+                let file = parse_thrift(&idl)?;
+                let struct_names = file
+                    .items
+                    .iter()
+                    .filter_map(|item| match item {
+                        thrift_parser::Item::Struct(s) => Some(s.name.as_str().to_string()),
+                        _ => None,
+                    })
+                    .collect::<Vec<String>>();
+                (parse_thrift_declared_errors(&file)?, Some(struct_names))
             }
         };
-        self.idl_include_dir = self.idl_file.parent().unwrap().to_path_buf();
+        for (service_name, methods) in &declared_errors {
+            for (method, error) in methods {
+                if !service_methods
+                    .get(service_name)
+                    .is_some_and(|m| m.contains(method))
+                {
+                    return Err(FcplugError::IdlCheck(format!(
+                        "The declared error '{error}' is placed on '{service_name}.{method}', which is not a method in the IDL."
+                    )));
+                }
+                if !message_names.contains(error) {
+                    return Err(FcplugError::IdlCheck(format!(
+                        "The declared error '{error}' of '{service_name}.{method}' should be a message defined in the IDL."
+                    )));
+                }
+                // protoc generates no `Error()` method, and thriftgo generates it for the exceptions only
+                if struct_names
+                    .as_ref()
+                    .is_none_or(|names| names.contains(error))
+                {
+                    self.go_error_impls.insert(error.clone());
+                }
+            }
+        }
+        self.declared_errors = declared_errors;
+        Ok(())
+    }
+
+    pub(crate) fn declared_error(&self, service_name: &str, method_name: &str) -> Option<&String> {
+        self.declared_errors
+            .get(service_name)
+            .and_then(|methods| methods.get(method_name))
+    }
+
+    fn tidy_idl(&mut self) -> Result<(), FcplugError> {
+        let go_mod_name = &self.gomod_name;
+        let idl = fs::read_to_string(&self.config.idl_file).with_path(&self.config.idl_file)?;
+        let (idl_file, idl) = match self.idl_type {
+            IdlType::Proto | IdlType::ProtoNoCodec => (
+                self.target_out_dir.join(go_mod_name.clone() + ".proto"),
+                idl + &format!(
+                    "\noption go_package=\"./;{go_mod_name}\";\npackage {go_mod_name};\n"
+                ),
+            ),
+            IdlType::Thrift | IdlType::ThriftNoCodec => {
+                // the declared errors are handled by fcplug, not by the thrift codegen
+                let tidied = strip_thrift_throws(&idl)?;
+                (
+                    self.target_out_dir.join(go_mod_name.clone() + ".thrift"),
+                    tidied,
+                )
+            }
+        };
+        // keep the modified time of the unchanged file
+        if fs::read_to_string(&idl_file).ok().as_ref() != Some(&idl) {
+            fs::write(&idl_file, idl).with_path(&idl_file)?;
+        }
+        self.idl_file = idl_file;
+        self.idl_include_dir = self.target_out_dir.clone();
+        Ok(())
     }
 
     // rustc-link-lib=[KIND=]NAME indicates that the specified value is a library name and should be passed to the compiler as a -l flag. The optional KIND can be one of static, dylib (the default), or framework, see rustc --help for more details.
@@ -427,14 +978,24 @@ impl WorkConfig {
     // rustc-link-search=[KIND=]PATH indicates the specified value is a library search path and should be passed to the compiler as a -L flag. The optional KIND can be one of dependency, crate, native, framework or all (the default), see rustc --help for more details.
     //
     // rustc-flags=FLAGS is a set of flags passed to the compiler, only -l and -L flags are supported.
+    /// The qualifier of the lib package used by the GoFFI code,
+    /// which is empty in the Go host mode since the code lives in the lib package.
+    pub(crate) fn goffi_mod_prefix(&self) -> String {
+        if self.go_host {
+            String::new()
+        } else {
+            format!("{}.", self.gomod_name)
+        }
+    }
+
     pub(crate) fn rustc_link(&self) {
         println!(
             "cargo:rustc-link-search=native={}",
-            self.clib_gen_dir.to_str().unwrap()
+            self.clib_gen_dir.display()
         );
         println!(
             "cargo:rustc-link-search=dependency={}",
-            self.clib_gen_dir.to_str().unwrap()
+            self.clib_gen_dir.display()
         );
         println!(
             "cargo:rustc-link-lib={}={}",
@@ -442,45 +1003,59 @@ impl WorkConfig {
         );
     }
 
+    /// Declare the `goffi-mock` feature used by the generated code,
+    /// so that it is not reported as unexpected when the target crate does not define it.
+    pub(crate) fn rustc_check_cfg(&self) {
+        println!("cargo:rustc-check-cfg=cfg(feature, values(\"goffi-mock\"))");
+    }
+
+    /// Watch the inputs owned by the user, i.e. the IDL, the manifests and the hand-written Go files,
+    /// but neither the directories nor the generated `*_gen*.go` files, which the generation rewrites.
+    /// The Rust C library linked into the C shared library is watched too, which the Go build reads.
     pub(crate) fn rerun_if_changed(&self) {
-        println!("cargo:rerun-if-changed={}", self.pkg_dir.to_str().unwrap());
-        println!(
-            "cargo:rerun-if-changed={}",
-            self.target_out_dir.to_str().unwrap()
-        );
+        for (_, env_key) in OVERRIDES {
+            println!("cargo:rerun-if-env-changed={env_key}");
+        }
+        for file in &self.input_files {
+            println!("cargo:rerun-if-changed={}", file.display());
+        }
     }
 
-    fn check_go_mod_path(&self) {
+    fn check_go_mod_path(&self) -> Result<(), FcplugError> {
         let f = &self.gomod_file;
         if f.exists() {
             if !f.is_file() {
-                exit_with_warning(
-                    253,
-                    format!("go mod file {} does not exist", f.to_str().unwrap()),
-                );
+                return Err(FcplugError::Config(format!(
+                    "go mod file {} does not exist",
+                    f.display()
+                )));
             } else {
                 let p = &self.gomod_path;
-                let s = fs::read_to_string(f).unwrap();
+                let s = fs::read_to_string(f).with_path(f)?;
                 if !s.contains(&format!("module {p}\n"))
                     && !s.contains(&format!("module {p}\t"))
                     && !s.contains(&format!("module {p}\r"))
                     && !s.contains(&format!("module {p} "))
                 {
-                    exit_with_warning(
-                        253,
-                        format!("go mod path should be {p}, file={}", f.to_str().unwrap()),
-                    );
+                    return Err(FcplugError::Config(format!(
+                        "go mod path should be {p}, file={}",
+                        f.display()
+                    )));
                 }
             }
         }
+        Ok(())
     }
 
-    fn init_files(&self) -> anyhow::Result<()> {
-        fs::create_dir_all(&self.go_main_dir)?;
-        fs::create_dir_all(&self.rust_mod_dir)?;
-        fs::create_dir_all(&self.clib_gen_dir)?;
+    fn init_files(&self) -> Result<(), FcplugError> {
+        if !self.go_host {
+            fs::create_dir_all(&self.go_main_dir).with_path(&self.go_main_dir)?;
+        }
+        fs::create_dir_all(&self.rust_mod_dir).with_path(&self.rust_mod_dir)?;
+        fs::create_dir_all(&self.clib_gen_dir).with_path(&self.clib_gen_dir)?;
+        // the Rust C library is written by cargo after the crate is built,
+        // an empty placeholder would be taken as the built library
         for f in [
-            &self.rust_clib_file,
             &self.rust_clib_header,
             &self.go_clib_file,
             &self.go_clib_header,
@@ -489,29 +1064,1143 @@ impl WorkConfig {
             OpenOptions::new()
                 .write(true)
                 .create(true)
-                .open(&self.clib_gen_dir.join(f))?;
+                .open(&self.clib_gen_dir.join(f))
+                .with_path(f)?;
         }
         Ok(())
     }
 
-    pub(crate) fn go_cmd_path(&self, cmd: &'static str) -> String {
+    /// Build in the workspace of `go_work` if it uses the crate, otherwise with `GOWORK=off`,
+    /// and in the vendor mode if `vendor/modules.txt` exists.
+    /// Neither is used with the GoFFI mock, which runs no Go commands.
+    fn set_go_modes(&mut self, go_work: Option<PathBuf>) {
+        if self.goffi_mock {
+            return;
+        }
+        match go_work {
+            Some(go_work) if self.is_used_by_go_work(&go_work) => self.go_workspace = true,
+            Some(_) => self.go_work_off = true,
+            None => {}
+        }
+        self.go_vendor = self.pkg_dir.join("vendor").join("modules.txt").is_file();
+    }
+
+    /// The go.work of the Go commands in the crate directory, which is `$GOWORK` or found in the ancestors
+    fn find_go_work(&self) -> Option<PathBuf> {
+        self.find_go_work_by(env::var_os("GOWORK"))
+    }
+
+    /// `find_go_work` with the value of `$GOWORK`
+    fn find_go_work_by(&self, gowork: Option<OsString>) -> Option<PathBuf> {
+        match gowork {
+            Some(go_work) if go_work == "off" => None,
+            Some(go_work) if !go_work.is_empty() => Some(PathBuf::from(go_work)),
+            _ => self
+                .pkg_dir
+                .ancestors()
+                .map(|dir| dir.join("go.work"))
+                .find(|go_work| go_work.is_file()),
+        }
+    }
+
+    /// Whether the `use` directives of go.work contain the crate directory
+    fn is_used_by_go_work(&self, go_work: &Path) -> bool {
+        let Ok(content) = fs::read_to_string(go_work) else {
+            return false;
+        };
+        let work_dir = go_work.parent().unwrap_or(Path::new(""));
+        let mut in_use_block = false;
+        for line in content.lines() {
+            let line = line.split("//").next().unwrap_or_default().trim();
+            let dir = if in_use_block {
+                if line == ")" {
+                    in_use_block = false;
+                    continue;
+                }
+                line
+            } else if let Some(dir) = line.strip_prefix("use") {
+                if dir.trim() == "(" {
+                    in_use_block = true;
+                    continue;
+                }
+                dir.trim()
+            } else {
+                continue;
+            };
+            let dir = dir.trim_matches('"');
+            if dir.is_empty() {
+                continue;
+            }
+            let dir = work_dir.join(dir);
+            if dir.canonicalize().is_ok_and(|dir| dir == self.pkg_dir) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// The `go` command run in the crate directory,
+    /// which never downloads the modules in the offline mode.
+    pub(crate) fn go_command(&self) -> Command {
+        let mut cmd = Command::new(self.go_cmd_path("go"));
+        cmd.current_dir(&self.pkg_dir);
+        if self.go_work_off {
+            cmd.env("GOWORK", "off");
+        }
+        if self.go_offline {
+            cmd.env("GOPROXY", "off");
+        }
+        cmd
+    }
+
+    /// The Go module requirements of the generated code, whose versions are pinned by `Config::go_requires`
+    pub(crate) fn go_requires(&self, defaults: &[String]) -> Vec<String> {
+        let module = |require: &str| {
+            require
+                .split_once('@')
+                .map_or(require, |(module, _)| module)
+                .to_string()
+        };
+        let mut requires = defaults
+            .iter()
+            .filter(|default| {
+                !self
+                    .config
+                    .go_requires
+                    .iter()
+                    .any(|require| module(require) == module(default))
+            })
+            .cloned()
+            .collect::<Vec<String>>();
+        requires.extend(self.config.go_requires.iter().cloned());
+        requires
+    }
+
+    pub(crate) fn is_go_require_pinned(&self, go_require: &str) -> bool {
+        self.config
+            .go_requires
+            .iter()
+            .any(|require| require == go_require)
+    }
+
+    pub(crate) fn go_cmd_path(&self, cmd: &'static str) -> PathBuf {
         if let Some(go_root_path) = &self.config.go_root_path {
-            go_root_path
-                .join("bin")
-                .join(cmd)
-                .to_str()
+            go_root_path.join("bin").join(cmd)
+        } else if let Some(go_root_path) = env::var_os("GOROOT") {
+            PathBuf::from(go_root_path).join("bin").join(cmd)
+        } else {
+            PathBuf::from(cmd)
+        }
+    }
+}
+
+/// Whether the Go file is generated, which is named `*_gen*.go` by fcplug, e.g. `echo_gen_linux_amd64.go`,
+/// or follows the convention of the generated code, e.g. `// Code generated by protoc-gen-go. DO NOT EDIT.`
+fn is_generated_go_file(path: &Path) -> bool {
+    let is_gen_name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .is_some_and(|stem| stem.ends_with("_gen") || stem.contains("_gen_"));
+    is_gen_name
+        || fs::read_to_string(path).is_ok_and(|content| {
+            content
+                .lines()
+                .take_while(|line| !line.starts_with("package "))
+                .any(|line| {
+                    line.starts_with("// Code generated ") && line.ends_with(" DO NOT EDIT.")
+                })
+        })
+}
+
+/// Parse Cargo.toml of the crate, which is None if it does not exist
+fn read_manifest(crate_dir: &Path) -> Result<Option<toml::Table>, FcplugError> {
+    let manifest_file = crate_dir.join("Cargo.toml");
+    let Ok(manifest) = fs::read_to_string(&manifest_file) else {
+        return Ok(None);
+    };
+    manifest
+        .parse::<toml::Table>()
+        .map(Some)
+        .map_err(|e| FcplugError::Config(format!("{}: {e}", manifest_file.display())))
+}
+
+/// Parse the thrift IDL into the AST, which keeps the `throws` clauses dropped by pilota.
+fn parse_thrift(idl: &str) -> Result<thrift_parser::File, FcplugError> {
+    match thrift_parser::File::parse(idl) {
+        Ok((rest, file)) if rest.trim().is_empty() => Ok(file),
+        Ok((rest, _)) => Err(FcplugError::IdlCheck(format!(
+            "Failed to parse the thrift IDL near '{}'.",
+            rest.trim().lines().next().unwrap_or_default()
+        ))),
+        Err(e) => Err(FcplugError::IdlCheck(format!(
+            "Failed to parse the thrift IDL: {e}"
+        ))),
+    }
+}
+
+/// The declared errors of the thrift functions by the `throws` clauses, which declare one exception at most.
+fn parse_thrift_declared_errors(
+    file: &thrift_parser::File,
+) -> Result<HashMap<String, HashMap<String, String>>, FcplugError> {
+    let mut declared_errors = HashMap::new();
+    for item in &file.items {
+        let thrift_parser::Item::Service(service) = item else {
+            continue;
+        };
+        let mut methods = HashMap::new();
+        for function in &service.functions {
+            let (service_name, method) = (service.name.as_str(), function.name.as_str());
+            let error = match &function.throws[..] {
+                [] => continue,
+                [field] => match &field.ty.0 {
+                    thrift_parser::Ty::Path(path) if path.segments.len() == 1 => {
+                        path.segments[0].as_str().to_string()
+                    }
+                    _ => {
+                        return Err(FcplugError::IdlCheck(format!(
+                            "The exception of '{service_name}.{method}' should be a struct or an exception defined in the IDL."
+                        )))
+                    }
+                },
+                _ => {
+                    return Err(FcplugError::IdlCheck(format!(
+                        "'{service_name}.{method}' throws {} exceptions, but only one is supported.",
+                        function.throws.len()
+                    )))
+                }
+            };
+            methods.insert(method.to_string(), error);
+        }
+        if !methods.is_empty() {
+            declared_errors.insert(service.name.as_str().to_string(), methods);
+        }
+    }
+    Ok(declared_errors)
+}
+
+/// The declared errors of the protobuf rpc methods by the `@throws(MyError)` comment annotations, each of which is in
+/// the leading or the trailing comment of an `rpc` statement. The comments are attached by the protobuf tokenizer
+/// like the `leading_comments` and `trailing_comments` of protoc's `SourceCodeInfo`, which the pure parser does not
+/// record: the comment block right above the statement, or the comment following it on its last line.
+fn parse_proto_declared_errors(
+    idl: &str,
+) -> Result<HashMap<String, HashMap<String, String>>, FcplugError> {
+    use protobuf_support::lexer::lexer_impl::Lexer;
+    use protobuf_support::lexer::parser_language::ParserLanguage;
+    use protobuf_support::lexer::token::Token;
+
+    let line_starts = std::iter::once(0)
+        .chain(idl.match_indices('\n').map(|(i, _)| i + 1))
+        .collect::<Vec<usize>>();
+    // the 1-based line of the byte offset
+    let line_of = |offset: usize| line_starts.partition_point(|&start| start <= offset);
+
+    // the tokens with their byte offsets, and the comments before each token and after the last one
+    let mut tokens = Vec::new();
+    let mut comments = vec![Vec::new()];
+    let mut trivia_start = 0;
+    let mut lexer = Lexer::new(idl, ParserLanguage::Proto);
+    loop {
+        let token = lexer.next_token().map_err(|e| {
+            FcplugError::IdlCheck(format!(
+                "Failed to tokenize the protobuf IDL at {}: {e}",
+                lexer.loc
+            ))
+        })?;
+        let start = match &token {
+            Some(token) => {
+                let line_start = line_starts[token.loc.line as usize - 1];
+                idl[line_start..]
+                    .char_indices()
+                    .nth(token.loc.col as usize - 1)
+                    .map_or(idl.len(), |(i, _)| line_start + i)
+            }
+            None => idl.len(),
+        };
+        // the trivia between the tokens are the whitespaces and the comments only
+        let mut i = trivia_start
+            + idl[trivia_start..start]
+                .find('/')
+                .unwrap_or(start - trivia_start);
+        while i < start {
+            let rest = &idl[i..start];
+            let end = if rest.starts_with("//") {
+                rest.find('\n').map_or(start, |j| i + j)
+            } else if rest.starts_with("/*") {
+                rest.find("*/").map_or(start, |j| i + j + 2)
+            } else {
+                i += rest.len() - rest.trim_start().len();
+                continue;
+            };
+            comments.last_mut().unwrap().push(i..end);
+            i = end;
+        }
+        let Some(token) = token else {
+            break;
+        };
+        // no comment is in the token but the string literal
+        trivia_start = match &token.token {
+            Token::StrLit(lit) => start + lit.escaped.len() + 2,
+            _ => start + 1,
+        };
+        tokens.push((token.token, start));
+        comments.push(Vec::new());
+    }
+
+    // the rpc statements as (service, method, the first token, the last token)
+    let mut rpcs = Vec::new();
+    let mut service: Option<(&String, usize)> = None;
+    let mut depth = 0;
+    for (i, (token, _)) in tokens.iter().enumerate() {
+        match token {
+            Token::Symbol('{') => depth += 1,
+            Token::Symbol('}') => {
+                depth -= 1;
+                if service.is_some_and(|(_, body_depth)| depth < body_depth) {
+                    service = None;
+                }
+            }
+            Token::Ident(keyword) if keyword == "service" && depth == 0 => {
+                if let Some((Token::Ident(name), _)) = tokens.get(i + 1) {
+                    service = Some((name, depth + 1));
+                }
+            }
+            Token::Ident(keyword) if keyword == "rpc" => {
+                let (Some((service, body_depth)), Some((Token::Ident(method), _))) =
+                    (service, tokens.get(i + 1))
+                else {
+                    continue;
+                };
+                if depth != body_depth {
+                    continue;
+                }
+                // the statement ends with `;` or the option block
+                let mut inner_depth = 0;
+                let last = (i..tokens.len())
+                    .find(|&j| match tokens[j].0 {
+                        Token::Symbol(';') => inner_depth == 0,
+                        Token::Symbol('{') => {
+                            inner_depth += 1;
+                            false
+                        }
+                        Token::Symbol('}') => {
+                            inner_depth -= 1;
+                            inner_depth == 0
+                        }
+                        _ => false,
+                    })
+                    .unwrap_or(tokens.len() - 1);
+                rpcs.push((service, method, i, last));
+            }
+            _ => {}
+        }
+    }
+
+    // the comment start -> the rpc which it is attached to
+    let mut attached = HashMap::new();
+    for (rpc, &(_, _, first, last)) in rpcs.iter().enumerate() {
+        // the leading comments are contiguous up to the statement, but not the trailing one of the previous token
+        let prev_line = first.checked_sub(1).map(|prev| line_of(tokens[prev].1));
+        let mut next_line = line_of(tokens[first].1);
+        for comment in comments[first].iter().rev() {
+            if line_of(comment.end - 1) + 1 < next_line || Some(line_of(comment.start)) == prev_line
+            {
+                break;
+            }
+            attached.insert(comment.start, rpc);
+            next_line = line_of(comment.start);
+        }
+        let last_line = line_of(tokens[last].1);
+        if let Some(comment) = comments[last + 1].first() {
+            if line_of(comment.start) == last_line {
+                attached.insert(comment.start, rpc);
+            }
+        }
+    }
+
+    let annotation_re = Regex::new(r"@throws\b(?:\(\s*(\w+)\s*\))?").unwrap();
+    let mut declared_errors: HashMap<String, HashMap<String, String>> = HashMap::new();
+    for comment in comments.iter().flatten() {
+        let text = &idl[comment.clone()];
+        let Some(caps) = annotation_re.captures(text) else {
+            continue;
+        };
+        let line_no = line_of(comment.start + caps.get(0).unwrap().start());
+        let error = match caps.get(1) {
+            Some(error) if annotation_re.find_iter(text).count() == 1 => error.as_str().to_string(),
+            _ => {
+                return Err(FcplugError::IdlCheck(format!(
+                    "Invalid annotation at line {line_no}: '{}', expect one '@throws(MyError)' with a message name.",
+                    idl[line_starts[line_no - 1]..].lines().next().unwrap_or_default().trim()
+                )))
+            }
+        };
+        let Some(&(service, method, first, _)) =
+            attached.get(&comment.start).map(|&rpc| &rpcs[rpc])
+        else {
+            return Err(FcplugError::IdlCheck(format!(
+                "The annotation '@throws({error})' at line {line_no} should be placed above an 'rpc' statement or at the end of its line."
+            )));
+        };
+        let methods = declared_errors.entry(service.clone()).or_default();
+        if methods.insert(method.clone(), error).is_some() {
+            return Err(FcplugError::IdlCheck(format!(
+                "The method '{method}' at line {} has more than one '@throws' annotation, expect one.",
+                line_of(tokens[first].1)
+            )));
+        }
+    }
+    Ok(declared_errors)
+}
+
+/// Remove the `throws (...)` clauses of the thrift functions, which pilota cannot resolve.
+/// The clauses are located by the grammar of pilota-thrift-parser, whose `Function` keeps no span.
+fn strip_thrift_throws(idl: &str) -> Result<String, FcplugError> {
+    use nom::branch::alt;
+    use nom::bytes::complete::{tag, take_till, take_until};
+    use nom::character::complete::{multispace1, one_of};
+    use nom::combinator::{map, opt, recognize};
+    use nom::multi::{many0, many1};
+    use nom::sequence::{preceded, terminated, tuple};
+    use nom::IResult;
+    use thrift_parser::{Annotations, Field, Ident, Item, Path, Type};
+
+    fn blank(input: &str) -> IResult<&str, ()> {
+        let comment = alt((
+            preceded(tag("//"), take_till(|c| c == '\n')),
+            preceded(tag("/*"), terminated(take_until("*/"), tag("*/"))),
+            preceded(tag("#"), take_till(|c| c == '\n')),
+        ));
+        map(many1(alt((comment, multispace1))), |_| ())(input)
+    }
+    fn fields(input: &str) -> IResult<&str, Vec<Field>> {
+        many1(map(tuple((opt(blank), Field::parse)), |(_, field)| field))(input)
+    }
+    // the same as `Function::parse`, but returns the `throws` clause
+    fn function(input: &str) -> IResult<&str, Option<&str>> {
+        map(
+            tuple((
+                opt(tuple((tag("oneway"), blank))),
+                Type::parse,
+                blank,
+                Ident::parse,
+                opt(blank),
+                tag("("),
+                opt(fields),
+                opt(blank),
+                tag(")"),
+                opt(blank),
+                opt(recognize(tuple((
+                    tag("throws"),
+                    opt(blank),
+                    tag("("),
+                    fields,
+                    opt(blank),
+                    tag(")"),
+                )))),
+                opt(blank),
+                opt(Annotations::parse),
+                opt(one_of(",;")),
+            )),
+            |(_, _, _, _, _, _, _, _, _, _, throws, _, _, _)| throws,
+        )(input)
+    }
+    // the same as `Service::parse`, but returns the `throws` clauses
+    fn service(input: &str) -> IResult<&str, Vec<&str>> {
+        map(
+            tuple((
+                tag("service"),
+                blank,
+                Ident::parse,
+                opt(tuple((blank, tag("extends"), blank, Path::parse))),
+                opt(blank),
+                tag("{"),
+                many0(map(tuple((opt(blank), function)), |(_, throws)| throws)),
+                opt(blank),
+                tag("}"),
+                opt(blank),
+                opt(Annotations::parse),
+            )),
+            |(_, _, _, _, _, _, throws, _, _, _, _)| throws.into_iter().flatten().collect(),
+        )(input)
+    }
+
+    let mut tidied = String::with_capacity(idl.len());
+    let mut copied = 0;
+    let mut rest = idl;
+    loop {
+        rest = opt(blank)(rest).map_or(rest, |(rest, _)| rest);
+        if rest.is_empty() {
+            break;
+        }
+        let (remaining, clauses) = service(rest)
+            .or_else(|_| Item::parse(rest).map(|(remaining, _)| (remaining, vec![])))
+            .map_err(|e| FcplugError::IdlCheck(format!("Failed to parse the thrift IDL: {e}")))?;
+        for clause in clauses {
+            let start = clause.as_ptr() as usize - idl.as_ptr() as usize;
+            tidied.push_str(&idl[copied..start]);
+            copied = start + clause.len();
+        }
+        rest = remaining;
+    }
+    tidied.push_str(&idl[copied..]);
+    Ok(tidied)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{
+        parse_proto_declared_errors, parse_thrift, parse_thrift_declared_errors,
+        strip_thrift_throws, Config, WorkConfig,
+    };
+    use crate::FcplugError;
+
+    /// A scratch directory removed on drop
+    struct TempDir(std::path::PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("fcplug-test-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn declared(entries: &[(&str, &str, &str)]) -> HashMap<String, HashMap<String, String>> {
+        let mut declared: HashMap<String, HashMap<String, String>> = HashMap::new();
+        for (service, method, error) in entries {
+            declared
+                .entry(service.to_string())
+                .or_default()
+                .insert(method.to_string(), error.to_string());
+        }
+        declared
+    }
+
+    #[test]
+    fn test_thrift_throws() {
+        let idl = r#"
+struct Ping {
+    1: string msg,
+}
+exception EchoError {
+    1: string msg,
+}
+struct StructError {
+    1: string msg (go.tag = "json:\"msg\""),
+}
+service RustFFI {
+    // throws (1: Ping p) in the comment is not a clause
+    Ping echo_rs (1: Ping req) throws (1: EchoError err),
+    Ping echo_multiline (
+        1: Ping req,
+    ) throws (
+        1: StructError err (api.note = "(nested)"),
+    )
+    Ping echo_plain (1: Ping req),
+}
+"#;
+        let file = parse_thrift(idl).unwrap();
+        assert_eq!(
+            parse_thrift_declared_errors(&file).unwrap(),
+            declared(&[
+                ("RustFFI", "echo_rs", "EchoError"),
+                ("RustFFI", "echo_multiline", "StructError"),
+            ])
+        );
+        let tidied = strip_thrift_throws(idl).unwrap();
+        assert!(tidied.contains("// throws (1: Ping p) in the comment is not a clause"));
+        assert!(tidied.contains("Ping echo_rs (1: Ping req) ,"));
+        assert!(tidied.contains("    ) \n    Ping echo_plain (1: Ping req),"));
+        assert!(
+            parse_thrift_declared_errors(&parse_thrift(&tidied).unwrap())
                 .unwrap()
-                .to_string()
-        } else if let Ok(go_root_path) = env::var("GOROOT") {
-            PathBuf::from_str(&go_root_path)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_thrift_throws_multiple_types() {
+        let idl = r#"
+exception NotFound {}
+exception Denied {}
+service RustFFI {
+    void get (1: string key) throws (1: NotFound not_found, 2: Denied denied),
+}
+"#;
+        let err = parse_thrift_declared_errors(&parse_thrift(idl).unwrap()).unwrap_err();
+        assert!(
+            matches!(&err, FcplugError::IdlCheck(msg) if msg == "'RustFFI.get' throws 2 exceptions, but only one is supported."),
+            "{err}"
+        );
+        assert!(!strip_thrift_throws(idl).unwrap().contains("throws"));
+    }
+
+    #[test]
+    fn test_proto_throws() {
+        let idl = r#"
+message EchoError {}
+service RustFFI {
+  // @throws(EchoError)
+  rpc echo_rs (Ping) returns (Pong) {}
+  rpc echo_inline (Ping)
+      returns (Pong) {} // @throws(EchoError)
+  /*
+   * @throws(EchoError)
+   */
+  rpc echo_block (Ping) returns (Pong) {}
+  rpc echo_plain (Ping) returns (Pong) {}
+}
+service GoFFI {
+  // the declared error
+  // @throws(EchoError)
+  rpc echo_go (Ping) returns (Pong) {
+    option deprecated = true;
+  } // @throws(EchoError) is not in the leading comment of the next rpc
+  rpc echo_option (Ping) returns (Pong) {}
+  rpc echo_string (Ping) returns (Pong) { option (note) = "// @throws(Ping)"; } /* @throws(EchoError) */
+}
+"#;
+        // the trailing comment of the statement ending with the option block
+        let err = parse_proto_declared_errors(idl).unwrap_err();
+        assert!(
+            matches!(&err, FcplugError::IdlCheck(m) if m == "The method 'echo_go' at line 17 has more than one '@throws' annotation, expect one."),
+            "{err}"
+        );
+        let idl = idl.replace("} // @throws(EchoError) is", "} // the trailing comment is");
+        assert_eq!(
+            parse_proto_declared_errors(&idl).unwrap(),
+            declared(&[
+                ("RustFFI", "echo_rs", "EchoError"),
+                ("RustFFI", "echo_inline", "EchoError"),
+                ("RustFFI", "echo_block", "EchoError"),
+                ("GoFFI", "echo_go", "EchoError"),
+                ("GoFFI", "echo_string", "EchoError"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_proto_throws_invalid() {
+        for (idl, msg) in [
+            (
+                "service RustFFI {\n  // @throws(NotFound, Denied)\n  rpc get (Key) returns (Value) {}\n}",
+                "Invalid annotation at line 2: '// @throws(NotFound, Denied)', expect one '@throws(MyError)' with a message name.",
+            ),
+            (
+                "service RustFFI {\n  // @throws(EchoError)\n  option deprecated = true;\n  rpc echo_rs (Ping) returns (Pong) {}\n}",
+                "The annotation '@throws(EchoError)' at line 2 should be placed above an 'rpc' statement or at the end of its line.",
+            ),
+            (
+                "service RustFFI {\n  rpc echo_rs (Ping) returns (Pong) {}\n  // @throws(EchoError)\n}",
+                "The annotation '@throws(EchoError)' at line 3 should be placed above an 'rpc' statement or at the end of its line.",
+            ),
+            (
+                "// @throws(EchoError)\nservice RustFFI {\n  rpc echo_rs (Ping) returns (Pong) {}\n}",
+                "The annotation '@throws(EchoError)' at line 1 should be placed above an 'rpc' statement or at the end of its line.",
+            ),
+            (
+                "service RustFFI {\n  // @throws(EchoError)\n  rpc echo_rs (Ping) returns (Pong) {} // @throws(EchoError)\n}",
+                "The method 'echo_rs' at line 3 has more than one '@throws' annotation, expect one.",
+            ),
+            (
+                "service RustFFI {\n  // @throws(EchoError)\n\n  rpc echo_rs (Ping) returns (Pong) {}\n}",
+                "The annotation '@throws(EchoError)' at line 2 should be placed above an 'rpc' statement or at the end of its line.",
+            ),
+            (
+                "service RustFFI {\n  rpc echo_rs (Ping) // @throws(EchoError)\n      returns (Pong) {}\n}",
+                "The annotation '@throws(EchoError)' at line 2 should be placed above an 'rpc' statement or at the end of its line.",
+            ),
+        ] {
+            let err = parse_proto_declared_errors(idl).unwrap_err();
+            assert!(
+                matches!(&err, FcplugError::IdlCheck(m) if m == msg),
+                "{err}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_rust_clib_built() {
+        use std::time::{Duration, SystemTime};
+
+        let dir = TempDir::new("rust-clib");
+        let c = WorkConfig {
+            rust_clib_file: dir.0.join("libecho.a"),
+            rust_clib_header: dir.0.join("echo.h"),
+            ..Default::default()
+        };
+        let set_modified = |f: &std::path::Path, t: SystemTime| {
+            std::fs::File::options()
+                .write(true)
+                .open(f)
                 .unwrap()
-                .join("bin")
-                .join(cmd)
-                .to_str()
+                .set_modified(t)
                 .unwrap()
-                .to_string()
-        } else {
-            cmd.to_string()
+        };
+        let now = SystemTime::now();
+        std::fs::write(&c.rust_clib_header, "int echo();").unwrap();
+        set_modified(&c.rust_clib_header, now);
+        assert!(!c.is_rust_clib_built());
+
+        // the empty placeholder is not built
+        std::fs::write(&c.rust_clib_file, "").unwrap();
+        set_modified(&c.rust_clib_file, now + Duration::from_secs(1));
+        assert!(!c.is_rust_clib_built());
+
+        // the archive built before the RustFFI changes is stale
+        std::fs::write(&c.rust_clib_file, "!<arch>\n").unwrap();
+        set_modified(&c.rust_clib_file, now - Duration::from_secs(1));
+        assert!(!c.is_rust_clib_built());
+
+        set_modified(&c.rust_clib_file, now + Duration::from_secs(1));
+        assert!(c.is_rust_clib_built());
+    }
+
+    /// Apply the overrides of the Cargo.toml `manifest` and the environment variables `envs`
+    fn overridden(
+        dir: &TempDir,
+        manifest: &str,
+        envs: &[(&str, &str)],
+    ) -> Result<Config, FcplugError> {
+        std::fs::write(dir.0.join("Cargo.toml"), manifest).unwrap();
+        let mut config = Config::default();
+        config
+            .apply_overrides_with(&dir.0, |env_key| {
+                envs.iter()
+                    .find(|(key, _)| *key == env_key)
+                    .map(|(_, value)| value.to_string())
+            })
+            .map(|_| config)
+    }
+
+    #[test]
+    fn test_overrides_precedence() {
+        let dir = TempDir::new("overrides-precedence");
+        let manifest = r#"[package]
+name = "echo"
+
+[package.metadata.fcplug]
+idl_file = "idl/echo.proto"
+go_mod_parent = "github.com/andeya/metadata"
+go_host = true
+typed_rustffi = "1"
+rust_impl_type = "crate::EchoService"
+go_requires = ["github.com/bytedance/sonic@v1.9.1"]
+go_object_path = { import = "github.com/andeya/demo/impl", object_ident = "impl.Echo" }
+"#;
+        let config = overridden(&dir, manifest, &[]).unwrap();
+        // the relative idl_file is relative to the crate directory
+        assert_eq!(config.idl_file, dir.0.join("idl/echo.proto"));
+        assert_eq!(config.go_mod_parent, "github.com/andeya/metadata");
+        assert!(config.go_host);
+        assert!(config.typed_rustffi);
+        assert_eq!(config.rust_impl_type.unwrap().0, "crate::EchoService");
+        assert_eq!(config.go_requires, ["github.com/bytedance/sonic@v1.9.1"]);
+        let go_object_path = config.go_object_path.unwrap();
+        assert_eq!(go_object_path.import, "github.com/andeya/demo/impl");
+        assert_eq!(go_object_path.object_ident, "impl.Echo");
+
+        // the environment variables override the metadata
+        let config = overridden(
+            &dir,
+            manifest,
+            &[
+                ("FCPLUG_IDL_FILE", "/idl/echo.thrift"),
+                ("FCPLUG_GO_ROOT", "/usr/local/go"),
+                ("FCPLUG_GO_MOD_PARENT", "github.com/andeya/env"),
+                ("FCPLUG_GO_HOST", "0"),
+                ("FCPLUG_TYPED_RUSTFFI", ""),
+                (
+                    "FCPLUG_GO_REQUIRES",
+                    "github.com/andeya/gust@v1.5.2, github.com/bytedance/sonic@v1.10.0",
+                ),
+            ],
+        )
+        .unwrap();
+        assert_eq!(config.idl_file, std::path::Path::new("/idl/echo.thrift"));
+        assert_eq!(
+            config.go_root_path.unwrap(),
+            std::path::Path::new("/usr/local/go")
+        );
+        assert_eq!(config.go_mod_parent, "github.com/andeya/env");
+        assert!(!config.go_host);
+        assert!(!config.typed_rustffi);
+        assert_eq!(
+            config.go_requires,
+            [
+                "github.com/andeya/gust@v1.5.2",
+                "github.com/bytedance/sonic@v1.10.0"
+            ]
+        );
+        // the options not in the environment are kept
+        assert_eq!(config.rust_impl_type.unwrap().0, "crate::EchoService");
+
+        // `overrides` override both, e.g. the options of the `fcplug` command
+        std::fs::write(dir.0.join("Cargo.toml"), manifest).unwrap();
+        let mut config = Config {
+            overrides: vec![
+                (
+                    "go_mod_parent".to_string(),
+                    "github.com/andeya/cli".to_string(),
+                ),
+                ("go_host".to_string(), "true".to_string()),
+            ],
+            ..Default::default()
+        };
+        config
+            .apply_overrides_with(&dir.0, |env_key| {
+                (env_key == "FCPLUG_GO_MOD_PARENT").then(|| "github.com/andeya/env".to_string())
+            })
+            .unwrap();
+        assert_eq!(config.go_mod_parent, "github.com/andeya/cli");
+        assert!(config.go_host);
+        assert_eq!(config.rust_impl_type.unwrap().0, "crate::EchoService");
+    }
+
+    #[test]
+    fn test_overrides_bool() {
+        let dir = TempDir::new("overrides-bool");
+        let manifest = "[package]\nname = \"echo\"\n";
+        let envs = |value| {
+            [
+                ("FCPLUG_IDL_FILE", "echo.proto"),
+                ("FCPLUG_GO_MOD_PARENT", "github.com/andeya/demo"),
+                ("FCPLUG_GO_OFFLINE", value),
+            ]
+        };
+        for (value, expected) in [
+            ("true", true),
+            ("1", true),
+            ("false", false),
+            ("0", false),
+            ("", false),
+        ] {
+            let config = overridden(&dir, manifest, &envs(value)).unwrap();
+            assert_eq!(config.go_offline, expected, "{value}");
+        }
+        for value in ["yes", "TRUE", " 1"] {
+            let err = overridden(&dir, manifest, &envs(value)).err().unwrap();
+            assert!(
+                matches!(&err, FcplugError::Config(msg) if msg == &format!("the fcplug option go_offline should be a bool, got '{value}'")),
+                "{err}"
+            );
         }
+        let config = overridden(
+            &dir,
+            "[package.metadata.fcplug]\nidl_file = \"echo.proto\"\ngo_mod_parent = \"github.com/andeya/demo\"\nuse_goffi_cdylib = true\nadd_clib_to_git = false\n",
+            &[],
+        )
+        .unwrap();
+        assert!(config.use_goffi_cdylib);
+        assert!(!config.add_clib_to_git);
+    }
+
+    #[test]
+    fn test_overrides_invalid() {
+        let dir = TempDir::new("overrides-invalid");
+        let config_err =
+            |manifest: &str, envs: &[(&str, &str)]| match overridden(&dir, manifest, envs)
+                .err()
+                .unwrap()
+            {
+                FcplugError::Config(msg) => msg,
+                err => panic!("unexpected error: {err}"),
+            };
+        let required = "idl_file = \"echo.proto\"\ngo_mod_parent = \"github.com/andeya/demo\"\n";
+        let metadata = |options: &str| format!("[package.metadata.fcplug]\n{required}{options}");
+
+        assert_eq!(
+            config_err(&metadata("go_root = \"/usr/local/go\"\n"), &[]),
+            "unknown fcplug option go_root"
+        );
+        assert_eq!(
+            config_err(&metadata("go_host = 1\n"), &[]),
+            "unsupported package.metadata.fcplug.go_host"
+        );
+        assert_eq!(
+            config_err(
+                &metadata("go_requires = [\"github.com/andeya/gust@v1.5.2\", 1]\n"),
+                &[]
+            ),
+            "package.metadata.fcplug.go_requires should be strings"
+        );
+        assert_eq!(
+            config_err(
+                &metadata("go_object_path = { import = \"github.com/andeya/demo/impl\" }\n"),
+                &[]
+            ),
+            "package.metadata.fcplug.go_object_path.object_ident should be a string"
+        );
+        assert!(config_err("[package\n", &[]).contains("Cargo.toml"));
+        assert_eq!(
+            config_err("", &[("FCPLUG_GO_MOD_PARENT", "github.com/andeya/demo")]),
+            "idl_file is not configured"
+        );
+        assert_eq!(
+            config_err("", &[("FCPLUG_IDL_FILE", "echo.proto")]),
+            "go_mod_parent is not configured"
+        );
+
+        // the Go requirements are checked by the work config
+        std::fs::write(
+            dir.0.join("Cargo.toml"),
+            metadata("go_requires = [\"github.com/andeya/gust\"]\n"),
+        )
+        .unwrap();
+        let err = WorkConfig::new(
+            Config {
+                target_crate_dir: Some(dir.0.clone()),
+                ..Default::default()
+            },
+            true,
+        )
+        .err()
+        .unwrap();
+        assert!(
+            matches!(&err, FcplugError::Config(msg) if msg.starts_with("the Go requirement 'github.com/andeya/gust' should be")),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_artifact_dir() {
+        let dir = TempDir::new("artifact-dir");
+        let artifact_dir = |out_dir: &str| {
+            let out_dir = dir.0.join(out_dir);
+            std::fs::create_dir_all(&out_dir).unwrap();
+            WorkConfig::artifact_dir_of(&out_dir)
+        };
+        let root = dir.0.canonicalize().unwrap();
+        // the default, `--target`, `--target-dir` and the custom profile
+        for (out_dir, expected) in [
+            ("target/debug/build/echo-1a2b3c/out", "target/debug"),
+            ("target/release/build/echo-1a2b3c/out", "target/release"),
+            (
+                "target/aarch64-apple-darwin/debug/build/echo-1a2b3c/out",
+                "target/aarch64-apple-darwin/debug",
+            ),
+            (
+                "custom-target/x86_64-unknown-linux-gnu/release/build/echo-1a2b3c/out",
+                "custom-target/x86_64-unknown-linux-gnu/release",
+            ),
+            (
+                "target/release-lto/build/echo-1a2b3c/out",
+                "target/release-lto",
+            ),
+            // the relative `..` is resolved
+            (
+                "target/debug/build/echo-fcplug/../echo-1a2b3c/out",
+                "target/debug",
+            ),
+        ] {
+            assert_eq!(
+                artifact_dir(out_dir).unwrap(),
+                root.join(expected),
+                "{out_dir}"
+            );
+        }
+        for out_dir in [
+            "target/debug/build/echo-1a2b3c",
+            "target/debug/deps/echo-1a2b3c/out",
+        ] {
+            let err = artifact_dir(out_dir).err().unwrap();
+            assert!(
+                matches!(&err, FcplugError::Config(msg) if msg.starts_with("failed to locate the artifact directory")),
+                "{err}"
+            );
+        }
+        // OUT_DIR should exist
+        assert!(matches!(
+            WorkConfig::artifact_dir_of(&dir.0.join("missing/debug/build/echo-1a2b3c/out")),
+            Err(FcplugError::Io { .. })
+        ));
+    }
+
+    #[test]
+    fn test_rust_clib_name_base() {
+        let dir = TempDir::new("rust-clib-name");
+        let name_base = |manifest: Option<&str>, in_memory: bool, goffi_mock: bool| {
+            let manifest_file = dir.0.join("Cargo.toml");
+            match manifest {
+                Some(manifest) => std::fs::write(&manifest_file, manifest).unwrap(),
+                None => {
+                    let _ = std::fs::remove_file(&manifest_file);
+                }
+            }
+            WorkConfig {
+                pkg_dir: dir.0.clone(),
+                pkg_name: "echo_dir".to_string(),
+                in_memory,
+                goffi_mock,
+                ..Default::default()
+            }
+            .new_rust_clib_name_base()
+        };
+        let config_err = |ret: Result<String, FcplugError>| match ret {
+            Err(FcplugError::Config(msg)) => msg,
+            ret => panic!("unexpected result: {ret:?}"),
+        };
+
+        // `[lib] name` takes precedence over the package name
+        let manifest = "[package]\nname = \"echo-demo\"\n\n[lib]\nname = \"echo_lib\"\ncrate-type = [\"rlib\", \"staticlib\"]\n";
+        assert_eq!(name_base(Some(manifest), false, false).unwrap(), "echo_lib");
+        let manifest =
+            "[package]\nname = \"echo-demo\"\n\n[lib]\ncrate-type = [\"rlib\", \"staticlib\"]\n";
+        assert_eq!(
+            name_base(Some(manifest), false, false).unwrap(),
+            "echo_demo"
+        );
+        let manifest = "[package]\nname = \"echo-demo\"\n\n[lib]\ncrate_type = [\"staticlib\"]\n";
+        assert_eq!(
+            name_base(Some(manifest), false, false).unwrap(),
+            "echo_demo"
+        );
+
+        // the static library is only needed by the Go build
+        let manifest = "[package]\nname = \"echo-demo\"\n\n[lib]\ncrate-type = [\"cdylib\"]\n";
+        assert!(config_err(name_base(Some(manifest), false, false))
+            .starts_with("'staticlib' should be in [lib] crate-type of "));
+        assert_eq!(name_base(Some(manifest), false, true).unwrap(), "echo_demo");
+        assert_eq!(name_base(Some(manifest), true, false).unwrap(), "echo_demo");
+
+        assert!(config_err(name_base(
+            Some("[lib]\ncrate-type = [\"staticlib\"]\n"),
+            false,
+            false
+        ))
+        .starts_with("no [lib] name or [package] name in "));
+        // the crate directory names the in-memory generation without Cargo.toml
+        assert!(config_err(name_base(None, false, false)).ends_with("Cargo.toml does not exist"));
+        assert_eq!(name_base(None, true, false).unwrap(), "echo_dir");
+    }
+
+    #[test]
+    fn test_go_work() {
+        let dir = TempDir::new("go-work");
+        let root = dir.0.canonicalize().unwrap();
+        let pkg_dir = root.join("samples").join("echo");
+        std::fs::create_dir_all(&pkg_dir).unwrap();
+        let new_config = || WorkConfig {
+            pkg_dir: pkg_dir.clone(),
+            ..Default::default()
+        };
+        let go_work = root.join("go.work");
+
+        // without go.work
+        let c = new_config();
+        assert_eq!(c.find_go_work_by(None), None);
+        let mut c = new_config();
+        c.set_go_modes(None);
+        assert!(!c.go_workspace && !c.go_work_off && !c.go_vendor);
+
+        // the go.work in the ancestors, which may not use the crate
+        std::fs::write(&go_work, "go 1.18\n\nuse ./rust\n").unwrap();
+        assert_eq!(c.find_go_work_by(None), Some(go_work.clone()));
+        assert!(!c.is_used_by_go_work(&go_work));
+        let mut c = new_config();
+        c.set_go_modes(c.find_go_work_by(None));
+        assert!(!c.go_workspace && c.go_work_off);
+        // so it is not watched
+        assert!(!c.new_input_files().contains(&go_work));
+
+        for content in [
+            "go 1.18\n\nuse ./samples/echo\n",
+            "go 1.18\n\nuse \"./samples/echo\" // the sample\n",
+            "go 1.18\n\nuse (\n\t./rust\n\t./samples/echo/\n)\n",
+            "go 1.18\n\nuse (\n\t// ./rust\n\tsamples/../samples/echo\n)\n",
+        ] {
+            std::fs::write(&go_work, content).unwrap();
+            assert!(c.is_used_by_go_work(&go_work), "{content}");
+            let mut c = new_config();
+            c.set_go_modes(c.find_go_work_by(None));
+            assert!(c.go_workspace && !c.go_work_off, "{content}");
+        }
+        // the used go.work is watched, unless the Go commands are skipped by the GoFFI mock
+        let mut c = new_config();
+        c.set_go_modes(c.find_go_work_by(None));
+        assert!(c.new_input_files().contains(&go_work));
+        let mut c = WorkConfig {
+            goffi_mock: true,
+            ..new_config()
+        };
+        c.set_go_modes(c.find_go_work_by(None));
+        assert!(!c.go_workspace && !c.go_work_off);
+        assert!(!c.new_input_files().contains(&go_work));
+        for content in [
+            "go 1.18\n\n// use ./samples/echo\n",
+            "go 1.18\n\nuse (\n\t./samples\n)\n",
+        ] {
+            std::fs::write(&go_work, content).unwrap();
+            assert!(!c.is_used_by_go_work(&go_work), "{content}");
+        }
+
+        // `$GOWORK` takes precedence over the ancestors
+        let other_go_work = root.join("other.work");
+        assert_eq!(
+            c.find_go_work_by(Some(other_go_work.clone().into())),
+            Some(other_go_work)
+        );
+        assert_eq!(c.find_go_work_by(Some("off".into())), None);
+        assert_eq!(c.find_go_work_by(Some("".into())), Some(go_work));
+    }
+
+    #[test]
+    fn test_go_vendor_and_command() {
+        let dir = TempDir::new("go-vendor");
+        let pkg_dir = dir.0.canonicalize().unwrap();
+        let new_config = || WorkConfig {
+            pkg_dir: pkg_dir.clone(),
+            ..Default::default()
+        };
+        let envs = |c: &WorkConfig| {
+            c.go_command()
+                .get_envs()
+                .map(|(key, value)| (key.to_owned(), value.map(|value| value.to_owned())))
+                .collect::<Vec<_>>()
+        };
+
+        let mut c = new_config();
+        c.set_go_modes(None);
+        assert!(!c.go_vendor);
+        assert!(envs(&c).is_empty());
+        assert_eq!(c.go_command().get_current_dir(), Some(pkg_dir.as_path()));
+
+        // the vendor directory without modules.txt is not vendored
+        std::fs::create_dir_all(pkg_dir.join("vendor")).unwrap();
+        c.set_go_modes(None);
+        assert!(!c.go_vendor);
+        let modules_txt = pkg_dir.join("vendor").join("modules.txt");
+        std::fs::write(modules_txt, "# github.com/andeya/gust v1.5.2\n").unwrap();
+        c.set_go_modes(None);
+        assert!(c.go_vendor);
+
+        // the unused go.work is off, and the offline mode never downloads the modules
+        std::fs::write(pkg_dir.join("go.work"), "go 1.18\n\nuse ./other\n").unwrap();
+        let mut c = new_config();
+        c.set_go_modes(c.find_go_work_by(None));
+        assert_eq!(envs(&c), [("GOWORK".into(), Some("off".into()))]);
+        c.go_offline = true;
+        assert_eq!(
+            envs(&c),
+            [
+                ("GOPROXY".into(), Some("off".into())),
+                ("GOWORK".into(), Some("off".into()))
+            ]
+        );
+        let mut c = new_config();
+        c.go_offline = true;
+        c.set_go_modes(None);
+        assert_eq!(envs(&c), [("GOPROXY".into(), Some("off".into()))]);
     }
 }
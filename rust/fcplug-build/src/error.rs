@@ -0,0 +1,69 @@
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The error of the code generation, returned by [`generate_code`](crate::generate_code)
+#[derive(Debug)]
+pub enum FcplugError {
+    /// The IDL file does not pass the fcplug check, e.g. it configures the `package`
+    IdlCheck(String),
+    /// The `Config` or the build environment is invalid, e.g. a cargo environment variable is missing
+    Config(String),
+    /// The external tool, e.g. `go`, `protoc` or `thriftgo`, failed to start or exited with failure
+    Command {
+        program: String,
+        /// The exit code, which is `None` if the tool failed to start or was killed by a signal
+        code: Option<i32>,
+        /// The stderr of the tool, or the error of starting it
+        stderr: String,
+    },
+    /// Reading or writing the file failed
+    Io { path: PathBuf, source: io::Error },
+    /// Generating the Rust code or the C header failed
+    Codegen(String),
+    /// Building or linking the Go C library failed
+    Link(String),
+}
+
+impl Display for FcplugError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FcplugError::IdlCheck(msg) => write!(f, "IDL-Check: {msg}"),
+            FcplugError::Config(msg) => write!(f, "invalid config: {msg}"),
+            FcplugError::Command {
+                program,
+                code,
+                stderr,
+            } => match code {
+                Some(code) => write!(f, "'{program}' exited with code {code}: {stderr}"),
+                None => write!(f, "'{program}' failed: {stderr}"),
+            },
+            FcplugError::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            FcplugError::Codegen(msg) => write!(f, "failed to generate code: {msg}"),
+            FcplugError::Link(msg) => write!(f, "failed to link: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for FcplugError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FcplugError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Attach the file path to the IO error
+pub(crate) trait IoContext<T> {
+    fn with_path(self, path: impl AsRef<Path>) -> Result<T, FcplugError>;
+}
+
+impl<T> IoContext<T> for io::Result<T> {
+    fn with_path(self, path: impl AsRef<Path>) -> Result<T, FcplugError> {
+        self.map_err(|source| FcplugError::Io {
+            path: path.as_ref().to_path_buf(),
+            source,
+        })
+    }
+}
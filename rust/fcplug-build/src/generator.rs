@@ -1,6 +1,10 @@
 use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::env;
 use std::fs;
 use std::ops::Deref;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::Arc;
 
@@ -16,15 +20,22 @@ use pilota_build::{
 
 use crate::config::IdlType;
 use crate::config::{Config, WorkConfig};
-use crate::os_arch::get_go_os_arch_from_env;
-use crate::{deal_output, deal_result, exit_with_warning, CODE_IO};
+use crate::error::IoContext;
+use crate::os_arch::{get_go_os_arch, GoOS};
+use crate::{run_command, FcplugError};
 
 #[derive(Debug, Clone)]
 pub(crate) struct Generator {
     pub(crate) config: WorkConfig,
     pub(crate) go_lib_code: Arc<RefCell<String>>,
     pub(crate) go_main_code: Arc<RefCell<String>>,
+    /// C declarations of the GoFFI functions exported by the lib package in the Go host mode
+    pub(crate) go_lib_cdecls: Arc<RefCell<String>>,
+    /// The fake of the RustFFI services built with the `fcplug_fake` tag
+    pub(crate) go_fake_code: Arc<RefCell<String>>,
     pub(crate) rust_mod_impl_code: Arc<RefCell<String>>,
+    /// The generated files keyed by the relative path in the in-memory mode
+    pub(crate) memory_files: Arc<RefCell<BTreeMap<PathBuf, String>>>,
 }
 
 unsafe impl Send for Generator {}
@@ -38,47 +49,106 @@ pub(crate) struct ImportPkg {
 }
 
 pub(crate) struct MidOutput {
+    pub(crate) go_lib_common: String,
+    pub(crate) go_lib_cgo_common: String,
+    pub(crate) go_lib_fake_common: String,
+    pub(crate) go_main_common: String,
     pub(crate) rust_clib_includes: String,
     pub(crate) mod_requires: Vec<String>,
     pub(crate) imports: Vec<ImportPkg>,
 }
 
+/// The files written by `generate_code`
+#[derive(Debug, Clone, Default)]
+pub struct GeneratedArtifacts {
+    /// The generated Rust module, e.g. `src/echo_ffi/echo_gen.rs`
+    pub rust_gen_file: PathBuf,
+    /// The Rust module implementing the FFI, which is written only if it does not exist
+    pub rust_impl_file: PathBuf,
+    /// The C header of the Rust library
+    pub rust_clib_header: PathBuf,
+    /// The generated Go files and `go.mod`, empty if the GoFFI is mocked by the `goffi-mock` feature
+    pub go_files: Vec<PathBuf>,
+    /// The Go C library, if it is built and linked
+    pub go_clib_file: Option<PathBuf>,
+    /// The C header of the Go C library, if it is built
+    pub go_clib_header: Option<PathBuf>,
+}
+
 impl Generator {
-    pub(crate) fn generate(config: Config) {
+    fn new(config: WorkConfig) -> Self {
         Self {
-            config: WorkConfig::new(config),
+            config,
             go_lib_code: Arc::new(RefCell::new(String::new())),
             go_main_code: Arc::new(RefCell::new(String::new())),
+            go_lib_cdecls: Arc::default(),
+            go_fake_code: Arc::default(),
             rust_mod_impl_code: Arc::new(RefCell::new("".to_string())),
+            memory_files: Arc::default(),
         }
-        .gen_code();
     }
-    fn gen_code(self) {
-        self.config.rerun_if_changed();
+    pub(crate) fn generate(config: Config) -> Result<GeneratedArtifacts, FcplugError> {
+        Self::new(WorkConfig::new(config, false)?).gen_code()
+    }
+    pub(crate) fn generate_in_memory(
+        config: Config,
+    ) -> Result<BTreeMap<PathBuf, String>, FcplugError> {
+        // the scratch directory is removed with the config
+        let generator = Self::new(WorkConfig::new(config, true)?);
+        let memory_files = generator.memory_files.clone();
+        generator.gen_code()?;
+        let files = memory_files.take();
+        Ok(files)
+    }
+    fn gen_code(self) -> Result<GeneratedArtifacts, FcplugError> {
+        if !self.config.in_memory {
+            self.config.rerun_if_changed();
+            self.config.rustc_check_cfg();
+            // nothing relevant has changed since the last generation
+            if self.config.is_fingerprint_unchanged() {
+                if let Some(artifacts) = self.last_artifacts() {
+                    return Ok(artifacts);
+                }
+            }
+        }
 
+        // both are absolute paths, so the relative path always exists
         let clib_dir_relative_root =
-            pathdiff::diff_paths(&self.config.clib_gen_dir, &self.config.pkg_dir)
-                .unwrap()
-                .to_str()
-                .unwrap()
+            pathdiff::diff_paths(&self.config.clib_link_dir, &self.config.pkg_dir)
+                .unwrap_or_default()
+                .display()
                 .to_string();
 
         let clib_dir_relative_cgobin =
-            pathdiff::diff_paths(&self.config.clib_gen_dir, &self.config.go_main_dir)
-                .unwrap()
-                .to_str()
-                .unwrap()
+            pathdiff::diff_paths(&self.config.clib_link_dir, &self.config.go_main_dir)
+                .unwrap_or_default()
+                .display()
                 .to_string();
 
         let go_mod_name = self.config.gomod_name.clone();
         let rust_clib_name_base = self.config.rust_clib_name_base.clone();
 
-        self.build_code_by_idl();
-        self.rust_gen_more_code();
-        let mid_output = self.clone()._gen_code();
+        self.build_code_by_idl()?;
+        self.rust_gen_more_code()?;
+        let mid_output = self.clone()._gen_code()?;
 
         // build rust c lib
-        self.gen_rust_clib(mid_output.rust_clib_includes.as_str());
+        self.gen_rust_clib(mid_output.rust_clib_includes.as_str())?;
+
+        let mut artifacts = GeneratedArtifacts {
+            rust_gen_file: self.config.rust_mod_gen_file.clone(),
+            rust_impl_file: self.config.rust_mod_impl_file.clone(),
+            rust_clib_header: self.config.rust_clib_header.clone(),
+            ..Default::default()
+        };
+
+        // the GoFFI is mocked in Rust, so the Go code is neither generated nor built
+        if self.config.goffi_mock {
+            if !self.config.in_memory {
+                self.config.save_fingerprint()?;
+            }
+            return Ok(artifacts);
+        }
 
         // write go lib code
         let mut lib_imports = String::new();
@@ -92,45 +162,128 @@ impl Generator {
                 lib_imports_use = format!("{lib_imports_use}{}\n", v.use_code);
             });
         let go_pkg_code = self.go_lib_code.borrow().clone();
+        let go_lib_cdecls = self.go_lib_cdecls.borrow().clone();
+        let mut go_lib_common = mid_output.go_lib_common.clone();
+        go_lib_common.push_str(&mid_output.go_lib_cgo_common);
+        if self.config.has_goffi && self.config.go_host {
+            go_lib_common.push_str(&mid_output.go_main_common);
+        }
         *self.go_lib_code.borrow_mut() = format!(
             r###"// Code generated by fcplug. DO NOT EDIT.
 
+            //go:build !fcplug_fake
+
             package {go_mod_name}
             /*
             #cgo CFLAGS: -I{clib_dir_relative_root}
-            #cgo LDFLAGS: -L{clib_dir_relative_root} -l{rust_clib_name_base} -ldl -lm
 
             #include "{rust_clib_name_base}.h"
+            {go_lib_cdecls}
             */
             import "C"
 
             import (
+                "context"
                 "errors"
                 "fmt"
                 "reflect"
+                "runtime"
+                "sync"
+                "time"
                 "unsafe"
 
                 {lib_imports}
             )
 
             var (
+                _ context.Context
                 _ = errors.New
                 _ = fmt.Sprintf
                 _ reflect.SliceHeader
+                _ = runtime.SetFinalizer
+                _ sync.Mutex
+                _ time.Duration
                 _ unsafe.Pointer
             )
             {lib_imports_use}
 
             {go_pkg_code}
+
+            {go_lib_common}
             "###
         );
-        deal_result(
-            CODE_IO,
-            std::fs::write(&self.config.go_lib_file, self.go_lib_code.borrow().as_str()),
-        );
+        self.write_file(&self.config.go_lib_file, self.go_lib_code.borrow().as_str())?;
+        artifacts.go_files.push(self.config.go_lib_file.clone());
+
+        // write go link code, which is left out of the cgobin build, since the Rust C library does not exist yet
+        self.write_file(
+            &self.config.go_link_file,
+            &format!(
+                r###"// Code generated by fcplug. DO NOT EDIT.
+
+            //go:build !fcplug_fake && !fcplug_cgobin
+
+            package {go_mod_name}
+
+            /*
+            #cgo LDFLAGS: -L{clib_dir_relative_root} -l{rust_clib_name_base} -ldl -lm
+            */
+            import "C"
+            "###
+            ),
+        )?;
+        artifacts.go_files.push(self.config.go_link_file.clone());
+
+        // write go fake code, which replaces the lib code without the Rust C library
+        let go_fake_code = self.go_fake_code.borrow().clone();
+        let go_lib_fake_common = &mid_output.go_lib_fake_common;
+        let go_lib_common = &mid_output.go_lib_common;
+        self.write_file(
+            &self.config.go_fake_file,
+            &format!(
+                r###"// Code generated by fcplug. DO NOT EDIT.
+
+            //go:build fcplug_fake
+
+            package {go_mod_name}
+
+            import (
+                "context"
+                "errors"
+                "fmt"
+                "reflect"
+                "runtime"
+                "sync"
+                "time"
+                "unsafe"
+
+                {lib_imports}
+            )
+
+            var (
+                _ context.Context
+                _ = errors.New
+                _ = fmt.Sprintf
+                _ reflect.SliceHeader
+                _ = runtime.SetFinalizer
+                _ sync.Mutex
+                _ time.Duration
+                _ unsafe.Pointer
+            )
+            {lib_imports_use}
+
+            {go_fake_code}
+
+            {go_lib_common}
+
+            {go_lib_fake_common}
+            "###
+            ),
+        )?;
+        artifacts.go_files.push(self.config.go_fake_file.clone());
 
         // write go main code
-        if self.config.has_goffi {
+        if self.config.has_goffi && !self.config.go_host {
             let mut main_imports = String::new();
             let mut main_imports_use = String::new();
             mid_output
@@ -141,7 +294,33 @@ impl Generator {
                     main_imports = format!("{main_imports}\"{}\"\n   ", v.import_path);
                     main_imports_use = format!("{main_imports_use}{}\n", v.use_code);
                 });
-            let go_main_code = self.go_main_code.borrow().clone();
+            let mut go_main_code = self.go_main_code.borrow().clone();
+            if let Some(obj) = &self.config.go_object_path {
+                let pkg = obj.package_name().unwrap_or_default();
+                main_imports = format!("{main_imports}{pkg} \"{}\"\n   ", obj.import);
+                let object_assigns = self
+                    .config
+                    .goffi_services
+                    .iter()
+                    .map(|name| format!("Global{name} = {}", obj.object_ident))
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                go_main_code.push_str(&format!(
+                    r###"
+        func init() {{
+            {object_assigns}
+        }}
+        "###
+                ));
+            }
+            let go_main_common = &mid_output.go_main_common;
+            // the C shared library contains the Rust C library,
+            // while the Rust symbols of the C archive are resolved when rustc links it
+            let cgobin_ldflags = if self.config.use_goffi_cdylib {
+                format!("-L{clib_dir_relative_cgobin} -l{rust_clib_name_base} -ldl -lm")
+            } else {
+                "-ldl -lm".to_string()
+            };
             *self.go_main_code.borrow_mut() = format!(
                 r###"// Code generated by fcplug. DO NOT EDIT.
 
@@ -149,7 +328,7 @@ impl Generator {
 
         /*
         #cgo CFLAGS: -I{clib_dir_relative_cgobin}
-        #cgo LDFLAGS: -L{clib_dir_relative_cgobin} -l{rust_clib_name_base} -ldl -lm
+        #cgo LDFLAGS: {cgobin_ldflags}
 
         #include "{rust_clib_name_base}.h"
         */
@@ -171,235 +350,443 @@ impl Generator {
         {main_imports_use}
 
         {go_main_code}
+
+        {go_main_common}
         "###
             );
-            deal_result(
-                CODE_IO,
-                std::fs::write(
-                    &self.config.go_main_file,
-                    self.go_main_code.borrow().as_str(),
-                ),
-            );
-            if !self.config.go_main_impl_file.exists() {
-                deal_result(
-                    CODE_IO,
-                    std::fs::write(
-                        &self.config.go_main_impl_file,
-                        format!(
-                            r###"package main
+            self.write_file(
+                &self.config.go_main_file,
+                self.go_main_code.borrow().as_str(),
+            )?;
+            artifacts.go_files.push(self.config.go_main_file.clone());
+            if self.config.go_object_path.is_none()
+                && !self.file_exists(&self.config.go_main_impl_file)
+            {
+                let impl_assigns = self
+                    .config
+                    .goffi_services
+                    .iter()
+                    .map(|name| format!("Global{name} = _Unimplemented{name}{{}}"))
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                self.write_file(
+                    &self.config.go_main_impl_file,
+                    &format!(
+                        r###"package main
 
             func init() {{
                 // TODO: Replace with your own implementation, then re-execute `cargo build`
-                GlobalGoFfi = _UnimplementedGoFfi{{}}
+                {impl_assigns}
             }}
 
             "###
-                        ),
                     ),
-                );
+                )?;
+                artifacts
+                    .go_files
+                    .push(self.config.go_main_impl_file.clone());
             }
         }
 
         // write go mod
-        let _ = if !self.config.gomod_file.exists() {
-            let mod_requires = mid_output
-                .mod_requires
+        let go_requires = self.config.go_requires(&mid_output.mod_requires);
+        if !self.file_exists(&self.config.gomod_file) {
+            let mod_requires = go_requires
                 .iter()
                 .map(|v| v.replace("@", " "))
                 .collect::<Vec<String>>()
                 .join("\n    ");
-            deal_result(
-                CODE_IO,
-                std::fs::write(
-                    &self.config.gomod_file,
-                    format!(
-                        r###"module {}
+            self.write_file(
+                &self.config.gomod_file,
+                &format!(
+                    r###"module {}
 
-            go 1.18
+            go {}
 
             require (
                 {mod_requires}
             )
 
             "###,
-                        &self.config.gomod_path
-                    ),
+                    &self.config.gomod_path,
+                    // log/slog of the log bridge
+                    if self.config.go_log_bridge {
+                        "1.21"
+                    } else {
+                        "1.18"
+                    },
                 ),
-            );
+            )?;
         } else {
-            let mod_content = fs::read_to_string(&self.config.gomod_file).unwrap();
-            for mod_require in &mid_output.mod_requires {
-                if mod_content.contains(
-                    &(mod_require
-                        .splitn(2, "@")
-                        .next()
-                        .unwrap_or_default()
-                        .to_string()
-                        + " "),
-                ) {
+            let mod_content =
+                fs::read_to_string(&self.config.gomod_file).with_path(&self.config.gomod_file)?;
+            for go_require in &go_requires {
+                let (module, version) = go_require.split_once('@').unwrap_or((go_require, ""));
+                // the existing version is kept unless it is pinned by the config
+                if mod_content.contains(&format!("{module} {version}"))
+                    || mod_content.contains(&format!("{module} "))
+                        && !self.config.is_go_require_pinned(go_require)
+                {
                     continue;
                 }
-                deal_output(
-                    Command::new(self.config.go_cmd_path("go"))
-                        .env("GO111MODULE", "on")
-                        .arg("get")
-                        .arg(mod_require)
-                        .output(),
-                );
+                // `go mod edit` never downloads the module, which is left to `go mod tidy`
+                run_command(
+                    self.config
+                        .go_command()
+                        .arg("mod")
+                        .arg("edit")
+                        .arg(format!("-require={go_require}")),
+                )?;
             }
         };
+        artifacts.go_files.push(self.config.gomod_file.clone());
+
+        // the Go tools are not run in the in-memory mode
+        if self.config.in_memory {
+            return Ok(artifacts);
+        }
 
         // format go code
-        let pkg_dir_str = self.config.pkg_dir.to_str().unwrap().to_string();
-        deal_output(
+        run_command(
             Command::new(self.config.go_cmd_path("gofmt"))
                 .arg("-l")
                 .arg("-w")
-                .arg(&pkg_dir_str)
-                .output(),
-        );
-        deal_output(
-            Command::new(self.config.go_cmd_path("go"))
-                .current_dir(&self.config.pkg_dir)
-                .arg("mod")
-                .arg("tidy")
-                .output(),
-        );
+                .arg(&self.config.pkg_dir),
+        )?;
+        // `go mod tidy` may download the modules, and the workspace modules are resolved by go.work instead
+        if !self.config.go_offline && !self.config.go_workspace {
+            run_command(self.config.go_command().arg("mod").arg("tidy"))?;
+            if self.config.go_vendor {
+                run_command(self.config.go_command().arg("mod").arg("vendor"))?;
+            }
+        }
 
         // build go c lib
-        self.gen_go_clib();
+        if self.gen_go_clib()? {
+            artifacts.go_clib_file = Some(self.config.go_clib_file.clone());
+            artifacts.go_clib_header = Some(self.config.go_clib_header.clone());
+        }
+        self.config.save_fingerprint()?;
+        Ok(artifacts)
+    }
+
+    /// The artifacts of the last generation, or None if any of them is missing,
+    /// and the Go C library is linked as it is.
+    fn last_artifacts(&self) -> Option<GeneratedArtifacts> {
+        // the empty files are created by `WorkConfig::init_files`
+        let is_ready = |path: &Path| fs::metadata(path).is_ok_and(|m| m.is_file() && m.len() > 0);
+        let mut artifacts = GeneratedArtifacts {
+            rust_gen_file: self.config.rust_mod_gen_file.clone(),
+            rust_impl_file: self.config.rust_mod_impl_file.clone(),
+            rust_clib_header: self.config.rust_clib_header.clone(),
+            ..Default::default()
+        };
+        if !self.config.goffi_mock {
+            artifacts.go_files = vec![
+                self.config.go_lib_file.clone(),
+                self.config.go_link_file.clone(),
+                self.config.go_fake_file.clone(),
+            ];
+            if self.config.has_goffi && !self.config.go_host {
+                artifacts.go_files.push(self.config.go_main_file.clone());
+                artifacts.go_clib_file = Some(self.config.go_clib_file.clone());
+                artifacts.go_clib_header = Some(self.config.go_clib_header.clone());
+            }
+            artifacts.go_files.push(self.config.gomod_file.clone());
+        }
+        let all_ready = [
+            &artifacts.rust_gen_file,
+            &artifacts.rust_impl_file,
+            &artifacts.rust_clib_header,
+        ]
+        .into_iter()
+        .chain(&artifacts.go_files)
+        .chain(&artifacts.go_clib_file)
+        .chain(&artifacts.go_clib_header)
+        .all(|path| is_ready(path));
+        if !all_ready {
+            return None;
+        }
+        if artifacts.go_clib_file.is_some() {
+            self.config.rustc_link();
+        }
+        Some(artifacts)
     }
 
-    fn gen_rust_clib(&self, with_after_include: &str) {
-        let _ = cbindgen::Builder::new()
-            .with_src(&self.config.rust_mod_gen_file)
+    fn gen_rust_clib(&self, with_after_include: &str) -> Result<(), FcplugError> {
+        let bindings = cbindgen::Builder::new()
+            .with_src(self.work_file(&self.config.rust_mod_gen_file))
             .with_language(cbindgen::Language::C)
             .with_after_include(with_after_include)
             .generate()
-            .inspect(|b| {
-                let _ = b.write_to_file(&self.config.rust_clib_header);
-            })
-            .inspect_err(|e| {
-                exit_with_warning(254, format!("failed to generate rust clib: {e:?}"))
-            });
+            .map_err(|e| FcplugError::Codegen(format!("failed to generate rust clib: {e}")))?;
+        if self.config.in_memory {
+            let mut header = Vec::new();
+            bindings.write(&mut header);
+            self.write_file(
+                &self.config.rust_clib_header,
+                &String::from_utf8_lossy(&header),
+            )?;
+        } else {
+            bindings.write_to_file(&self.config.rust_clib_header);
+        }
+        Ok(())
     }
-    pub(crate) fn gen_go_clib(&self) {
-        if !self.config.has_goffi {
-            return;
+    /// Build the Go C library and link it, returns whether it is built.
+    pub(crate) fn gen_go_clib(&self) -> Result<bool, FcplugError> {
+        if !self.config.has_goffi || self.config.go_host {
+            return Ok(false);
         }
         let go_clib_file = &self.config.go_clib_file;
-        let go_clib_filename = go_clib_file.file_name().unwrap().to_str().unwrap();
+        let go_clib_filename = go_clib_file
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        // only the C shared library links the Rust C library, which does not exist before the crate is built
         let rust_clib_file = &self.config.rust_clib_file;
-        if !rust_clib_file.exists() {
+        let built = !self.config.use_goffi_cdylib || self.config.is_rust_clib_built();
+        if !built {
             println!(
-                "cargo:warning='{}' file does not exist, should re-execute 'cargo build'",
-                rust_clib_file.file_name().unwrap().to_str().unwrap()
+                "cargo:warning='{}' file does not exist or is older than '{}', should re-execute 'cargo build'",
+                rust_clib_file.file_name().unwrap_or_default().to_string_lossy(),
+                self.config
+                    .rust_clib_header
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
             );
         } else {
-            let mut cmd = Command::new(self.config.go_cmd_path("go"));
-            match get_go_os_arch_from_env() {
+            let mut cmd = self.config.go_command();
+            cmd.env("CGO_ENABLED", "1").arg("build");
+            // the workspace mode does not support the vendored modules
+            if self.config.go_vendor && !self.config.go_workspace {
+                cmd.arg("-mod=vendor");
+            }
+            let go_os = match get_go_os_arch(&self.config.target) {
                 Ok((os, arch)) => {
                     cmd.env("GOOS", os.as_ref()).env("GOARCH", arch.as_ref());
+                    Some(os)
                 }
                 Err(e) => {
-                    println!("cargo:warning={e}")
+                    println!("cargo:warning={e}");
+                    None
                 }
+            };
+            if !self.config.use_goffi_cdylib {
+                // the C archive is built against the Rust C header only,
+                // so the Rust symbols are left unresolved in the cgo link check
+                let unresolved = match go_os {
+                    Some(GoOS::darwin | GoOS::ios) => "-Wl,-undefined,dynamic_lookup",
+                    _ => "-Wl,--unresolved-symbols=ignore-all",
+                };
+                let ldflags = match env::var("CGO_LDFLAGS") {
+                    Ok(ldflags) if !ldflags.is_empty() => format!("{ldflags} {unresolved}"),
+                    _ => unresolved.to_string(),
+                };
+                cmd.env("CGO_LDFLAGS", ldflags).arg("-tags=fcplug_cgobin");
             }
-            deal_output(
-                cmd.env("CGO_ENABLED", "1")
-                    .arg("build")
-                    .arg(format!("-buildmode={}", self.config.go_buildmode))
-                    .arg(format!("-o={}", go_clib_file.to_str().unwrap()))
-                    .arg(self.config.go_main_dir.to_str().unwrap())
-                    .output(),
-            );
+            run_command(
+                cmd.arg(format!("-buildmode={}", self.config.go_buildmode))
+                    .arg(format!("-o={}", go_clib_file.display()))
+                    .arg(&self.config.go_main_dir),
+            )?;
             // lib{go_clib_name_base}.h -> {go_clib_name_base}.h
             if let Err(err) = fs::rename(
-                go_clib_file
-                    .parent()
-                    .unwrap()
-                    .join(go_clib_filename.rsplit_once(".").unwrap().0.to_owned() + ".h"),
+                go_clib_file.with_extension("h"),
                 &self.config.go_clib_header,
             ) {
                 println!("cargo:warning=failed to fix go C lib file name '{}'", err);
             };
             if !go_clib_file.exists() {
-                println!(
-                    "cargo:warning=failed to execute 'go build -buildmode={}', should re-execute 'cargo build' to ensure the correctness of '{}'",
+                return Err(FcplugError::Link(format!(
+                    "'go build -buildmode={}' did not output '{}'",
                     self.config.go_buildmode, go_clib_filename,
-                );
+                )));
             }
             self.config.rustc_link();
         }
-        if self.config.update_fingerprint() {
-            println!("cargo:warning=The crate files has changed, it is recommended to re-execute 'cargo build' to ensure the correctness of '{}'", go_clib_filename);
-        }
+        Ok(built)
     }
-    fn build_code_by_idl(&self) {
+    fn build_code_by_idl(&self) -> Result<(), FcplugError> {
         let include_dirs = vec![self.config.idl_include_dir.clone()];
-        match self.config.idl_type {
-            IdlType::Proto | IdlType::ProtoNoCodec => {
-                pilota_build::Builder::protobuf_with_backend(self.clone())
-                    .doc_header("// Code generated by fcplug. DO NOT EDIT.".to_string())
-                    .include_dirs(include_dirs)
-                    .plugin(AutoDerivePlugin::new(
-                        Arc::new(["#[derive(::serde::Serialize, ::serde::Deserialize)]".into()]),
-                        |_| PredicateResult::GoOn,
-                    ))
-                    .ignore_unused(true)
-                    .compile(
-                        [&self.config.idl_file],
-                        Output::File(self.config.rust_mod_gen_file.clone()),
-                    )
-            }
-            IdlType::Thrift | IdlType::ThriftNoCodec => {
-                pilota_build::Builder::thrift_with_backend(self.clone())
-                    .doc_header("// Code generated by fcplug. DO NOT EDIT.".to_string())
-                    .include_dirs(include_dirs)
-                    .plugin(AutoDerivePlugin::new(
-                        Arc::new(["#[derive(::serde::Serialize, ::serde::Deserialize)]".into()]),
-                        |_| PredicateResult::GoOn,
-                    ))
-                    .ignore_unused(true)
-                    .compile(
-                        [&self.config.idl_file],
-                        Output::File(self.config.rust_mod_gen_file.clone()),
-                    )
-            }
-        }
+        // the declared errors are not used by the services in the IDL
+        let touches = [(
+            self.config.idl_file.clone(),
+            self.config
+                .declared_errors
+                .values()
+                .flat_map(|methods| methods.values().cloned())
+                .collect::<Vec<String>>(),
+        )];
+        // pilota panics on the invalid IDL
+        let compile = || {
+            std::panic::catch_unwind(AssertUnwindSafe(|| match self.config.idl_type {
+                IdlType::Proto | IdlType::ProtoNoCodec => {
+                    pilota_build::Builder::protobuf_with_backend(self.clone())
+                        .doc_header("// Code generated by fcplug. DO NOT EDIT.".to_string())
+                        .include_dirs(include_dirs)
+                        .plugin(AutoDerivePlugin::new(
+                            Arc::new(
+                                ["#[derive(::serde::Serialize, ::serde::Deserialize)]".into()],
+                            ),
+                            |_| PredicateResult::GoOn,
+                        ))
+                        .ignore_unused(true)
+                        .touch(touches)
+                        .compile(
+                            [&self.config.idl_file],
+                            Output::File(self.work_file(&self.config.rust_mod_gen_file)),
+                        )
+                }
+                IdlType::Thrift | IdlType::ThriftNoCodec => {
+                    pilota_build::Builder::thrift_with_backend(self.clone())
+                        .doc_header("// Code generated by fcplug. DO NOT EDIT.".to_string())
+                        .include_dirs(include_dirs)
+                        .plugin(AutoDerivePlugin::new(
+                            Arc::new(
+                                ["#[derive(::serde::Serialize, ::serde::Deserialize)]".into()],
+                            ),
+                            |_| PredicateResult::GoOn,
+                        ))
+                        .ignore_unused(true)
+                        .touch(touches)
+                        .compile(
+                            [&self.config.idl_file],
+                            Output::File(self.work_file(&self.config.rust_mod_gen_file)),
+                        )
+                }
+            }))
+        };
+        self.config.run_pilota(compile)?.map_err(|e| {
+            FcplugError::Codegen(
+                e.downcast_ref::<String>()
+                    .cloned()
+                    .or_else(|| e.downcast_ref::<&str>().map(|msg| msg.to_string()))
+                    .unwrap_or_else(|| "pilota panicked".to_string()),
+            )
+        })
     }
 
-    fn rust_gen_more_code(&self) {
-        let mut rust_code = std::fs::read_to_string(&self.config.rust_mod_gen_file).unwrap();
-        if !self.config.has_rustffi {
-            rust_code.push_str(&format!("pub(super) trait RustFfi {{}}"));
+    fn rust_gen_more_code(&self) -> Result<(), FcplugError> {
+        let rust_gen_work_file = self.work_file(&self.config.rust_mod_gen_file);
+        let mut rust_code =
+            std::fs::read_to_string(&rust_gen_work_file).with_path(&rust_gen_work_file)?;
+        let rust_impl_name = &self.config.rust_mod_impl_name;
+        let mut ffi_traits = self.config.rustffi_services.clone();
+        let mut impl_call_traits = String::new();
+        for name in &self.config.goffi_services {
+            ffi_traits.push(name.clone());
+            ffi_traits.push(format!("{name}Call"));
+            impl_call_traits.push_str(&format!("impl {name}Call for {rust_impl_name} {{}}\n"));
         }
-        if !self.config.has_goffi {
-            rust_code.push_str(&format!("pub(super) trait GoFfi {{}}"));
-            rust_code.push_str(&format!("pub trait GoFfiCall {{}}"));
+        let ffi_traits = if ffi_traits.is_empty() {
+            String::new()
+        } else {
+            format!(": {}", ffi_traits.join(" + "))
+        };
+        let mut declared_errors = self
+            .config
+            .declared_errors
+            .values()
+            .flat_map(|methods| methods.values())
+            .map(|error| (&**error).struct_ident().to_string())
+            .collect::<Vec<String>>();
+        declared_errors.sort();
+        declared_errors.dedup();
+        for error in declared_errors {
+            rust_code.push_str(&format!("impl ::fcplug::DeclaredError for {error} {{}}\n"));
         }
-        let rust_impl_name = &self.config.rust_mod_impl_name;
-        rust_code.push_str(&format!(
-            r###"trait Ffi: RustFfi + GoFfi + GoFfiCall {{}}
+        let (impl_struct, ffi_impl) = if self.config.has_rust_impl_type {
+            (
+                String::new(),
+                format!(
+                    r###"static FFI_IMPL: ::std::sync::OnceLock<&'static {rust_impl_name}> = ::std::sync::OnceLock::new();
+                    if let Some(ffi_impl) = FFI_IMPL.get() {{
+                        return Ok(ffi_impl);
+                    }}
+                    let ffi_impl = ::fcplug::rust_ffi::<{rust_impl_name}>()?;
+                    Ok(*FFI_IMPL.get_or_init(|| ffi_impl))"###
+                ),
+            )
+        } else {
+            (
+                format!("pub struct {rust_impl_name};\n"),
+                format!("Ok(&{rust_impl_name})"),
+            )
+        };
+        if self.config.has_rustffi {
+            rust_code.push_str(&format!(
+                r###"
+            /// The instance which the RustFFI exports dispatch to, unavailable until it is registered
+            #[inline]
+            fn ffi_impl() -> ::fcplug::ABIResult<&'static {rust_impl_name}> {{
+                {ffi_impl}
+            }}
+            "###
+            ));
+        }
+        if self.config.has_goffi && !self.config.go_host {
+            rust_code.push_str(&format!(
+                r###"
+            #[cfg(not(feature = "goffi-mock"))]
+            #[link(name = "{}", kind = "{}")]
+            extern "C" {{
+                fn fcplug_go_init(config: ::fcplug::Buffer) -> ::fcplug::GoFfiResult;
+                fn fcplug_go_health() -> ::fcplug::GoFfiResult;
+                fn fcplug_go_shutdown() -> ::fcplug::GoFfiResult;
+            }}
+
+            /// The lifecycle of the Go side, whose hooks are `GlobalGoLifecycle` in the Go package.
+            pub struct GoLifecycle;
+
+            #[cfg(not(feature = "goffi-mock"))]
+            impl GoLifecycle {{
+                pub fn init(config: &[u8]) -> ::fcplug::ABIResult<()> {{
+                    let mut config = config.to_vec();
+                    ::fcplug::ABIResult::from(unsafe {{ fcplug_go_init(::fcplug::Buffer::from_vec_mut(&mut config)) }})
+                }}
+                pub fn health() -> ::fcplug::ABIResult<()> {{
+                    ::fcplug::ABIResult::from(unsafe {{ fcplug_go_health() }})
+                }}
+                pub fn shutdown() -> ::fcplug::ABIResult<()> {{
+                    ::fcplug::ABIResult::from(unsafe {{ fcplug_go_shutdown() }})
+                }}
+            }}
 
-        pub struct {rust_impl_name};
+            /// The Go side is mocked, so its lifecycle does nothing.
+            #[cfg(feature = "goffi-mock")]
+            impl GoLifecycle {{
+                pub fn init(_config: &[u8]) -> ::fcplug::ABIResult<()> {{
+                    Ok(())
+                }}
+                pub fn health() -> ::fcplug::ABIResult<()> {{
+                    Ok(())
+                }}
+                pub fn shutdown() -> ::fcplug::ABIResult<()> {{
+                    Ok(())
+                }}
+            }}
+            "###,
+                self.config.go_clib_name_base, self.config.rustc_link_kind_goffi,
+            ));
+        }
+        rust_code.push_str(&format!(
+            r###"trait Ffi{ffi_traits} {{}}
 
-        impl GoFfiCall for {rust_impl_name} {{}}
-        impl Ffi for {rust_impl_name} {{}}
+        {impl_struct}
+        {impl_call_traits}impl Ffi for {rust_impl_name} {{}}
         "###
         ));
-        std::fs::write(&self.config.rust_mod_gen_file, rust_code).unwrap();
-        fmt_file(&self.config.rust_mod_gen_file);
+        self.write_rust_file(&self.config.rust_mod_gen_file, &rust_code)?;
 
-        if !self.config.rust_mod_impl_file.exists() {
+        if !self.file_exists(&self.config.rust_mod_impl_file) {
             let rust_mod_impl_code = self.rust_mod_impl_code.borrow();
             let mod_gen_name = &self.config.rust_mod_gen_name;
-            deal_result(
-                CODE_IO,
-                std::fs::write(
-                    &self.config.rust_mod_impl_file,
-                    &format!(
-                        r###"#![allow(unused_variables)]
+            self.write_rust_file(
+                &self.config.rust_mod_impl_file,
+                &format!(
+                    r###"#![allow(unused_variables)]
 
                 pub use {mod_gen_name}::*;
 
@@ -407,11 +794,52 @@ impl Generator {
 
                 {rust_mod_impl_code}
                 "###
-                    ),
                 ),
-            );
-            fmt_file(&self.config.rust_mod_impl_file);
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The file actually written for the path, which is in the scratch directory in the in-memory mode
+    fn work_file(&self, path: &Path) -> PathBuf {
+        if self.config.in_memory {
+            self.config
+                .target_out_dir
+                .join(path.file_name().unwrap_or_default())
+        } else {
+            path.to_path_buf()
+        }
+    }
+
+    /// The existing file is kept, except in the in-memory mode which always generates it
+    fn file_exists(&self, path: &Path) -> bool {
+        !self.config.in_memory && path.exists()
+    }
+
+    fn write_file(&self, path: &Path, content: &str) -> Result<(), FcplugError> {
+        if !self.config.in_memory {
+            return fs::write(path, content).with_path(path);
+        }
+        let key = path
+            .strip_prefix(&self.config.pkg_dir)
+            .or_else(|_| path.strip_prefix(&self.config.clib_gen_dir))
+            .unwrap_or(path);
+        self.memory_files
+            .borrow_mut()
+            .insert(key.to_path_buf(), content.to_string());
+        Ok(())
+    }
+
+    /// Write the Rust file formatted by rustfmt
+    fn write_rust_file(&self, path: &Path, content: &str) -> Result<(), FcplugError> {
+        let work_file = self.work_file(path);
+        fs::write(&work_file, content).with_path(&work_file)?;
+        fmt_file(&work_file);
+        if self.config.in_memory {
+            let content = fs::read_to_string(&work_file).with_path(&work_file)?;
+            self.write_file(path, &content)?;
         }
+        Ok(())
     }
 }
 
@@ -435,6 +863,8 @@ impl MakeBackend for Generator {
                 context: Cx(context.clone()),
                 go_lib_code: self.go_lib_code.clone(),
                 go_main_code: self.go_main_code.clone(),
+                go_lib_cdecls: self.go_lib_cdecls.clone(),
+                go_fake_code: self.go_fake_code.clone(),
             },
             context: Cx(context),
             config: self.config,
@@ -464,6 +894,12 @@ pub(crate) trait GoCodegenBackend {
         method: &Arc<Method>,
     ) -> Option<(String, String)>;
     fn codegen_rustffi_service_impl(&self, service_def_id: DefId, s: &Service) -> String;
+    /// The stub field and the method of the fake built with the `fcplug_fake` tag
+    fn codegen_rustffi_fake_method(
+        &self,
+        service_def_id: DefId,
+        method: &Arc<Method>,
+    ) -> Option<(String, String)>;
     fn codegen_goffi_iface_method(
         &self,
         service_def_id: DefId,
@@ -498,6 +934,8 @@ pub(crate) struct GoGeneratorBackend {
     pub(crate) context: Cx,
     pub(crate) go_lib_code: Arc<RefCell<String>>,
     pub(crate) go_main_code: Arc<RefCell<String>>,
+    pub(crate) go_lib_cdecls: Arc<RefCell<String>>,
+    pub(crate) go_fake_code: Arc<RefCell<String>>,
 }
 
 #[derive(Clone)]
@@ -516,14 +954,52 @@ pub(crate) enum ServiceType {
     GoFfi,
 }
 
+impl ServiceType {
+    /// Determine the service type by the name suffix, case-insensitively,
+    /// e.g. `RustFFI`, `UserRustFFI`, `order_go_ffi`.
+    pub(crate) fn from_name(name: &str) -> Option<ServiceType> {
+        let name = name.to_lowercase().replace(['_', '-'], "");
+        if name.ends_with("rustffi") {
+            Some(ServiceType::RustFfi)
+        } else if name.ends_with("goffi") {
+            Some(ServiceType::GoFfi)
+        } else {
+            None
+        }
+    }
+}
+
 impl Cx {
     pub(crate) fn service_type(&self, service_def_id: DefId) -> ServiceType {
-        match self.rust_name(service_def_id).to_lowercase().as_str() {
-            "rustffi" => ServiceType::RustFfi,
-            "goffi" => ServiceType::GoFfi,
-            _ => {
-                unreachable!()
-            }
+        match self.item(service_def_id).unwrap().as_ref() {
+            Item::Service(s) => ServiceType::from_name(&s.name).unwrap_or_else(|| unreachable!()),
+            _ => unreachable!(),
+        }
+    }
+    /// The service name in the IDL
+    pub(crate) fn service_name(&self, service_def_id: DefId) -> String {
+        match self.item(service_def_id).unwrap().as_ref() {
+            Item::Service(s) => s.name.to_string(),
+            _ => unreachable!(),
+        }
+    }
+    /// The struct ident of the error message declared for the method in the IDL,
+    /// only the methods with message result can declare an error.
+    pub(crate) fn declared_error(
+        &self,
+        config: &WorkConfig,
+        service_def_id: DefId,
+        method: &Method,
+    ) -> Option<String> {
+        if method.ret.is_scalar() && !self.is_empty_ty(&method.ret.kind) {
+            // the scalar result is returned directly, without error
+            return None;
+        }
+        match self.item(service_def_id).unwrap().as_ref() {
+            Item::Service(s) => config
+                .declared_error(&s.name, &method.name)
+                .map(|error| (&**error).struct_ident().to_string()),
+            _ => None,
         }
     }
     pub(crate) fn is_empty_ty(&self, kind: &TyKind) -> bool {
@@ -606,6 +1082,22 @@ impl GoGeneratorBackend {
             TyKind::Arc(ty) => format!("*{}", self.go_codegen_item_ty(&ty.kind, is_main)),
         }
     }
+    /// The C type of the FFI argument, declared in the Go host mode
+    pub(crate) fn c_arg_type(&self, ty: &TyKind) -> &'static str {
+        match ty {
+            TyKind::U8 => "uint8_t",
+            TyKind::Bool => "bool",
+            TyKind::I8 => "int8_t",
+            TyKind::I16 => "int16_t",
+            TyKind::I32 => "int32_t",
+            TyKind::I64 => "int64_t",
+            TyKind::UInt32 => "uint32_t",
+            TyKind::UInt64 => "uint64_t",
+            TyKind::F32 => "float",
+            TyKind::F64 => "double",
+            _ => "Buffer",
+        }
+    }
     pub(crate) fn arg_name(&self, arg: &Arc<Arg>) -> String {
         arg.name.0.to_lowercase()
     }
@@ -641,6 +1133,15 @@ impl GoGeneratorBackend {
     pub(crate) fn iface_method_name(&self, method: &Arc<Method>) -> String {
         method.name.0.upper_camel_ident().to_string()
     }
+    /// The code of the package where the GoFFI is implemented,
+    /// it is the lib package in the Go host mode, otherwise the `cgobin` main package.
+    pub(crate) fn goffi_code(&self) -> &Arc<RefCell<String>> {
+        if self.config.go_host {
+            &self.go_lib_code
+        } else {
+            &self.go_main_code
+        }
+    }
     pub(crate) fn ffi_func_name(&self, service_def_id: DefId, method: &Arc<Method>) -> String {
         let service_name_lower = self.context.rust_name(service_def_id).to_lowercase();
         let method_name_lower = (&**method.name).fn_ident();
@@ -735,9 +1236,14 @@ impl CodegenBackend for GeneratorBackend {
             .filter(|m| !m.is_empty())
             .collect::<Vec<String>>();
         let name = self.context.rust_name(service_def_id);
-        methods.push("".to_string());
-        let trait_methods = methods.join(";\n");
-        let impl_trait_methods = methods.join(" { todo!() }\n");
+        // the GoFFI trait methods have default bodies
+        let (trait_methods, impl_trait_methods) = match service_type {
+            ServiceType::RustFfi => {
+                methods.push("".to_string());
+                (methods.join(";\n"), methods.join(" { todo!() }\n"))
+            }
+            ServiceType::GoFfi => (methods.join("\n"), String::new()),
+        };
         stream.push_str(&format! {r#"
             pub(super) trait {name} {{
                 {trait_methods}
@@ -769,10 +1275,13 @@ impl CodegenBackend for GeneratorBackend {
             }
         }
         // go
+        let name = self.context.rust_name(service_def_id);
         match service_type {
             ServiceType::RustFfi => {
                 let mut iface_methods = String::new();
                 let mut impl_methods = String::new();
+                let mut fake_fields = String::new();
+                let mut fake_methods = String::new();
                 for method in &s.methods {
                     if let Some((iface_method, impl_method)) =
                         self.go.codegen_rustffi_iface_method(service_def_id, method)
@@ -781,24 +1290,61 @@ impl CodegenBackend for GeneratorBackend {
                         impl_methods.push_str(&format!(
                             r###"
                     //go:inline
-                    func (RustFfiImpl) {iface_method} {{
+                    func ({name}Impl) {iface_method} {{
                         {impl_method}
                     }}
                     "###
                         ));
                     }
+                    if let Some((fake_field, fake_method)) =
+                        self.go.codegen_rustffi_fake_method(service_def_id, method)
+                    {
+                        fake_fields.push_str(&format!("{fake_field}\n"));
+                        fake_methods.push_str(&fake_method);
+                    }
                 }
                 self.go.go_lib_code.borrow_mut().push_str(&format!(
                     r###"
-                        var GlobalRustFfi RustFfi = RustFfiImpl{{}}
+                        var Global{name} {name} = {name}Impl{{}}
 
-                        type RustFfi interface {{
+                        type {name} interface {{
                             {iface_methods}
                         }}
-                        type RustFfiImpl struct{{}}
+                        type {name}Impl struct{{}}
                         {impl_methods}
                         "###
                 ));
+                self.go.go_fake_code.borrow_mut().push_str(&format!(
+                    r###"
+                        var Global{name} {name} = &{name}Fake{{}}
+
+                        type {name} interface {{
+                            {iface_methods}
+                        }}
+
+                        // {name}Fake the fake of {name} for the Go tests without the Rust library,
+                        // each method calls its stub func and records the call.
+                        type {name}Fake struct {{
+                            mu    sync.Mutex
+                            calls []FakeCall
+                            {fake_fields}
+                        }}
+
+                        func (f *{name}Fake) record(method string, args ...any) {{
+                            f.mu.Lock()
+                            defer f.mu.Unlock()
+                            f.calls = append(f.calls, FakeCall{{Method: method, Args: args}})
+                        }}
+
+                        // Calls returns the recorded calls
+                        func (f *{name}Fake) Calls() []FakeCall {{
+                            f.mu.Lock()
+                            defer f.mu.Unlock()
+                            return append([]FakeCall(nil), f.calls...)
+                        }}
+                        {fake_methods}
+                        "###
+                ));
             }
             ServiceType::GoFfi => {
                 let mut iface_body = String::new();
@@ -807,22 +1353,22 @@ impl CodegenBackend for GeneratorBackend {
                     if let Some(m) = self.go.codegen_goffi_iface_method(service_def_id, method) {
                         iface_body.push_str(&format!("{m}\n"));
                         impl_methods.push_str(&format!(
-                            r###"func (_UnimplementedGoFfi) {m} {{
+                            r###"func (_Unimplemented{name}) {m} {{
                         panic("unimplemented")
                     }}
                     "###
                         ));
                     }
                 }
-                self.go.go_main_code.borrow_mut().push_str(&format!(
+                self.go.goffi_code().borrow_mut().push_str(&format!(
                     r###"
 
-                        var GlobalGoFfi GoFfi = _UnimplementedGoFfi{{}}
+                        var Global{name} {name} = _Unimplemented{name}{{}}
 
-                        type GoFfi interface {{
+                        type {name} interface {{
                             {iface_body}
                         }}
-                        type _UnimplementedGoFfi struct{{}}
+                        type _Unimplemented{name} struct{{}}
                         {impl_methods}
 
                         "###
@@ -831,14 +1377,14 @@ impl CodegenBackend for GeneratorBackend {
         }
 
         match service_type {
-            ServiceType::RustFfi => self
-                .go
-                .go_lib_code
-                .borrow_mut()
-                .push_str(&self.go.codegen_rustffi_service_impl(service_def_id, &s)),
+            ServiceType::RustFfi => {
+                let code = self.go.codegen_rustffi_service_impl(service_def_id, &s);
+                self.go.go_lib_code.borrow_mut().push_str(&code);
+                self.go.go_fake_code.borrow_mut().push_str(&code);
+            }
             ServiceType::GoFfi => self
                 .go
-                .go_main_code
+                .goffi_code()
                 .borrow_mut()
                 .push_str(&self.go.codegen_goffi_service_impl(service_def_id, &s)),
         }
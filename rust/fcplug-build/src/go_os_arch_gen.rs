@@ -20,7 +20,8 @@ pub enum GoOS {
 
 #[derive(strum::AsRefStr, strum::EnumString)]
 pub enum GoArch {
-    #[strum(serialize = "386")]_386,
+    #[strum(serialize = "386")]
+    _386,
     amd64,
     arm,
     arm64,
@@ -2,17 +2,19 @@
 #![allow(dead_code)]
 #![feature(trait_alias)]
 
-use std::fmt::Debug;
-use std::io;
-use std::process::Output as CmdOutput;
-
-use backtrace::Backtrace;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
 pub use config::{Config, GoObjectPath, UnitLikeStructPath};
+pub use error::FcplugError;
+pub use generator::GeneratedArtifacts;
 
+use crate::error::IoContext;
 use crate::generator::Generator;
 
 mod config;
+mod error;
 mod generator;
 mod go_os_arch_gen;
 mod os_arch;
@@ -38,70 +40,358 @@ const BUILD_MODE: &'static str = "release";
 #[cfg(debug_assertions)]
 const BUILD_MODE: &'static str = "debug";
 
-pub fn generate_code(config: Config) {
+/// Generate the Rust, Go and C code by the IDL, and build the Go C library,
+/// which is called in `build.rs` of the target crate.
+pub fn generate_code(config: Config) -> Result<GeneratedArtifacts, FcplugError> {
     Generator::generate(config)
 }
 
-const CODE_UNKNOWN: i32 = -1;
-const CODE_CMD_UNKNOWN: i32 = -2;
-const CODE_IO: i32 = -3;
-const CODE_GIT: i32 = -4;
+/// Generate the code into memory, without writing the crate files or running the Go tools,
+/// e.g. for the snapshot tests of the generated code.
+///
+/// The contents are keyed by the path relative to the crate directory, e.g. `src/echo_ffi/echo_gen.rs`
+/// and `go.mod`, except the C header of the Rust library which is keyed by its file name, e.g. `echo.h`.
+/// The existing crate files such as `go.mod` are ignored, and the Go code generated by `protoc` or `thriftgo`
+/// is not included.
+pub fn generate_code_in_memory(config: Config) -> Result<BTreeMap<PathBuf, String>, FcplugError> {
+    Generator::generate_in_memory(config)
+}
 
-fn exit_with_warning(code: i32, message: impl AsRef<str>) {
-    let mut frames = vec![];
-    for bf in Backtrace::new().frames()[4..].as_ref() {
-        if bf
-            .symbols()
-            .get(0)
-            .unwrap()
-            .name()
-            .unwrap()
-            .to_string()
-            .starts_with("build_script_build::main::")
-        {
-            break;
-        }
-        frames.push(bf.clone());
+/// Run the command, the stderr of the successful command is reported as the cargo warning.
+fn run_command(cmd: &mut Command) -> Result<(), FcplugError> {
+    let program = cmd.get_program().to_string_lossy().to_string();
+    let output = cmd.output().map_err(|e| FcplugError::Command {
+        program: program.clone(),
+        code: None,
+        stderr: e.to_string(),
+    })?;
+    if !output.status.success() {
+        return Err(FcplugError::Command {
+            program,
+            code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        });
     }
-    println!(
-        "cargo:warning={}\nbacktrace:\n{:?}",
-        message.as_ref(),
-        Backtrace::from(frames)
-    );
-    std::process::exit(code);
+    if output.stderr.is_empty() {
+        println!("{output:?}");
+    } else {
+        println!(
+            "cargo:warning={:?}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(())
 }
 
-fn deal_result<T, E: Debug>(code: i32, result: Result<T, E>) -> T {
-    match result {
-        Ok(t) => t,
-        Err(e) => {
-            exit_with_warning(code, format!("{e:?}"));
-            unreachable!()
-        }
+/// Run `f` with the stdout of the process redirected into the file, and return what it printed,
+/// since pilota prints to the stdout unconditionally.
+/// The stdout lock is held, so the other threads do not print into the file.
+#[cfg(unix)]
+pub(crate) fn redirect_stdout<R>(
+    file: &Path,
+    f: impl FnOnce() -> R,
+) -> Result<(R, String), FcplugError> {
+    use std::io::Write;
+    use std::os::fd::AsRawFd;
+
+    let out = std::fs::File::create(file).with_path(file)?;
+    let mut stdout = std::io::stdout().lock();
+    let _ = stdout.flush();
+    // SAFETY: the descriptors are valid, and the stdout is restored before returning
+    let saved = unsafe { libc::dup(libc::STDOUT_FILENO) };
+    if saved < 0 {
+        return Err(std::io::Error::last_os_error()).with_path(file);
+    }
+    if unsafe { libc::dup2(out.as_raw_fd(), libc::STDOUT_FILENO) } < 0 {
+        let err = std::io::Error::last_os_error();
+        unsafe { libc::close(saved) };
+        return Err(err).with_path(file);
     }
+    let ret = f();
+    let _ = stdout.flush();
+    unsafe {
+        libc::dup2(saved, libc::STDOUT_FILENO);
+        libc::close(saved);
+    }
+    let printed = std::fs::read_to_string(file).with_path(file)?;
+    Ok((ret, printed))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn redirect_stdout<R>(
+    _file: &Path,
+    f: impl FnOnce() -> R,
+) -> Result<(R, String), FcplugError> {
+    Ok((f(), String::new()))
+}
+
+/// The environment variable set by cargo for the build script
+fn cargo_env(key: &str) -> Result<String, FcplugError> {
+    std::env::var(key).map_err(|e| {
+        FcplugError::Config(format!(
+            "{e}: {key}, generate_code should be called in build.rs"
+        ))
+    })
+}
+
+#[test]
+fn test_generate_code_in_memory() {
+    use std::path::Path;
+
+    let crate_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../samples/echo_pb");
+    let config = Config {
+        idl_file: crate_dir.join("echo.proto"),
+        target_crate_dir: Some(crate_dir),
+        go_mod_parent: "github.com/andeya/fcplug/samples".to_string(),
+        ..Default::default()
+    };
+    let files = generate_code_in_memory(config.clone()).unwrap();
+    assert_eq!(
+        files
+            .keys()
+            .map(|k| k.to_str().unwrap())
+            .collect::<Vec<_>>(),
+        [
+            "cgobin/clib_goffi_gen.go",
+            "cgobin/clib_goffi_impl.go",
+            "echo_pb.h",
+            "echo_pb_gen.go",
+            "echo_pb_gen_fake.go",
+            "echo_pb_gen_link.go",
+            "go.mod",
+            "src/echo_pb_ffi/echo_pb_gen.rs",
+            "src/echo_pb_ffi/mod.rs",
+        ]
+    );
+    assert!(
+        files[Path::new("go.mod")].starts_with("module github.com/andeya/fcplug/samples/echo_pb\n")
+    );
+    assert!(
+        files[Path::new("echo_pb_gen_link.go")].contains("#cgo LDFLAGS: -Ltarget/debug -lecho_pb")
+    );
+    // the Go C archive is built against the Rust C header only
+    assert!(!files[Path::new("cgobin/clib_goffi_gen.go")].contains("-lecho_pb"));
+    assert!(files[Path::new("echo_pb.h")].contains("rustffi_echo_rs"));
+    assert!(files[Path::new("src/echo_pb_ffi/mod.rs")].contains("impl RustFfi for FfiImpl"));
+    // the exports fail with `RC_UNAVAILABLE` until the RustFFI instance is registered
+    let rust_gen = &files[Path::new("src/echo_pb_ffi/echo_pb_gen.rs")];
+    assert!(rust_gen.contains("fn ffi_impl() -> ::fcplug::ABIResult<&'static FfiImpl>"));
+    assert!(rust_gen.contains("Err(err) => return ::fcplug::RustFfiResult::from_err(err),"));
+    // the unsafe GoFFI calls also take the encoded result by `Vec<u8>`
+    assert!(rust_gen.contains(".into_result::<T, Pong, ::fcplug::protobuf::PbMessage<_>>()"));
+    assert!(files[Path::new("go.mod")].contains("github.com/bytedance/sonic v1.9.1\n"));
+    // the output is stable for the snapshot tests
+    assert_eq!(files, generate_code_in_memory(config.clone()).unwrap());
+
+    // the GoFFI calls fail with `RC_UNAVAILABLE` until the Go host registers the functions
+    let files = generate_code_in_memory(Config {
+        go_host: true,
+        ..config.clone()
+    })
+    .unwrap();
+    let rust_gen = &files[Path::new("src/echo_pb_ffi/echo_pb_gen.rs")];
+    assert!(rust_gen.contains("None => ::fcplug::GoFfiResult::from_err(::fcplug::FfiError::new("));
+    assert!(rust_gen.contains("\"GoFFI `goffi_echo_go` is not registered by the Go host\""));
+    assert!(!rust_gen.contains(".expect(\"GoFFI"));
+    // so do the GoFFI calls without an installed mock
+    assert!(rust_gen.contains("\"the GoFFI mock of `GoFfi::echo_go` is not installed, install it by `GoFfiMock::echo_go`\""));
+    assert!(!rust_gen.contains(".expect(\"the GoFFI mock"));
+
+    // the versions of the Go modules are pinned by the config
+    let files = generate_code_in_memory(Config {
+        go_requires: vec!["github.com/bytedance/sonic@v1.10.0".to_string()],
+        ..config
+    })
+    .unwrap();
+    let gomod = &files[Path::new("go.mod")];
+    assert!(gomod.contains("github.com/bytedance/sonic v1.10.0\n"));
+    assert!(!gomod.contains("github.com/bytedance/sonic v1.9.1"));
+}
+
+#[test]
+fn test_generate_declared_errors_in_memory() {
+    use std::path::Path;
+
+    let crate_dir =
+        std::env::temp_dir().join(format!("fcplug-test-{}/echo_err", std::process::id()));
+    std::fs::create_dir_all(&crate_dir).unwrap();
+    let idl_file = crate_dir.join("echo_err.thrift");
+    std::fs::write(
+        &idl_file,
+        r#"
+struct Ping {
+    1: string msg,
+}
+exception EchoError {
+    1: string msg,
+}
+struct StructError {
+    1: string msg,
+}
+service RustFFI {
+    Ping echo_rs (1: Ping req) throws (1: EchoError err),
+    Ping echo_multiline (
+        1: Ping req,
+    ) throws (
+        1: StructError err,
+    )
+}
+"#,
+    )
+    .unwrap();
+    let files = generate_code_in_memory(Config {
+        idl_file,
+        target_crate_dir: Some(crate_dir.clone()),
+        go_mod_parent: "github.com/andeya/fcplug/samples".to_string(),
+        ..Default::default()
+    });
+    std::fs::remove_dir_all(crate_dir.parent().unwrap()).unwrap();
+    let files = files.unwrap();
+    let go_gen = &files[Path::new("echo_err_gen.go")];
+    // thriftgo generates the `Error()` method of the exceptions, but not of the structs
+    assert!(go_gen.contains("func (x *StructError) Error() string {"));
+    assert!(!go_gen.contains("func (x *EchoError) Error() string {"));
+    let rust_gen = &files[Path::new("src/echo_err_ffi/echo_err_gen.rs")];
+    assert_eq!(
+        rust_gen
+            .matches("RustFfiResult::from_typed::<_, _, ")
+            .count(),
+        2
+    );
 }
 
-fn deal_output(output: io::Result<CmdOutput>) {
-    match output {
-        Ok(output) => {
-            if !output.status.success() {
-                exit_with_warning(
-                    output.status.code().unwrap_or(CODE_CMD_UNKNOWN),
-                    format!("{output:?}"),
-                );
-            } else {
-                if output.stderr.is_empty() {
-                    println!("{output:?}");
-                } else {
-                    println!(
-                        "cargo:warning={:?}",
-                        String::from_utf8(output.stderr.clone()).unwrap_or(format!("{output:?}"))
-                    );
-                }
-            }
-        }
-        Err(e) => {
-            exit_with_warning(CODE_UNKNOWN, format!("{e:?}"));
-        }
+#[test]
+fn test_generate_invalid_idl_in_memory() {
+    let crate_dir = std::env::temp_dir()
+        .join(format!("fcplug-test-invalid-{}", std::process::id()))
+        .join("echo_invalid");
+    std::fs::create_dir_all(&crate_dir).unwrap();
+    let idl_file = crate_dir.join("echo_invalid.thrift");
+    std::fs::write(&idl_file, "service Echo {\n    i32 add (1: i32 a),\n}\n").unwrap();
+    let ret = generate_code_in_memory(Config {
+        idl_file,
+        target_crate_dir: Some(crate_dir.clone()),
+        go_mod_parent: "github.com/andeya/fcplug/samples".to_string(),
+        ..Default::default()
+    });
+    std::fs::remove_dir_all(crate_dir.parent().unwrap()).unwrap();
+    assert!(matches!(ret, Err(FcplugError::IdlCheck(_))), "{ret:?}");
+    // the scratch directory does not leak on the errors
+    let scratch_prefix = format!("fcplug-echo_invalid-{}-", std::process::id());
+    assert!(!std::fs::read_dir(std::env::temp_dir())
+        .unwrap()
+        .any(|entry| entry
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .starts_with(&scratch_prefix)));
+}
+
+#[test]
+fn test_generate_multiple_services_in_memory() {
+    use std::path::Path;
+
+    let crate_dir = std::env::temp_dir()
+        .join(format!("fcplug-test-multi-{}", std::process::id()))
+        .join("echo_multi");
+    std::fs::create_dir_all(&crate_dir).unwrap();
+    let idl_file = crate_dir.join("echo_multi.proto");
+    let generate = |idl: &str| {
+        std::fs::write(&idl_file, idl).unwrap();
+        generate_code_in_memory(Config {
+            idl_file: idl_file.clone(),
+            target_crate_dir: Some(crate_dir.clone()),
+            go_mod_parent: "github.com/andeya/fcplug/samples".to_string(),
+            ..Default::default()
+        })
+    };
+    let messages = "syntax = \"proto3\";\n\n\
+        message Ping {\n  string msg = 1;\n}\n\n\
+        message Pong {\n  string msg = 1;\n}\n";
+    // the RustFFI services may have the methods of the same name, which are called by their traits
+    let ok = generate(&format!(
+        "{messages}\nservice EchoRustFFI {{\n  rpc echo (Ping) returns (Pong) {{}}\n}}\n\n\
+        service AuditRustFFI {{\n  rpc echo (Ping) returns (Pong) {{}}\n}}\n\n\
+        service EchoGoFFI {{\n  rpc echo (Ping) returns (Pong) {{}}\n}}\n\n\
+        service AuditGoFFI {{\n  rpc audit (Ping) returns (Pong) {{}}\n}}\n"
+    ));
+    // the calls of the GoFFI services are all implemented on `FfiImpl`
+    let conflict = generate(&format!(
+        "{messages}\nservice EchoGoFFI {{\n  rpc echo (Ping) returns (Pong) {{}}\n}}\n\n\
+        service AuditGoFFI {{\n  rpc echo (Ping) returns (Pong) {{}}\n}}\n"
+    ));
+    std::fs::remove_dir_all(crate_dir.parent().unwrap()).unwrap();
+
+    let files = ok.unwrap();
+    let header = &files[Path::new("echo_multi.h")];
+    assert!(header.contains("echorustffi_echo(") && header.contains("auditrustffi_echo("));
+    let rust_gen = &files[Path::new("src/echo_multi_ffi/echo_multi_gen.rs")];
+    assert!(rust_gen.contains("<FfiImpl as EchoRustFfi>::echo("));
+    assert!(rust_gen.contains("<FfiImpl as AuditRustFfi>::echo("));
+    match conflict {
+        Err(FcplugError::Codegen(msg)) => assert!(
+            msg.contains("both have the method 'echo', whose calls conflict on `FfiImpl`"),
+            "{msg}"
+        ),
+        ret => panic!(
+            "unexpected result: {:?}",
+            ret.map(|files| files.into_keys().collect::<Vec<_>>())
+        ),
     }
 }
+
+#[test]
+fn test_generate_scalar_methods_in_memory() {
+    use std::path::Path;
+
+    let crate_dir = std::env::temp_dir()
+        .join(format!("fcplug-test-scalar-{}", std::process::id()))
+        .join("echo_scalar");
+    std::fs::create_dir_all(&crate_dir).unwrap();
+    let idl_file = crate_dir.join("echo_scalar.thrift");
+    std::fs::write(
+        &idl_file,
+        "service RustFFI {\n    i32 add_rs (1: i32 a, 2: i32 b),\n}\n\
+        service GoFFI {\n    i32 add_go (1: i32 a, 2: i32 b),\n}\n",
+    )
+    .unwrap();
+    let config = Config {
+        idl_file,
+        target_crate_dir: Some(crate_dir.clone()),
+        go_mod_parent: "github.com/andeya/fcplug/samples".to_string(),
+        ..Default::default()
+    };
+    let files = generate_code_in_memory(config.clone());
+    let go_host_files = generate_code_in_memory(Config {
+        go_host: true,
+        ..config
+    });
+    std::fs::remove_dir_all(crate_dir.parent().unwrap()).unwrap();
+    let files = files.unwrap();
+    let rust_gen = &files[Path::new("src/echo_scalar_ffi/echo_scalar_gen.rs")];
+    // the scalar exports are not intercepted, but counted as in flight for the shutdown
+    assert!(rust_gen.contains("::fcplug::enter_rust_ffi_scalar(||"));
+    assert!(rust_gen.contains("Err(err) => return ::fcplug::unavailable_scalar(err),"));
+    assert!(rust_gen.contains(
+        "\"the GoFFI mock of `GoFfi::add_go` is not installed, install it by `GoFfiMock::add_go`\""
+    ));
+    assert!(!rust_gen.contains("::fcplug::GoFfiResult::from_err"));
+    // Go returns the scalar result directly
+    assert!(rust_gen
+        .contains("unsafe fn add_go(a: i32, b: i32) -> i32 {\n        goffi_add_go(a, b)\n    }"));
+    assert!(!rust_gen.contains("add_go_set_result"));
+    let go_main = &files[Path::new("cgobin/clib_goffi_gen.go")];
+    assert!(go_main.contains("AddGo(a int32,b int32) int32\n"));
+    assert!(go_main.contains("func goffi_add_go(a int32,b int32) int32 {"));
+
+    // the C declaration of the Go export matches the function pointer registered to Rust
+    let files = go_host_files.unwrap();
+    let rust_gen = &files[Path::new("src/echo_scalar_ffi/echo_scalar_gen.rs")];
+    assert!(rust_gen.contains("OnceLock<extern \"C\" fn(a: i32, b: i32) -> i32>"));
+    let header = &files[Path::new("echo_scalar.h")];
+    assert!(header.contains("void goffi_add_go_register(int32_t (*f)(int32_t a, int32_t b));"));
+    let go_gen = &files[Path::new("echo_scalar_gen.go")];
+    assert!(go_gen.contains("extern int32_t goffi_add_go(int32_t a, int32_t b);\n"));
+    assert!(go_gen.contains("func goffi_add_go(a C.int32_t,b C.int32_t) C.int32_t {"));
+    assert!(!go_gen.contains("GoFfiResult goffi_add_go("));
+}
@@ -1,4 +1,3 @@
-use std::env;
 use std::str::FromStr;
 
 pub(crate) use crate::go_os_arch_gen::{GoArch, GoOS};
@@ -11,17 +10,21 @@ pub(crate) fn parse_target_triple(target_triple: &str) -> Result<(RustOS, RustAr
         .map(ToOwned::to_owned)
         .collect::<Vec<String>>();
     Ok((
-        RustOS::from_str(a.get(2).unwrap_or(&"".to_string())).map_err(|_| { format!("unknown os {:?}", a.get(2)) })?,
-        RustArch::from_str(a.get(0).unwrap_or(&"".to_string())).map_err(|_| { format!("unknown arch {:?}", a.get(0)) })?,
+        RustOS::from_str(a.get(2).unwrap_or(&"".to_string()))
+            .map_err(|_| format!("unknown os {:?}", a.get(2)))?,
+        RustArch::from_str(a.get(0).unwrap_or(&"".to_string()))
+            .map_err(|_| format!("unknown arch {:?}", a.get(0)))?,
     ))
 }
 
-pub(crate) fn parse_target_triple_from_env() -> Result<(RustOS, RustArch), String> {
-    parse_target_triple(env::var("TARGET").unwrap().as_str())
-}
-
-pub(crate) fn get_go_os_arch_from_env() -> Result<(GoOS, GoArch), String> {
-    let (os, arch) = parse_target_triple_from_env()?;
+/// The Go OS and arch of the target triple, which is `TARGET` of the build script or `Config::target`
+pub(crate) fn get_go_os_arch(target_triple: &str) -> Result<(GoOS, GoArch), String> {
+    if target_triple.is_empty() {
+        return Err(
+            "the target triple is unknown, neither TARGET nor Config::target is set".to_string(),
+        );
+    }
+    let (os, arch) = parse_target_triple(target_triple)?;
     Ok((GoOS::try_from(os)?, GoArch::try_from(arch)?))
 }
 
@@ -2,7 +2,8 @@
 
 #[derive(strum::AsRefStr, strum::EnumString)]
 pub enum RustOS {
-    #[strum(serialize = "3ds")]_3ds,
+    #[strum(serialize = "3ds")]
+    _3ds,
     aix,
     android,
     androideabi,
@@ -2,9 +2,10 @@ use std::sync::Arc;
 
 use pilota_build::rir::Method;
 use pilota_build::ty::TyKind;
-use pilota_build::{rir::Service, DefId};
+use pilota_build::{rir::Service, DefId, IdentName};
 
-use crate::generator::{GoCodegenBackend, GoGeneratorBackend};
+use crate::config::IdlType;
+use crate::generator::{Generator, GoCodegenBackend, GoGeneratorBackend};
 
 impl GoCodegenBackend for GoGeneratorBackend {
     // {lib}.go
@@ -50,23 +51,597 @@ impl GoCodegenBackend for GoGeneratorBackend {
             })
             .collect::<Vec<String>>()
             .join(",");
+        let with_declared_error = self
+            .context
+            .declared_error(&self.config, service_def_id, method)
+            .map(|error| format!(".withDeclaredError(unmarshalDeclaredError[{error}])"))
+            .unwrap_or_default();
         Some((
             iface_method,
-            format!("return newRustFfiResult[{ret_type}](C.{ffi_func_name}({args_assign}))"),
+            format!("return newRustFfiResult[{ret_type}](C.{ffi_func_name}({args_assign})){with_declared_error}"),
         ))
     }
     // {lib}.go
-    fn codegen_rustffi_service_impl(&self, _service_def_id: DefId, _s: &Service) -> String {
-        r###"
-type ResultCode = int8
+    fn codegen_rustffi_service_impl(&self, service_def_id: DefId, s: &Service) -> String {
+        let name = self.context.rust_name(service_def_id);
+        let (marshal, unmarshal) = match self.config.idl_type {
+            IdlType::Proto | IdlType::ProtoNoCodec => ("TBytesFromPb", "PbUnmarshal()"),
+            IdlType::Thrift | IdlType::ThriftNoCodec => {
+                ("TBytesFromJson", "Unmarshal(sonic.ConfigStd.Unmarshal)")
+            }
+        };
+        let mut client_methods = String::new();
+        for method in &s.methods {
+            // the scalar result is returned directly, which needs no wrapper
+            if method.ret.is_scalar() && !self.context.is_empty_ty(&method.ret.kind) {
+                continue;
+            }
+            let iface_method_name = self.iface_method_name(method);
+            let args_sign = method
+                .args
+                .iter()
+                .map(|arg| {
+                    if arg.ty.is_scalar() {
+                        format!("{} {}", self.arg_name(arg), self.arg_type(arg, false))
+                    } else {
+                        format!("{} *{}", self.arg_name(arg), self.arg_type(arg, false))
+                    }
+                })
+                .fold("ctx context.Context".to_string(), |acc, v| acc + ", " + &v);
+            let is_empty_ret = self.context.is_empty_ty(&method.ret.kind);
+            let (ret_sign, err_ret) = if is_empty_ret {
+                ("error".to_string(), "err")
+            } else {
+                (
+                    format!("(*{}, error)", self.ret_type(method, false)),
+                    "nil, err",
+                )
+            };
+            let marshal_args = method
+                .args
+                .iter()
+                .filter(|arg| !arg.ty.is_scalar())
+                .map(|arg| {
+                    let arg_name = self.arg_name(arg);
+                    let arg_type = self.arg_type(arg, false);
+                    format!(
+                        r###"_{arg_name}, err := {marshal}[*{arg_type}]({arg_name})
+                    if err != nil {{
+                        return {err_ret}
+                    }}
+                    "###
+                    )
+                })
+                .collect::<String>();
+            let args_assign = method
+                .args
+                .iter()
+                .map(|arg| {
+                    if arg.ty.is_scalar() {
+                        self.arg_name(arg)
+                    } else {
+                        format!("_{}", self.arg_name(arg))
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(",");
+            let ret = if is_empty_ret {
+                "return ret.AsError()".to_string()
+            } else {
+                format!("return ret.{unmarshal}")
+            };
+            client_methods.push_str(&format!(
+                r###"
+                func ({name}Client) {iface_method_name}({args_sign}) {ret_sign} {{
+                    if err := ctx.Err(); err != nil {{
+                        return {err_ret}
+                    }}
+                    {marshal_args}ret := Global{name}.{iface_method_name}({args_assign})
+                    defer ret.Free()
+                    {ret}
+                }}
+                "###
+            ));
+        }
+        format!(
+            r###"
+            // {name}Client the idiomatic client of {name}, which encodes the arguments, decodes the result,
+            // and frees the Rust buffer before returning.
+            type {name}Client struct{{}}
+            {client_methods}
+            "###
+        )
+    }
+
+    // {lib}_fake.go
+    fn codegen_rustffi_fake_method(
+        &self,
+        service_def_id: DefId,
+        method: &Arc<Method>,
+    ) -> Option<(String, String)> {
+        let (iface_method, _) = self.codegen_rustffi_iface_method(service_def_id, method)?;
+        let name = self.context.rust_name(service_def_id);
+        let iface_method_name = self.iface_method_name(method);
+        let ret_type = self.ret_type(method, false);
+        let stub_args_sign = method
+            .args
+            .iter()
+            .map(|arg| {
+                if arg.ty.is_scalar() {
+                    format!("{} {}", self.arg_name(arg), self.arg_type(arg, false))
+                } else {
+                    format!("{} *{}", self.arg_name(arg), self.arg_type(arg, false))
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        let is_empty_ret = self.context.is_empty_ty(&method.ret.kind);
+        let stub_ret = if is_empty_ret {
+            "error".to_string()
+        } else {
+            format!("(*{ret_type}, error)")
+        };
+        let decode_args = method
+            .args
+            .iter()
+            .filter(|arg| !arg.ty.is_scalar())
+            .map(|arg| {
+                let arg_name = self.arg_name(arg);
+                let arg_type = self.arg_type(arg, false);
+                format!(
+                    r###"_{arg_name}, err := fakeUnmarshal[{arg_type}]({arg_name}.Bytes())
+                    if err != nil {{
+                        return fakeRustFfiResult[{ret_type}](nil, NewFfiError(RcDecode, err.Error()), nil)
+                    }}
+                    "###
+                )
+            })
+            .collect::<String>();
+        let args_assign = method
+            .args
+            .iter()
+            .map(|arg| {
+                if arg.ty.is_scalar() {
+                    self.arg_name(arg)
+                } else {
+                    format!("_{}", self.arg_name(arg))
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        let record_args = if args_assign.is_empty() {
+            String::new()
+        } else {
+            format!(", {args_assign}")
+        };
+        let (is_declared, with_declared_error) =
+            match self
+                .context
+                .declared_error(&self.config, service_def_id, method)
+            {
+                Some(error) => (
+                    format!("isDeclaredError[{error}]"),
+                    format!(".withDeclaredError(unmarshalDeclaredError[{error}])"),
+                ),
+                None => ("nil".to_string(), String::new()),
+            };
+        let call = if is_empty_ret {
+            format!("return fakeRustFfiResult[{ret_type}](nil, f.{iface_method_name}Func({args_assign}), {is_declared}){with_declared_error}")
+        } else {
+            format!(
+                r###"ret, err := f.{iface_method_name}Func({args_assign})
+                    return fakeRustFfiResult[{ret_type}](ret, err, {is_declared}){with_declared_error}"###
+            )
+        };
+        Some((
+            format!("{iface_method_name}Func func({stub_args_sign}) {stub_ret}"),
+            format!(
+                r###"
+                func (f *{name}Fake) {iface_method} {{
+                    {decode_args}f.record("{iface_method_name}"{record_args})
+                    if f.{iface_method_name}Func == nil {{
+                        return fakeRustFfiResult[{ret_type}](nil, NewFfiError(RcUnknown, "{name}Fake.{iface_method_name} is not stubbed"), nil)
+                    }}
+                    {call}
+                }}
+                "###
+            ),
+        ))
+    }
+
+    // main.go, or {lib}.go in the Go host mode
+    fn codegen_goffi_iface_method(&self, _def_id: DefId, method: &Arc<Method>) -> Option<String> {
+        let mod_prefix = self.config.goffi_mod_prefix();
+        let is_main = !self.config.go_host;
+        let iface_method_name = self.iface_method_name(method);
+        let args_sign = method
+            .args
+            .iter()
+            .map(|arg| {
+                if arg.ty.is_scalar() {
+                    format!("{} {}", self.arg_name(arg), self.arg_type(arg, is_main))
+                } else {
+                    format!(
+                        "{} {mod_prefix}TBytes[{}]",
+                        self.arg_name(arg),
+                        self.arg_type(arg, is_main)
+                    )
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+        let ret_type = self.ret_type(method, is_main);
+        let is_empty_ret = self.context.is_empty_ty(&method.ret.kind);
+        Some(if is_empty_ret {
+            format!("{iface_method_name}({args_sign}) ResultMsg")
+        } else if method.ret.is_scalar() {
+            // the scalar result is returned directly, which cannot carry the error
+            format!("{iface_method_name}({args_sign}) {ret_type}")
+        } else {
+            format!("{iface_method_name}({args_sign}) gust.EnumResult[{mod_prefix}TBytes[*{ret_type}], ResultMsg]")
+        })
+    }
+
+    // main.go, or {lib}.go in the Go host mode
+    fn codegen_goffi_service_impl(&self, service_def_id: DefId, s: &Service) -> String {
+        let mod_prefix = self.config.goffi_mod_prefix();
+        let go_host = self.config.go_host;
+        let name = self.context.rust_name(service_def_id);
+        let mut ffi_functions = String::new();
+        let mut ffi_registers = String::new();
+
+        for method in &s.methods {
+            let is_empty_ret = self.context.is_empty_ty(&method.ret.kind);
+            let iface_method_name = self.iface_method_name(method);
+            let ffi_func_name = self.ffi_func_name(service_def_id, method);
+            let ffi_args_assign = method
+                .args
+                .iter()
+                .map(|arg| {
+                    if arg.ty.is_scalar() {
+                        let name = self.arg_name(arg);
+                        if go_host {
+                            format!("{}({name})", self.arg_type(arg, false))
+                        } else if let TyKind::Bool = arg.ty.kind {
+                            format!("bool({name})")
+                        } else {
+                            name
+                        }
+                    } else {
+                        format!(
+                            "asBytes[{}]({})",
+                            self.arg_type(arg, !go_host),
+                            self.arg_name(arg)
+                        )
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(",");
+            let ffi_args_sign = method
+                .args
+                .iter()
+                .map(|arg| {
+                    if go_host && arg.ty.is_scalar() {
+                        format!("{} C.{}", self.arg_name(arg), self.c_arg_type(&arg.ty.kind))
+                    } else if arg.ty.is_scalar() {
+                        format!("{} {}", self.arg_name(arg), self.arg_type(arg, true))
+                    } else {
+                        format!("{} C.struct_Buffer", self.arg_name(arg))
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(",");
+            let is_scalar_ret = method.ret.is_scalar() && !is_empty_ret;
+            if go_host {
+                let c_args = method
+                    .args
+                    .iter()
+                    .map(|arg| format!("{} {}", self.c_arg_type(&arg.ty.kind), self.arg_name(arg)))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                // it matches the function pointer type registered to Rust
+                let c_ret = if is_scalar_ret {
+                    self.c_arg_type(&method.ret.kind)
+                } else {
+                    "GoFfiResult"
+                };
+                self.go_lib_cdecls
+                    .borrow_mut()
+                    .push_str(&format!("extern {c_ret} {ffi_func_name}({c_args});\n"));
+                ffi_registers.push_str(&format!(
+                    "C.{ffi_func_name}_register((*[0]byte)(C.{ffi_func_name}))\n"
+                ));
+            }
+
+            let call_info = format!(
+                "&{mod_prefix}GoFfiCallInfo{{Service: \"{}\", Method: \"{}\", Args: []any{{{ffi_args_assign}}}}}",
+                &**s.name, &**method.name
+            );
+            if is_scalar_ret {
+                // the interceptors see the `ResultMsg`, which the scalar result has not
+                let ret_type = if go_host {
+                    format!("C.{}", self.c_arg_type(&method.ret.kind))
+                } else {
+                    self.ret_type(method, true)
+                };
+                ffi_functions.push_str(&format!(
+                    r###"
+                //go:inline
+                //export {ffi_func_name}
+                func {ffi_func_name}({ffi_args_sign}) {ret_type} {{
+                    return {ret_type}(Global{name}.{iface_method_name}({ffi_args_assign}))
+                }}
+                "###
+                ));
+            } else if is_empty_ret {
+                ffi_functions.push_str(&format!(r###"
+                //go:inline
+                //export {ffi_func_name}
+                func {ffi_func_name}({ffi_args_sign}) C.struct_GoFfiResult {{
+                    _{iface_method_name}_Err := {mod_prefix}InterceptGoFfi({call_info}, func() error {{
+                        if _{iface_method_name}_Ret_Msg := Global{name}.{iface_method_name}({ffi_args_assign}); _{iface_method_name}_Ret_Msg.Code != {mod_prefix}RcNoError {{
+                            return &_{iface_method_name}_Ret_Msg
+                        }}
+                        return nil
+                    }})
+                    if _{iface_method_name}_Err == nil {{
+                        return C.struct_GoFfiResult{{}}
+                    }}
+                    return C.struct_GoFfiResult{{
+                        code:     C.int32_t(_{iface_method_name}_Err.Code),
+                        data_ptr: C.leak_buffer(asBuffer({mod_prefix}TBytesFromBytes[string](_{iface_method_name}_Err.Encode()))),
+                    }}
+                }}
+                "###));
+            } else {
+                let ret_type = format!("{mod_prefix}TBytes[*{}]", self.ret_type(method, !go_host));
+                ffi_functions.push_str(&format!(r###"
+                //go:inline
+                //export {ffi_func_name}
+                func {ffi_func_name}({ffi_args_sign}) C.struct_GoFfiResult {{
+                    var _{iface_method_name}_Ret {ret_type}
+                    _{iface_method_name}_Err := {mod_prefix}InterceptGoFfi({call_info}, func() error {{
+                        _Ret := Global{name}.{iface_method_name}({ffi_args_assign})
+                        if _Ret.IsOk() {{
+                            _{iface_method_name}_Ret = _Ret.Unwrap()
+                            return nil
+                        }}
+                        _Ret_Msg := _Ret.UnwrapErr()
+                        return &_Ret_Msg
+                    }})
+                    if _{iface_method_name}_Err == nil {{
+                        return C.{ffi_func_name}_set_result(asBuffer(_{iface_method_name}_Ret))
+                    }}
+                    if _{iface_method_name}_Err.Code == {mod_prefix}RcNoError {{
+                        _{iface_method_name}_Err.Code = {mod_prefix}RcUnknown
+                    }}
+                    return C.struct_GoFfiResult{{
+                        code:     C.int32_t(_{iface_method_name}_Err.Code),
+                        data_ptr: C.leak_buffer(asBuffer({mod_prefix}TBytesFromBytes[string](_{iface_method_name}_Err.Encode()))),
+                    }}
+                }}
+                "###));
+            }
+        }
+        if go_host {
+            ffi_functions.push_str(&format!(
+                r###"
+                // Register{name} installs the GoFFI implementation and registers it to Rust,
+                // the Go host process should call it at startup.
+                func Register{name}(impl {name}) {{
+                    Global{name} = impl
+                    {ffi_registers}
+                }}
+                "###
+            ));
+        }
+
+        ffi_functions
+    }
+}
+
+impl Generator {
+    // {lib}.go
+    pub(crate) fn go_lib_common_code(&self) -> String {
+        let mut code = r###"
+// ResultCode negative codes are reserved for the framework, positive codes are free for the users
+type ResultCode = int32
 
 const (
 	RcNoError ResultCode = 0
 	RcDecode  ResultCode = -1
 	RcEncode  ResultCode = -2
-	RcUnknown ResultCode = -128
+	// RcDeclaredError the data is an encoded error declared in the IDL
+	RcDeclaredError ResultCode = -3
+	// RcUnavailable the library is not initialized or is shutting down
+	RcUnavailable ResultCode = -4
+	RcUnknown     ResultCode = -128
+	// RcUserMin the smallest code of the user errors
+	RcUserMin ResultCode = 1
+)
+
+// FfiError the error crossing the FFI boundary, encoded as JSON
+type FfiError struct {
+	Code    ResultCode        `json:"code"`
+	Kind    string            `json:"kind"`
+	Msg     string            `json:"msg"`
+	Cause   *FfiError         `json:"cause,omitempty"`
+	Details map[string]string `json:"details,omitempty"`
+}
+
+// The sentinel errors of the framework codes, e.g. `errors.Is(err, ErrDecode)`
+var (
+	ErrDecode   = NewFfiError(RcDecode, "decode error")
+	ErrEncode   = NewFfiError(RcEncode, "encode error")
+	ErrDeclared    = NewFfiError(RcDeclaredError, "declared error")
+	ErrUnavailable = NewFfiError(RcUnavailable, "unavailable")
+	ErrUnknown     = NewFfiError(RcUnknown, "unknown error")
+)
+
+// NewFfiError creates an error whose kind is derived from the code
+//
+//go:inline
+func NewFfiError(code ResultCode, msg string) *FfiError {
+	return &FfiError{Code: code, Kind: errorKind(code), Msg: msg}
+}
+
+func errorKind(code ResultCode) string {
+	switch {
+	case code == RcDecode:
+		return "decode"
+	case code == RcEncode:
+		return "encode"
+	case code == RcDeclaredError:
+		return "declared"
+	case code == RcUnknown || code == RcNoError:
+		return "unknown"
+	case code < RcNoError:
+		return "framework"
+	default:
+		return "user"
+	}
+}
+
+func (e *FfiError) Error() string {
+	if e.Cause != nil {
+		return e.Msg + ": " + e.Cause.Error()
+	}
+	return e.Msg
+}
+
+//go:inline
+func (e *FfiError) Unwrap() error {
+	if e.Cause == nil {
+		return nil
+	}
+	return e.Cause
+}
+
+// Is reports whether the target is an *FfiError with the same code
+//
+//go:inline
+func (e *FfiError) Is(target error) bool {
+	t, ok := target.(*FfiError)
+	return ok && t.Code == e.Code
+}
+
+// Encode encodes the error for Rust, the declared error keeps its encoded message
+func (e FfiError) Encode() []byte {
+	if e.Code == RcDeclaredError {
+		return valconv.StringToReadonlyBytes[string](e.Msg)
+	}
+	if e.Kind == "" {
+		e.Kind = errorKind(e.Code)
+	}
+	b, err := sonic.Marshal(e)
+	if err != nil {
+		return valconv.StringToReadonlyBytes[string](e.Msg)
+	}
+	return b
+}
+
+// NewDeclaredError returns the FfiError carrying the error declared in the IDL,
+// e.g. `NewDeclaredError(TBytesFromPbUnchecked(&MyError{}))`
+//
+//go:inline
+func NewDeclaredError[T any](e TBytes[T]) FfiError {
+	return FfiError{Code: RcDeclaredError, Kind: "declared", Msg: string(e.Bytes())}
+}
+
+func decodeFfiError(code ResultCode, b []byte) *FfiError {
+	var e FfiError
+	if err := sonic.ConfigStd.Unmarshal(b, &e); err != nil {
+		return NewFfiError(code, string(b))
+	}
+	e.Code = code
+	return &e
+}
+
+// GoFfiCallInfo the GoFFI call seen by the interceptors
+type GoFfiCallInfo struct {
+	// Service the service name in the IDL, e.g. `GoFFI`
+	Service string
+	// Method the method name in the IDL, e.g. `echo_go`
+	Method string
+	// Args the arguments from Rust, in the declared order
+	Args []any
+	// Metadata free for the interceptors to share data with the later ones
+	Metadata map[string]string
+}
+
+// GoFfiInterceptor wraps every GoFFI implementation called by Rust, such as auth checks and metrics.
+// It calls `next` to run the later interceptors and then the implementation,
+// the error returned without calling `next` rejects the call.
+type GoFfiInterceptor func(call *GoFfiCallInfo, next func() error) error
+
+var (
+	goFfiInterceptorsMu sync.RWMutex
+	goFfiInterceptors   []GoFfiInterceptor
 )
 
+// AddGoFfiInterceptor appends the interceptor to the chain run by the generated GoFFI exports
+func AddGoFfiInterceptor(interceptor GoFfiInterceptor) {
+	goFfiInterceptorsMu.Lock()
+	defer goFfiInterceptorsMu.Unlock()
+	goFfiInterceptors = append(goFfiInterceptors[:len(goFfiInterceptors):len(goFfiInterceptors)], interceptor)
+}
+
+// InterceptGoFfi runs the GoFFI implementation through the interceptors, called by the generated exports.
+// The returned error is converted by AsFfiError.
+func InterceptGoFfi(call *GoFfiCallInfo, impl func() error) *FfiError {
+	goFfiInterceptorsMu.RLock()
+	interceptors := goFfiInterceptors
+	goFfiInterceptorsMu.RUnlock()
+	next := impl
+	for i := len(interceptors) - 1; i >= 0; i-- {
+		interceptor, inner := interceptors[i], next
+		next = func() error { return interceptor(call, inner) }
+	}
+	return AsFfiError(next())
+}
+
+// AsFfiError converts the error to be sent to Rust, the errors other than *FfiError become RcUnknown
+func AsFfiError(err error) *FfiError {
+	if err == nil {
+		return nil
+	}
+	var e *FfiError
+	if errors.As(err, &e) {
+		return e
+	}
+	return NewFfiError(RcUnknown, err.Error())
+}
+
+// RustLogConfig the logging config of the Rust library, the empty fields keep the defaults of `fcplug::log::LogConfig`
+type RustLogConfig struct {
+	// Filter the level filters per target in the `RUST_LOG` syntax, e.g. `warn,my_crate=debug`
+	Filter string `json:"filter,omitempty"`
+	// Output "stdout" or "file"
+	Output string `json:"output,omitempty"`
+	// Format "text" or "json"
+	Format string `json:"format,omitempty"`
+	// Dir the directory of the log files
+	Dir        string `json:"dir,omitempty"`
+	FilePrefix string `json:"file_prefix,omitempty"`
+	// Rotation "minutely", "hourly", "daily" or "never"
+	Rotation string `json:"rotation,omitempty"`
+	// MaxFiles the number of the rotated files to keep, 0 keeps all
+	MaxFiles int `json:"max_files,omitempty"`
+}
+
+// GoLifecycle the hooks of the Go side, called by Rust through the generated `GoLifecycle` when Rust is the host
+type GoLifecycle interface {
+	// Init is called once with the config from Rust, which must be copied to be kept
+	Init(config []byte) error
+	// Health the error reports the Go side unhealthy
+	Health() error
+	// Shutdown stops the background goroutines and flushes the logs and metrics
+	Shutdown() error
+}
+
+// GlobalGoLifecycle the Go side hooks, nil means nothing to do
+var GlobalGoLifecycle GoLifecycle
+
 // TBytes bytes with type marker
 type TBytes[T any] struct {
 	bytes []byte
@@ -124,6 +699,11 @@ func (b TBytes[T]) Len() int {
 	return len(b.bytes)
 }
 
+//go:inline
+func (b TBytes[T]) Bytes() []byte {
+	return b.bytes
+}
+
 // PbUnmarshal as protobuf to unmarshal
 // NOTE: maybe reference Rust memory buffer
 //
@@ -217,78 +797,29 @@ func (b TBytes[T]) ForCBuffer() (unsafe.Pointer, int) {
 	return unsafe.Pointer(&b.bytes[0]), size
 }
 
+// RustFfiResult Rust FFI Result for Go
+// NOTE: must call Free method to free rust memory buffer, or use AutoFree!
+type RustFfiResult[T any] struct {
+	CBuffer
+	Code        ResultCode
+	_nil        *T
+	declaredErr func([]byte) error
+}
+
 //go:inline
-func (b TBytes[T]) asBuffer() C.struct_Buffer {
-	p, size := b.ForCBuffer()
-	if size == 0 {
-		return C.struct_Buffer{}
-	}
-	return C.struct_Buffer{
-		ptr: (*C.uint8_t)(p),
-		len: C.uintptr_t(size),
-		cap: C.uintptr_t(size),
-	}
+func (r RustFfiResult[T]) String() string {
+	return fmt.Sprintf("Code: %d, CBuffer: %s", r.Code, r.CBuffer.AsString())
 }
 
-// CBuffer Rust buffer for Go
-type CBuffer struct {
-	buf C.struct_Buffer
-}
-
-// Free free rust memory buffer, must be called!
-//
-//go:inline
-func (b CBuffer) Free() {
-	if b.buf.len > 0 {
-		C.free_buffer(b.buf)
-	}
-}
-
-//go:inline
-func (b CBuffer) Len() int {
-	return int(b.buf.len)
-}
-
-//go:inline
-func (b CBuffer) AsBytes() []byte {
-	if b.buf.len == 0 {
-		return nil
-	}
-	return *(*[]byte)(unsafe.Pointer(&reflect.SliceHeader{
-		Data: uintptr(unsafe.Pointer(b.buf.ptr)),
-		Len:  int(b.buf.len),
-		Cap:  int(b.buf.cap),
-	}))
-}
-
-//go:inline
-func (b CBuffer) AsString() string {
-    if b.buf.len == 0 {
-		return ""
-	}
-	return valconv.BytesToString[string](b.AsBytes())
-}
-
-// RustFfiResult Rust FFI Result for Go
-// NOTE: must call Free method to free rust memory buffer!
-type RustFfiResult[T any] struct {
-	CBuffer
-	Code ResultCode
-	_nil *T
-}
-
-//go:inline
-func newRustFfiResult[T any](ret C.struct_RustFfiResult) RustFfiResult[T] {
-	return RustFfiResult[T]{
-		CBuffer: CBuffer{buf: ret.data},
-		Code:    ResultCode(ret.code),
-		_nil:    nil,
-	}
-}
-
-//go:inline
-func (r RustFfiResult[T]) String() string {
-	return fmt.Sprintf("Code: %d, CBuffer: %s", r.Code, r.CBuffer.AsString())
+// AutoFree frees the Rust memory buffer by a finalizer when the returned result is garbage collected,
+// so Free must not be called on it any more.
+// NOTE: the bytes referencing the Rust memory buffer must not outlive the returned result
+func (r RustFfiResult[T]) AutoFree() *RustFfiResult[T] {
+	p := &r
+	runtime.SetFinalizer(p, func(p *RustFfiResult[T]) {
+		p.Free()
+	})
+	return p
 }
 
 //go:inline
@@ -301,12 +832,21 @@ func (r RustFfiResult[T]) IsOk() bool {
 //
 //go:inline
 func (r RustFfiResult[T]) AsError() error {
+	if r.Code == RcDeclaredError && r.declaredErr != nil {
+		return r.declaredErr(r.AsBytes())
+	}
 	if r.Code != RcNoError {
-		return errors.New(r.AsString())
+		return decodeFfiError(r.Code, r.AsBytes())
 	}
 	return nil
 }
 
+//go:inline
+func (r RustFfiResult[T]) withDeclaredError(unmarshal func([]byte) error) RustFfiResult[T] {
+	r.declaredErr = unmarshal
+	return r
+}
+
 // PbUnmarshal as protobuf to unmarshal
 // NOTE: maybe reference Rust memory buffer
 //
@@ -407,127 +947,435 @@ func (r RustFfiResult[T]) UnmarshalUnchecked(unmarshal func([]byte, any) error)
 }
 
 "###
-        .to_string()
+        .to_string();
+        code.push_str(&self.go_declared_error_code());
+        if self.config.go_log_bridge {
+            code.push_str(&self.go_log_bridge_code());
+        }
+        code
     }
 
-    // main.go
-    fn codegen_goffi_iface_method(&self, _def_id: DefId, method: &Arc<Method>) -> Option<String> {
-        let mod_name = self.config.gomod_name.clone();
-        let iface_method_name = self.iface_method_name(method);
-        let args_sign = method
-            .args
-            .iter()
-            .map(|arg| {
-                if arg.ty.is_scalar() {
-                    format!("{} {}", self.arg_name(arg), self.arg_type(arg, true))
-                } else {
-                    format!(
-                        "{} {mod_name}.TBytes[{}]",
-                        self.arg_name(arg),
-                        self.arg_type(arg, true)
-                    )
-                }
-            })
-            .collect::<Vec<String>>()
-            .join(",");
-        let ret_type = self.ret_type(method, true);
-        let is_empty_ret = self.context.is_empty_ty(&method.ret.kind);
-        Some(if is_empty_ret {
-            format!("{iface_method_name}({args_sign}) ResultMsg")
+    // {lib}.go, the code depending on the Rust C library
+    pub(crate) fn go_lib_cgo_code(&self) -> String {
+        let mut code = r###"
+//go:inline
+func (b TBytes[T]) asBuffer() C.struct_Buffer {
+	p, size := b.ForCBuffer()
+	if size == 0 {
+		return C.struct_Buffer{}
+	}
+	return C.struct_Buffer{
+		ptr: (*C.uint8_t)(p),
+		len: C.uintptr_t(size),
+		cap: C.uintptr_t(size),
+	}
+}
+
+// CBuffer Rust buffer for Go
+type CBuffer struct {
+	buf C.struct_Buffer
+}
+
+// Free free rust memory buffer, must be called!
+//
+//go:inline
+func (b CBuffer) Free() {
+	if b.buf.len > 0 {
+		C.free_buffer(b.buf)
+	}
+}
+
+//go:inline
+func (b CBuffer) Len() int {
+	return int(b.buf.len)
+}
+
+//go:inline
+func (b CBuffer) AsBytes() []byte {
+	if b.buf.len == 0 {
+		return nil
+	}
+	return *(*[]byte)(unsafe.Pointer(&reflect.SliceHeader{
+		Data: uintptr(unsafe.Pointer(b.buf.ptr)),
+		Len:  int(b.buf.len),
+		Cap:  int(b.buf.cap),
+	}))
+}
+
+//go:inline
+func (b CBuffer) AsString() string {
+    if b.buf.len == 0 {
+		return ""
+	}
+	return valconv.BytesToString[string](b.AsBytes())
+}
+
+//go:inline
+func newRustFfiResult[T any](ret C.struct_RustFfiResult) RustFfiResult[T] {
+	return RustFfiResult[T]{
+		CBuffer: CBuffer{buf: ret.data},
+		Code:    ResultCode(ret.code),
+		_nil:    nil,
+	}
+}
+
+// InitRust initializes the Rust library with the config, which is passed to its `Lifecycle::init`
+func InitRust(config []byte) error {
+	ret := newRustFfiResult[any](C.fcplug_init(TBytesFromBytes[any](config).asBuffer()))
+	defer ret.Free()
+	return ret.AsError()
+}
+
+// RustHealth reports whether the Rust library is initialized and healthy
+func RustHealth() error {
+	ret := newRustFfiResult[any](C.fcplug_health())
+	defer ret.Free()
+	return ret.AsError()
+}
+
+// InitRustLog installs the logger of the Rust library, or reconfigures it at runtime
+func InitRustLog(config RustLogConfig) error {
+	b, err := sonic.Marshal(config)
+	if err != nil {
+		return err
+	}
+	ret := newRustFfiResult[any](C.fcplug_log_init(TBytesFromBytes[any](b).asBuffer()))
+	defer ret.Free()
+	return ret.AsError()
+}
+
+// ShutdownRust rejects the new RustFFI calls, waits for the in-flight ones within the timeout,
+// calls the Rust `Lifecycle::shutdown`, and then flushes the Rust logs. It can be called again after the timeout error.
+func ShutdownRust(timeout time.Duration) error {
+	ret := newRustFfiResult[any](C.fcplug_shutdown(C.uint64_t(timeout.Milliseconds())))
+	defer ret.Free()
+	return ret.AsError()
+}
+
+"###
+        .to_string();
+        if self.config.go_log_bridge {
+            code.push_str(
+                r###"
+//export fcplug_go_log
+func fcplug_go_log(record C.struct_Buffer) {
+	handleRustLog(CBuffer{buf: record}.AsBytes())
+}
+
+//go:inline
+func registerRustLogCallback() {
+	C.fcplug_log_set_callback((*[0]byte)(C.fcplug_go_log))
+}
+
+//go:inline
+func writeRustLog(record []byte) {
+	C.fcplug_log_write(TBytesFromBytes[any](record).asBuffer())
+}
+"###,
+            );
+        }
+        code
+    }
+
+    // {lib}_fake.go, replacing `go_lib_cgo_code` in the fake built with the `fcplug_fake` tag
+    pub(crate) fn go_lib_fake_code(&self) -> String {
+        let log_bridge = if self.config.go_log_bridge {
+            r###"
+// registerRustLogCallback does nothing in the fake
+func registerRustLogCallback() {}
+
+// writeRustLog drops the record in the fake
+func writeRustLog(record []byte) {}
+"###
         } else {
-            format!("{iface_method_name}({args_sign}) gust.EnumResult[{mod_name}.TBytes[*{ret_type}], ResultMsg]")
-        })
+            ""
+        };
+        let (marshal, unmarshal) = match self.config.idl_type {
+            IdlType::Proto | IdlType::ProtoNoCodec => (
+                "proto.Marshal(v.(proto.Message))",
+                "proto.Unmarshal(b, any(&t).(proto.Message))",
+            ),
+            IdlType::Thrift | IdlType::ThriftNoCodec => {
+                ("sonic.Marshal(v)", "sonic.ConfigStd.Unmarshal(b, &t)")
+            }
+        };
+        format!(
+            r###"
+// CBuffer the result buffer, which is a Go slice in the fake
+type CBuffer struct {{
+	buf []byte
+}}
+
+// Free does nothing in the fake
+//
+//go:inline
+func (b CBuffer) Free() {{}}
+
+//go:inline
+func (b CBuffer) Len() int {{
+	return len(b.buf)
+}}
+
+//go:inline
+func (b CBuffer) AsBytes() []byte {{
+	return b.buf
+}}
+
+//go:inline
+func (b CBuffer) AsString() string {{
+	return string(b.buf)
+}}
+
+// InitRust does nothing in the fake
+func InitRust(config []byte) error {{
+	return nil
+}}
+
+// RustHealth reports healthy in the fake
+func RustHealth() error {{
+	return nil
+}}
+
+// InitRustLog does nothing in the fake
+func InitRustLog(config RustLogConfig) error {{
+	return nil
+}}
+
+// ShutdownRust does nothing in the fake
+func ShutdownRust(timeout time.Duration) error {{
+	return nil
+}}
+
+// FakeCall a call recorded by the fakes
+type FakeCall struct {{
+	Method string
+	Args   []any
+}}
+
+func fakeMarshal(v any) ([]byte, error) {{
+	return {marshal}
+}}
+
+func fakeUnmarshal[T any](b []byte) (*T, error) {{
+	var t T
+	if len(b) > 0 {{
+		if err := {unmarshal}; err != nil {{
+			return nil, err
+		}}
+	}}
+	return &t, nil
+}}
+
+//go:inline
+func isDeclaredError[E any](err error) bool {{
+	_, ok := err.(*E)
+	return ok
+}}
+
+// fakeRustFfiResult encodes the result of the stub as Rust does
+func fakeRustFfiResult[T any](ret *T, err error, isDeclared func(error) bool) RustFfiResult[T] {{
+	if err != nil {{
+		if isDeclared != nil && isDeclared(err) {{
+			if b, merr := fakeMarshal(err); merr == nil {{
+				return RustFfiResult[T]{{CBuffer: CBuffer{{buf: b}}, Code: RcDeclaredError}}
+			}} else {{
+				err = merr
+			}}
+		}}
+		var e *FfiError
+		if !errors.As(err, &e) {{
+			e = NewFfiError(RcUnknown, err.Error())
+		}}
+		code := e.Code
+		if code == RcNoError {{
+			code = RcUnknown
+		}}
+		return RustFfiResult[T]{{CBuffer: CBuffer{{buf: e.Encode()}}, Code: code}}
+	}}
+	if ret == nil {{
+		return RustFfiResult[T]{{}}
+	}}
+	b, err := fakeMarshal(ret)
+	if err != nil {{
+		return RustFfiResult[T]{{CBuffer: CBuffer{{buf: NewFfiError(RcEncode, err.Error()).Encode()}}, Code: RcEncode}}
+	}}
+	return RustFfiResult[T]{{CBuffer: CBuffer{{buf: b}}}}
+}}
+{log_bridge}"###
+        )
     }
 
-    // main.go
-    fn codegen_goffi_service_impl(&self, service_def_id: DefId, s: &Service) -> String {
-        let mod_name = self.config.gomod_name.clone();
-        let mut ffi_functions = String::new();
+    // {lib}.go and {lib}_fake.go, the bridge between Rust `tracing` and Go `log/slog`
+    fn go_log_bridge_code(&self) -> String {
+        r###"
+// BridgeRustLog forwards the Rust tracing events matching the filter to the logger, e.g. `slog.Default()`,
+// with the `target` and `spans` attributes. The filter is in the `RUST_LOG` syntax, e.g. `info,my_crate=debug`.
+// NOTE: the logger must not write to the handler of NewRustLogHandler, which forwards the records back to Rust.
+func BridgeRustLog(logger *slog.Logger, filter string) error {
+	rustLogger.Store(logger)
+	registerRustLogCallback()
+	return InitRustLog(RustLogConfig{Filter: filter, Output: "callback"})
+}
 
-        for method in &s.methods {
-            let is_empty_ret = self.context.is_empty_ty(&method.ret.kind);
-            let iface_method_name = self.iface_method_name(method);
-            let ffi_func_name = self.ffi_func_name(service_def_id, method);
-            let ffi_args_assign = method
-                .args
-                .iter()
-                .map(|arg| {
-                    if arg.ty.is_scalar() {
-                        let name = self.arg_name(arg);
-                        if let TyKind::Bool = arg.ty.kind {
-                            format!("bool({name})")
-                        } else {
-                            name
-                        }
-                    } else {
-                        format!(
-                            "asBytes[{}]({})",
-                            self.arg_type(arg, true),
-                            self.arg_name(arg)
-                        )
-                    }
-                })
-                .collect::<Vec<String>>()
-                .join(",");
-            let ffi_args_sign = method
-                .args
-                .iter()
-                .map(|arg| {
-                    if arg.ty.is_scalar() {
-                        format!("{} {}", self.arg_name(arg), self.arg_type(arg, true))
-                    } else {
-                        format!("{} C.struct_Buffer", self.arg_name(arg))
-                    }
-                })
-                .collect::<Vec<String>>()
-                .join(",");
+var rustLogger atomic.Pointer[slog.Logger]
 
-            if is_empty_ret {
-                ffi_functions.push_str(&format!(r###"
-                //go:inline
-                //export {ffi_func_name}
-                func {ffi_func_name}({ffi_args_sign}) C.struct_GoFfiResult {{
-                    if _{iface_method_name}_Ret_Msg := GlobalGoFfi.{iface_method_name}({ffi_args_assign}); _{iface_method_name}_Ret_Msg.Code == {mod_name}.RcNoError {{
-                        return C.struct_GoFfiResult{{}}
-                    }} else {{
-                        return C.struct_GoFfiResult{{
-                            code:     C.int8_t(_{iface_method_name}_Ret_Msg.Code),
-                            data_ptr: C.leak_buffer(asBuffer({mod_name}.TBytesFromString[string](_{iface_method_name}_Ret_Msg.Msg))),
-                        }}
-                    }}
-                }}
-                "###));
-            } else {
-                ffi_functions.push_str(&format!(r###"
-                //go:inline
-                //export {ffi_func_name}
-                func {ffi_func_name}({ffi_args_sign}) C.struct_GoFfiResult {{
-                    if _{iface_method_name}_Ret := GlobalGoFfi.{iface_method_name}({ffi_args_assign}); _{iface_method_name}_Ret.IsOk() {{
-                        return C.{ffi_func_name}_set_result(asBuffer(_{iface_method_name}_Ret.Unwrap()))
-                    }} else {{
-                        _{iface_method_name}_Ret_Msg := _{iface_method_name}_Ret.UnwrapErr()
-                        if _{iface_method_name}_Ret_Msg.Code == {mod_name}.RcNoError {{
-                            _{iface_method_name}_Ret_Msg.Code = {mod_name}.RcUnknown
-                        }}
-                        return C.struct_GoFfiResult{{
-                            code:     C.int8_t(_{iface_method_name}_Ret_Msg.Code),
-                            data_ptr: C.leak_buffer(asBuffer({mod_name}.TBytesFromString[string](_{iface_method_name}_Ret_Msg.Msg))),
-                        }}
-                    }}
-                }}
-                "###));
+type rustLogRecord struct {
+	Level  string `json:"level"`
+	Target string `json:"target"`
+	Spans  []struct {
+		Name   string `json:"name"`
+		Fields string `json:"fields"`
+	} `json:"spans"`
+	Fields map[string]any `json:"fields"`
+}
+
+// handleRustLog the record references the Rust memory buffer, which is copied by unmarshal
+func handleRustLog(record []byte) {
+	logger := rustLogger.Load()
+	if logger == nil {
+		return
+	}
+	var r rustLogRecord
+	if err := sonic.ConfigStd.Unmarshal(record, &r); err != nil {
+		return
+	}
+	msg, _ := r.Fields["message"].(string)
+	delete(r.Fields, "message")
+	attrs := make([]slog.Attr, 0, len(r.Fields)+2)
+	attrs = append(attrs, slog.String("target", r.Target))
+	if len(r.Spans) > 0 {
+		attrs = append(attrs, slog.Any("spans", r.Spans))
+	}
+	for k, v := range r.Fields {
+		attrs = append(attrs, slog.Any(k, v))
+	}
+	logger.LogAttrs(context.Background(), rustLogLevel(r.Level), msg, attrs...)
+}
+
+func rustLogLevel(level string) slog.Level {
+	switch level {
+	case "TRACE":
+		return slog.LevelDebug - 4
+	case "DEBUG":
+		return slog.LevelDebug
+	case "WARN":
+		return slog.LevelWarn
+	case "ERROR":
+		return slog.LevelError
+	default:
+		return slog.LevelInfo
+	}
+}
+
+// NewRustLogHandler returns the slog handler forwarding the Go log records into Rust tracing,
+// as the events with the target `go` whose attributes are kept as JSON in the `fields` field.
+func NewRustLogHandler(level slog.Leveler) slog.Handler {
+	return &rustLogHandler{level: level}
+}
+
+type rustLogHandler struct {
+	level  slog.Leveler
+	prefix string
+	attrs  map[string]any
+}
+
+func (h *rustLogHandler) Enabled(_ context.Context, level slog.Level) bool {
+	return level >= h.level.Level()
+}
+
+func (h *rustLogHandler) Handle(_ context.Context, r slog.Record) error {
+	fields := make(map[string]any, len(h.attrs)+r.NumAttrs())
+	for k, v := range h.attrs {
+		fields[k] = v
+	}
+	r.Attrs(func(a slog.Attr) bool {
+		fields[h.prefix+a.Key] = a.Value.Resolve().Any()
+		return true
+	})
+	b, err := sonic.Marshal(map[string]any{"level": int(r.Level), "message": r.Message, "fields": fields})
+	if err != nil {
+		return err
+	}
+	writeRustLog(b)
+	return nil
+}
+
+func (h *rustLogHandler) WithAttrs(attrs []slog.Attr) slog.Handler {
+	h2 := &rustLogHandler{level: h.level, prefix: h.prefix, attrs: make(map[string]any, len(h.attrs)+len(attrs))}
+	for k, v := range h.attrs {
+		h2.attrs[k] = v
+	}
+	for _, a := range attrs {
+		h2.attrs[h.prefix+a.Key] = a.Value.Resolve().Any()
+	}
+	return h2
+}
+
+func (h *rustLogHandler) WithGroup(name string) slog.Handler {
+	if name == "" {
+		return h
+	}
+	return &rustLogHandler{level: h.level, prefix: h.prefix + name + ".", attrs: h.attrs}
+}
+"###
+        .to_string()
+    }
+
+    // {lib}.go
+    fn go_declared_error_code(&self) -> String {
+        let unmarshal = match self.config.idl_type {
+            IdlType::Proto | IdlType::ProtoNoCodec => {
+                "proto.Unmarshal(b, any(PE(&e)).(proto.Message))"
             }
+            IdlType::Thrift | IdlType::ThriftNoCodec => "sonic.ConfigStd.Unmarshal(b, PE(&e))",
+        };
+        let mut code = format!(
+            r###"
+// unmarshalDeclaredError unmarshal the error declared in the IDL
+func unmarshalDeclaredError[E any, PE interface {{
+	*E
+	error
+}}](b []byte) error {{
+	var e E
+	if err := {unmarshal}; err != nil {{
+		return err
+	}}
+	return PE(&e)
+}}
+"###
+        );
+        for error in &self.config.go_error_impls {
+            let error = (&**error).struct_ident();
+            code.push_str(&format!(
+                r###"
+//go:inline
+func (x *{error}) Error() string {{
+	return x.String()
+}}
+"###
+            ));
         }
+        code
+    }
 
-        format!(
+    // main.go, or {lib}.go in the Go host mode
+    pub(crate) fn go_main_common_code(&self) -> String {
+        let mod_prefix = self.config.goffi_mod_prefix();
+        let mut code = format!(
             r###"
-        type ResultMsg struct {{
-            Code {mod_name}.ResultCode
-            Msg  string
+        // ResultMsg the error returned to Rust, e.g. `ResultMsg{{Code: 1, Msg: "not found"}}`
+        type ResultMsg = {mod_prefix}FfiError
+
+        // DeclaredError returns a ResultMsg carrying the error declared in the IDL,
+        // e.g. `DeclaredError({mod_prefix}TBytesFromPbUnchecked(&{mod_prefix}MyError{{}}))`
+        //
+        //go:inline
+        func DeclaredError[T any](e {mod_prefix}TBytes[T]) ResultMsg {{
+            return {mod_prefix}NewDeclaredError(e)
         }}
 
         //go:inline
-        func asBuffer[T any](b {mod_name}.TBytes[T]) C.struct_Buffer {{
+        func asBuffer[T any](b {mod_prefix}TBytes[T]) C.struct_Buffer {{
             p, size := b.ForCBuffer()
             if size == 0 {{
                 return C.struct_Buffer{{}}
@@ -540,18 +1388,67 @@ func (r RustFfiResult[T]) UnmarshalUnchecked(unmarshal func([]byte, any) error)
         }}
 
         //go:inline
-        func asBytes[T any](buf C.struct_Buffer) {mod_name}.TBytes[T] {{
+        func asBytes[T any](buf C.struct_Buffer) {mod_prefix}TBytes[T] {{
             if buf.len == 0 {{
-                return {mod_name}.TBytes[T]{{}}
+                return {mod_prefix}TBytes[T]{{}}
             }}
-            return {mod_name}.TBytesFromBytes[T](*(*[]byte)(unsafe.Pointer(&reflect.SliceHeader{{
+            return {mod_prefix}TBytesFromBytes[T](*(*[]byte)(unsafe.Pointer(&reflect.SliceHeader{{
                 Data: uintptr(unsafe.Pointer(buf.ptr)),
                 Len:  int(buf.len),
                 Cap:  int(buf.cap),
             }})))
         }}
 
-        {ffi_functions}
+        "###
+        );
+        if !self.config.go_host {
+            code.push_str(&self.go_main_lifecycle_code());
+        }
+        code
+    }
+
+    // main.go, the exports driving the Go side lifecycle from the Rust host
+    fn go_main_lifecycle_code(&self) -> String {
+        let mod_prefix = self.config.goffi_mod_prefix();
+        format!(
+            r###"
+        //export fcplug_go_init
+        func fcplug_go_init(config C.struct_Buffer) C.struct_GoFfiResult {{
+            if {mod_prefix}GlobalGoLifecycle == nil {{
+                return C.struct_GoFfiResult{{}}
+            }}
+            return asGoFfiResult({mod_prefix}GlobalGoLifecycle.Init(asBytes[any](config).Bytes()))
+        }}
+
+        //export fcplug_go_health
+        func fcplug_go_health() C.struct_GoFfiResult {{
+            if {mod_prefix}GlobalGoLifecycle == nil {{
+                return C.struct_GoFfiResult{{}}
+            }}
+            return asGoFfiResult({mod_prefix}GlobalGoLifecycle.Health())
+        }}
+
+        //export fcplug_go_shutdown
+        func fcplug_go_shutdown() C.struct_GoFfiResult {{
+            if {mod_prefix}GlobalGoLifecycle == nil {{
+                return C.struct_GoFfiResult{{}}
+            }}
+            return asGoFfiResult({mod_prefix}GlobalGoLifecycle.Shutdown())
+        }}
+
+        func asGoFfiResult(err error) C.struct_GoFfiResult {{
+            e := {mod_prefix}AsFfiError(err)
+            if e == nil {{
+                return C.struct_GoFfiResult{{}}
+            }}
+            if e.Code == {mod_prefix}RcNoError {{
+                e.Code = {mod_prefix}RcUnknown
+            }}
+            return C.struct_GoFfiResult{{
+                code:     C.int32_t(e.Code),
+                data_ptr: C.leak_buffer(asBuffer({mod_prefix}TBytesFromBytes[string](e.Encode()))),
+            }}
+        }}
         "###
         )
     }
@@ -3,6 +3,7 @@ use std::sync::Arc;
 use pilota_build::rir::{Method, Service};
 use pilota_build::{DefId, IdentName};
 
+use crate::config::IdlType;
 use crate::generator::{RustCodegenBackend, RustGeneratorBackend, ServiceType};
 
 impl RustCodegenBackend for RustGeneratorBackend {
@@ -14,7 +15,7 @@ impl RustCodegenBackend for RustGeneratorBackend {
         let method_name = (&**method.name).fn_ident();
         let args = self.codegen_method_args(service_def_id, method);
         let ret = self.codegen_method_ret(service_def_id, method);
-        Some(format!("fn {method_name}({args}) -> {ret}"))
+        Some(format!("fn {method_name}(&self, {args}) -> {ret}"))
     }
     fn codegen_rustffi_service_impl(&self, def_id: DefId, stream: &mut String, s: &Service) {
         let name = self.context.rust_name(def_id);
@@ -28,11 +29,63 @@ impl RustCodegenBackend for RustGeneratorBackend {
                     let args = self.codegen_ffi_args_param(def_id, method);
                     let args_ident = self.codegen_ffi_args_ident(def_id, method);
                     let ret = self.codegen_ffi_ret(def_id, method);
+                    let from = if self.context.declared_error(&self.config, def_id, method).is_some() {
+                        format!("{ret}::from_typed::<_, _, {}>", self.codec_message())
+                    } else {
+                        format!("{ret}::from")
+                    };
+                    let mut call = format!("<{ust} as {name}>::{fn_name}(ffi_impl, {args_ident})");
+                    let mut decode_args = String::new();
+                    if self.is_typed_rustffi(method) {
+                        decode_args = self.codegen_rustffi_decode_args(method);
+                        if !self.context.is_empty_ty(&method.ret.kind) {
+                            call = format!(
+                                "{call}.and_then(|ret| Ok(::fcplug::TBytes::try_from::<{}>(ret)?))",
+                                self.codec_message()
+                            );
+                        }
+                    }
+                    let mut body = format!("{decode_args}{from}({call})");
+                    // the interceptors see the `RustFfiResult`, which the scalar result has not
+                    if !method.ret.is_scalar() || self.context.is_empty_ty(&method.ret.kind) {
+                        let service_name = &**s.name;
+                        let method_name = &**method.name;
+                        let arg_bufs = method
+                            .args
+                            .iter()
+                            .filter(|arg| !arg.ty.is_scalar())
+                            .map(|arg| (&**arg.name).snake_ident().to_string())
+                            .collect::<Vec<String>>()
+                            .join(", ");
+                        body = format!(
+                            r###"::fcplug::intercept_rust_ffi("{service_name}", "{method_name}", &[{arg_bufs}], || {{
+                        {body}
+                    }})"###
+                        );
+                    } else {
+                        body = format!(
+                            r###"::fcplug::enter_rust_ffi_scalar(|| {{
+                        {body}
+                    }})"###
+                        );
+                    }
+                    let (unavailable, doc) = if method.ret.is_scalar() && !self.context.is_empty_ty(&method.ret.kind) {
+                        (
+                            "::fcplug::unavailable_scalar(err)",
+                            "/// The scalar result cannot carry the errors, so it aborts the process if no instance is registered\n/// or the library is shutting down.\n",
+                        )
+                    } else {
+                        ("::fcplug::RustFfiResult::from_err(err)", "")
+                    };
                     format!(
-                        r###"#[no_mangle]
+                        r###"{doc}#[no_mangle]
                 #[inline]
                 pub extern "C" fn {name_lower}_{fn_name}({args}) -> {ret} {{
-                    {ret}::from(<{ust} as {name}>::{fn_name}({args_ident}))
+                    let ffi_impl = match ffi_impl() {{
+                        Ok(ffi_impl) => ffi_impl,
+                        Err(err) => return {unavailable},
+                    }};
+                    {body}
                 }}
                 "###
                     )
@@ -46,13 +99,21 @@ impl RustCodegenBackend for RustGeneratorBackend {
         service_def_id: DefId,
         method: &Arc<Method>,
     ) -> Option<String> {
-        if self.context.is_empty_ty(&method.ret.kind) && !method.ret.is_scalar() {
+        // the scalar result is returned by Go directly, which needs no storing
+        if self.context.is_empty_ty(&method.ret.kind) || method.ret.is_scalar() {
             return None;
         }
         let method_name = (&**method.name).fn_ident();
         let ffi_ret = self.codegen_ffi_ret(service_def_id, method);
         let ret_ty_name = self.rust_codegen_item_ty(&method.ret.kind);
-        Some(format!("unsafe fn {method_name}_set_result(go_ret: ::fcplug::RustFfiArg<{ret_ty_name}>) -> {ffi_ret}"))
+        Some(format!(
+            r###"/// Store the result of Go, which is decoded by default.
+            unsafe fn {method_name}_set_result(mut go_ret: ::fcplug::RustFfiArg<{ret_ty_name}>) -> {ffi_ret} {{
+                {ffi_ret}::from_ok(go_ret.try_to_object::<{}>()?)
+            }}
+            "###,
+            self.codec_message()
+        ))
     }
     fn codegen_goffi_call_trait_method(
         &self,
@@ -64,15 +125,72 @@ impl RustCodegenBackend for RustGeneratorBackend {
         let method_name = (&**method.name).fn_ident();
         let args = self.codegen_method_args(service_def_id, method);
         let ret = self.codegen_method_ret(service_def_id, method);
-        let generic_signature = if self.context.is_empty_ty(&method.ret.kind) {
-            String::new()
+        let args_ident = self.codegen_ffi_args_ident(service_def_id, method);
+        if method.ret.is_scalar() && !self.context.is_empty_ty(&method.ret.kind) {
+            return Some(format!(
+                r###"unsafe fn {method_name}({args}) -> {ret} {{
+                {name_lower}_{method_name}({args_ident})
+            }}
+            "###
+            ));
+        }
+        let (generic_signature, ret_ty) = if self.context.is_empty_ty(&method.ret.kind) {
+            (String::new(), "()")
         } else {
-            "<T: Default>".to_string()
+            ("<T: Default + 'static>".to_string(), "T")
+        };
+        let mut ffi_call = format!("{name_lower}_{method_name}({args_ident})");
+        let mut bind_args = String::new();
+        // the interceptors see the `GoFfiResult`, which the scalar result has not
+        if !method.ret.is_scalar() || self.context.is_empty_ty(&method.ret.kind) {
+            let service_name = self.context.service_name(service_def_id);
+            let ffi_method_name = &**method.name;
+            let arg_bufs = method
+                .args
+                .iter()
+                .filter(|arg| !arg.ty.is_scalar())
+                .map(|arg| (&**arg.name).snake_ident().to_string())
+                .collect::<Vec<String>>();
+            // the buffers are bound once, so that the retries send the same requests
+            bind_args = arg_bufs
+                .iter()
+                .map(|ident| {
+                    format!("let {ident} = ::fcplug::Buffer::from_vec_mut(&mut {ident}.bytes);\n")
+                })
+                .collect();
+            let args_ident = method
+                .args
+                .iter()
+                .map(|arg| (&**arg.name).snake_ident().to_string())
+                .collect::<Vec<String>>()
+                .join(", ");
+            ffi_call = format!(
+                r###"::fcplug::intercept_go_ffi("{service_name}", "{ffi_method_name}", &[{}], || {{
+                    {name_lower}_{method_name}({args_ident})
+                }})"###,
+                arg_bufs.join(", ")
+            );
+        }
+        let body = match self
+            .context
+            .declared_error(&self.config, service_def_id, method)
+        {
+            Some(error) => format!(
+                "{ffi_call}.into_typed::<{ret_ty}, {error}, {}>()",
+                self.codec_message()
+            ),
+            None if self.context.is_empty_ty(&method.ret.kind) => {
+                format!("::fcplug::ABIResult::from({ffi_call})")
+            }
+            None => format!(
+                "{ffi_call}.into_result::<T, {}, {}>()",
+                self.rust_codegen_item_ty(&method.ret.kind),
+                self.codec_message()
+            ),
         };
-        let args_ident = self.codegen_ffi_args_ident(service_def_id, method);
         Some(format!(
             r###"unsafe fn {method_name}{generic_signature}({args}) -> {ret} {{
-                ::fcplug::ABIResult::from({name_lower}_{method_name}({args_ident}))
+                {bind_args}{body}
             }}
             "###
         ))
@@ -81,26 +199,80 @@ impl RustCodegenBackend for RustGeneratorBackend {
         let name = self.context.rust_name(def_id);
         let name_lower = name.to_lowercase();
         let ust = &self.config.rust_mod_impl_name;
-        let ffi_fns = s
-            .methods
-            .iter()
-            .map(|method| {
-                let fn_name = (&**method.name).fn_ident();
-                let args = self.codegen_ffi_args_param(def_id, method);
-                let ret = self.codegen_ffi_ret(def_id, method);
-                format!("fn {name_lower}_{fn_name}({args}) -> {ret};")
-            })
-            .collect::<Vec<String>>()
-            .join("\n");
-        stream.push_str(&format!(
-            r###"
+        if self.config.go_host {
+            stream.push_str(
+                &s.methods
+                    .iter()
+                    .map(|method| {
+                        let fn_name = (&**method.name).fn_ident();
+                        let ffi_name = format!("{name_lower}_{fn_name}");
+                        let static_name = ffi_name.to_uppercase();
+                        let args = self.codegen_ffi_args_param(def_id, method);
+                        let args_ident = method
+                            .args
+                            .iter()
+                            .map(|arg| (&**arg.name).snake_ident().to_string())
+                            .collect::<Vec<String>>()
+                            .join(", ");
+                        let ret = self.codegen_ffi_ret(def_id, method);
+                        let unregistered = format!(
+                            r###"::fcplug::FfiError::new(::fcplug::RC_UNAVAILABLE, "GoFFI `{ffi_name}` is not registered by the Go host")"###
+                        );
+                        let (unavailable, doc) = if method.ret.is_scalar() && !self.context.is_empty_ty(&method.ret.kind) {
+                            (
+                                format!("::fcplug::unavailable_scalar({unregistered})"),
+                                "/// The scalar result cannot carry the errors, so it aborts the process if the Go host has not registered it.\n",
+                            )
+                        } else {
+                            (format!("::fcplug::GoFfiResult::from_err({unregistered})"), "")
+                        };
+                        format!(
+                            r###"#[cfg(not(feature = "goffi-mock"))]
+                static {static_name}: ::std::sync::OnceLock<extern "C" fn({args}) -> {ret}> = ::std::sync::OnceLock::new();
+                #[cfg(not(feature = "goffi-mock"))]
+                #[no_mangle]
+                pub extern "C" fn {ffi_name}_register(f: extern "C" fn({args}) -> {ret}) {{
+                    let _ = {static_name}.set(f);
+                }}
+                #[cfg(not(feature = "goffi-mock"))]
+                {doc}#[inline]
+                unsafe fn {ffi_name}({args}) -> {ret} {{
+                    match {static_name}.get() {{
+                        Some(f) => f({args_ident}),
+                        None => {unavailable},
+                    }}
+                }}
+                "###
+                        )
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            );
+        } else {
+            let ffi_fns = s
+                .methods
+                .iter()
+                .map(|method| {
+                    let fn_name = (&**method.name).fn_ident();
+                    let args = self.codegen_ffi_args_param(def_id, method);
+                    let ret = self.codegen_ffi_ret(def_id, method);
+                    format!("fn {name_lower}_{fn_name}({args}) -> {ret};")
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+            stream.push_str(&format!(
+                r###"
+            #[cfg(not(feature = "goffi-mock"))]
             #[link(name = "{}", kind = "{}")]
             extern "C" {{
             {ffi_fns}
         }}
         "###,
-            self.config.go_clib_name_base, self.config.rustc_link_kind_goffi,
-        ));
+                self.config.go_clib_name_base, self.config.rustc_link_kind_goffi,
+            ));
+        }
+
+        self.codegen_goffi_mock(def_id, stream, s);
 
         let store_to_rust_fns = s
             .methods
@@ -121,10 +293,229 @@ impl RustCodegenBackend for RustGeneratorBackend {
             .collect::<Vec<String>>()
             .join("\n");
         stream.push_str(&store_to_rust_fns);
+
+        let client_fns = s
+            .methods
+            .iter()
+            .filter_map(|method| self.codegen_goffi_client_method(def_id, method))
+            .collect::<Vec<String>>()
+            .join("\n");
+        stream.push_str(&format!(
+            r###"
+            /// The safe and typed client of `{name}`, which encodes the arguments and decodes the result.
+            pub struct {name}Client;
+
+            impl {name}Client {{
+                {client_fns}
+            }}
+            "###
+        ));
     }
 }
 
 impl RustGeneratorBackend {
+    /// The Rust mock replacing the Go link under the `goffi-mock` feature,
+    /// which dispatches the GoFFI calls to the closures installed by the tests.
+    fn codegen_goffi_mock(&self, def_id: DefId, stream: &mut String, s: &Service) {
+        let name = self.context.rust_name(def_id);
+        let name_lower = name.to_lowercase();
+        let codec = self.codec_message();
+        let mut installers = String::new();
+        for method in &s.methods {
+            let fn_name = (&**method.name).fn_ident();
+            let ffi_name = format!("{name_lower}_{fn_name}");
+            let static_name = format!("{}_MOCK", ffi_name.to_uppercase());
+            let args = self.codegen_ffi_args_param(def_id, method);
+            let ffi_ret = self.codegen_ffi_ret(def_id, method);
+            let arg_types = method
+                .args
+                .iter()
+                .map(|arg| self.rust_codegen_item_ty(&arg.ty.kind).to_string())
+                .collect::<Vec<String>>()
+                .join(", ");
+            let decode_args = method
+                .args
+                .iter()
+                .map(|arg| {
+                    let ident = (&**arg.name).snake_ident();
+                    if arg.ty.is_scalar() {
+                        format!("{ident}")
+                    } else {
+                        let ty_name = self.rust_codegen_item_ty(&arg.ty.kind);
+                        format!("::fcplug::RustFfiArg::<{ty_name}>::from({ident}).try_to_object::<{codec}>().expect(\"failed to decode the GoFFI argument `{ident}`\")")
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+            let is_empty_ret = self.context.is_empty_ty(&method.ret.kind);
+            let ret_ty_name = self.rust_codegen_item_ty(&method.ret.kind);
+            let declared_error = self.context.declared_error(&self.config, def_id, method);
+            let ok_ty = if is_empty_ret {
+                "()".to_string()
+            } else {
+                ret_ty_name.to_string()
+            };
+            let mock_ret = if method.ret.is_scalar() && !is_empty_ret {
+                ok_ty.clone()
+            } else if let Some(error) = &declared_error {
+                format!("::fcplug::TypedResult<{ok_ty}, {error}>")
+            } else {
+                format!("::fcplug::ABIResult<{ok_ty}>")
+            };
+            let body = if method.ret.is_scalar() && !is_empty_ret {
+                format!("mock({decode_args})")
+            } else {
+                let on_ok = if is_empty_ret {
+                    "Ok(()) => ::fcplug::GoFfiResult { code: ::fcplug::RC_NO_ERROR, data_ptr: 0 },"
+                        .to_string()
+                } else {
+                    format!(
+                        r###"Ok(ret) => {{
+                            let mut ret = match ::fcplug::TBytes::<{ret_ty_name}>::try_from::<{codec}>(ret) {{
+                                Ok(ret) => ret.bytes,
+                                Err(e) => return e.into(),
+                            }};
+                            {ffi_name}_set_result(::fcplug::Buffer::from_vec_mut(&mut ret))
+                        }}"###
+                    )
+                };
+                let on_err = if declared_error.is_some() {
+                    format!("Err(e) => ::fcplug::GoFfiResult::from_typed_err::<_, {codec}>(e),")
+                } else {
+                    "Err(e) => e.into(),".to_string()
+                };
+                format!(
+                    r###"match mock({decode_args}) {{
+                        {on_ok}
+                        {on_err}
+                    }}"###
+                )
+            };
+            let not_installed = format!(
+                r###"::fcplug::FfiError::new(::fcplug::RC_UNAVAILABLE, "the GoFFI mock of `{name}::{fn_name}` is not installed, install it by `{name}Mock::{fn_name}`")"###
+            );
+            let unavailable = if method.ret.is_scalar() && !is_empty_ret {
+                format!("::fcplug::unavailable_scalar({not_installed})")
+            } else {
+                format!("::fcplug::GoFfiResult::from_err({not_installed})")
+            };
+            stream.push_str(&format!(
+                r###"
+                #[cfg(feature = "goffi-mock")]
+                static {static_name}: ::std::sync::RwLock<Option<::std::sync::Arc<dyn Fn({arg_types}) -> {mock_ret} + Send + Sync>>> = ::std::sync::RwLock::new(None);
+                #[cfg(feature = "goffi-mock")]
+                #[inline]
+                unsafe fn {ffi_name}({args}) -> {ffi_ret} {{
+                    let Some(mock) = {static_name}.read().unwrap().clone() else {{
+                        return {unavailable};
+                    }};
+                    {body}
+                }}
+                "###
+            ));
+            installers.push_str(&format!(
+                r###"/// Install the mock of `{fn_name}`, which replaces the previous one.
+                pub fn {fn_name}(f: impl Fn({arg_types}) -> {mock_ret} + Send + Sync + 'static) {{
+                    *{static_name}.write().unwrap() = Some(::std::sync::Arc::new(f));
+                }}
+                "###
+            ));
+        }
+        stream.push_str(&format!(
+            r###"
+            /// The mock of `{name}` for the Rust tests without the Go toolchain, enabled by the `goffi-mock` feature.
+            /// The mocks are global, so the tests installing different mocks of the same method should not run in parallel.
+            #[cfg(feature = "goffi-mock")]
+            pub struct {name}Mock;
+
+            #[cfg(feature = "goffi-mock")]
+            impl {name}Mock {{
+                {installers}
+            }}
+            "###
+        ));
+    }
+    /// Whether the RustFFI method receives the decoded arguments and returns the object,
+    /// the method returning a scalar keeps the raw arguments since it cannot report the decode error.
+    fn is_typed_rustffi(&self, method: &Method) -> bool {
+        self.config.typed_rustffi && !method.ret.is_scalar()
+    }
+    /// Decode the buffer arguments of the typed RustFFI method, returning `RC_DECODE` on failure
+    fn codegen_rustffi_decode_args(&self, method: &Method) -> String {
+        let codec = self.codec_message();
+        method
+            .args
+            .iter()
+            .filter(|arg| !arg.ty.is_scalar())
+            .map(|arg| {
+                let ident = (&**arg.name).snake_ident();
+                let ty_name = self.rust_codegen_item_ty(&arg.ty.kind);
+                format!("let {ident} = ::fcplug::RustFfiArg::<{ty_name}>::from({ident}).try_to_object::<{codec}>()?;\n")
+            })
+            .collect()
+    }
+    fn codegen_goffi_client_method(
+        &self,
+        service_def_id: DefId,
+        method: &Method,
+    ) -> Option<String> {
+        // the scalar result is not supported by the Go side
+        if method.ret.is_scalar() && !self.context.is_empty_ty(&method.ret.kind) {
+            return None;
+        }
+        let name = self.context.rust_name(service_def_id);
+        let method_name = (&**method.name).fn_ident();
+        let codec = self.codec_message();
+        let args = method
+            .args
+            .iter()
+            .map(|arg| {
+                let ident = (&**arg.name).snake_ident();
+                let ty_name = self.rust_codegen_item_ty(&arg.ty.kind);
+                if arg.ty.is_scalar() {
+                    format!("{ident}: {ty_name}")
+                } else {
+                    format!("{ident}: &{ty_name}")
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        let encode_args = method
+            .args
+            .iter()
+            .filter(|arg| !arg.ty.is_scalar())
+            .map(|arg| {
+                let ident = (&**arg.name).snake_ident();
+                format!("let {ident} = ::fcplug::TBytes::new(::fcplug::TryToBytes::<{codec}>::try_to_bytes({ident})?);\n")
+            })
+            .collect::<String>();
+        let args_ident = method
+            .args
+            .iter()
+            .map(|arg| (&**arg.name).snake_ident().to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        let ret_ty_name = self.rust_codegen_item_ty(&method.ret.kind);
+        let (ret_ty, turbofish) = if self.context.is_empty_ty(&method.ret.kind) {
+            ("()".to_string(), String::new())
+        } else {
+            (ret_ty_name.to_string(), format!("::<{ret_ty_name}>"))
+        };
+        let ret = match self
+            .context
+            .declared_error(&self.config, service_def_id, method)
+        {
+            Some(error) => format!("::fcplug::TypedResult<{ret_ty}, {error}>"),
+            None => format!("::fcplug::ABIResult<{ret_ty}>"),
+        };
+        let ust = &self.config.rust_mod_impl_name;
+        Some(format!(
+            r###"pub fn {method_name}({args}) -> {ret} {{
+                {encode_args}unsafe {{ <{ust} as {name}Call>::{method_name}{turbofish}({args_ident}) }}
+            }}
+            "###
+        ))
+    }
     fn codegen_ffi_args_param(&self, service_def_id: DefId, method: &Method) -> String {
         match self.context.service_type(service_def_id) {
             ServiceType::RustFfi => method
@@ -164,7 +555,7 @@ impl RustGeneratorBackend {
                 .iter()
                 .map(|arg| {
                     let ident = (&**arg.name).snake_ident();
-                    if arg.ty.is_scalar() {
+                    if arg.ty.is_scalar() || self.is_typed_rustffi(method) {
                         format!("{ident}")
                     } else {
                         format!("::fcplug::RustFfiArg::from({ident})")
@@ -195,7 +586,7 @@ impl RustGeneratorBackend {
                 .map(|arg| {
                     let ident = (&**arg.name).snake_ident();
                     let ty_name = self.rust_codegen_item_ty(&arg.ty.kind);
-                    if arg.ty.is_scalar() {
+                    if arg.ty.is_scalar() || self.is_typed_rustffi(method) {
                         format!("{ident}: {ty_name}")
                     } else {
                         format!("{ident}: ::fcplug::RustFfiArg<{ty_name}>")
@@ -219,14 +610,41 @@ impl RustGeneratorBackend {
                 .join(", "),
         }
     }
+    /// The codec message of the IDL types
+    fn codec_message(&self) -> &'static str {
+        match self.config.idl_type {
+            IdlType::Proto | IdlType::ProtoNoCodec => "::fcplug::protobuf::PbMessage<_>",
+            IdlType::Thrift | IdlType::ThriftNoCodec => "::fcplug::serde::JsonMessage<_>",
+        }
+    }
     fn codegen_method_ret(&self, service_def_id: DefId, method: &Method) -> String {
         let ty_name = self.rust_codegen_item_ty(&method.ret.kind);
+        if let Some(error) = self
+            .context
+            .declared_error(&self.config, service_def_id, method)
+        {
+            return match self.context.service_type(service_def_id) {
+                ServiceType::RustFfi if !self.context.is_empty_ty(&method.ret.kind) => {
+                    if self.is_typed_rustffi(method) {
+                        format!("::fcplug::TypedResult<{ty_name}, {error}>")
+                    } else {
+                        format!("::fcplug::TypedResult<::fcplug::TBytes<{ty_name}>, {error}>")
+                    }
+                }
+                ServiceType::GoFfi if !self.context.is_empty_ty(&method.ret.kind) => {
+                    format!("::fcplug::TypedResult<T, {error}>")
+                }
+                _ => format!("::fcplug::TypedResult<(), {error}>"),
+            };
+        }
         match self.context.service_type(service_def_id) {
             ServiceType::RustFfi => {
                 if self.context.is_empty_ty(&method.ret.kind) {
                     format!("::fcplug::ABIResult<()>")
                 } else if method.ret.is_scalar() {
                     format!("{ty_name}")
+                } else if self.is_typed_rustffi(method) {
+                    format!("::fcplug::ABIResult<{ty_name}>")
                 } else {
                     format!("::fcplug::ABIResult<::fcplug::TBytes<{ty_name}>>")
                 }
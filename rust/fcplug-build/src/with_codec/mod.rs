@@ -2,18 +2,28 @@ use std::fs;
 use std::process::Command;
 
 use crate::config::IdlType;
+use crate::error::IoContext;
 use crate::generator::{Generator, ImportPkg, MidOutput};
-use crate::{deal_output, deal_result, CODE_IO};
+use crate::{run_command, FcplugError};
 
 mod gen_go_codec;
 mod gen_rust_codec;
 
 impl Generator {
-    pub(crate) fn _gen_code(self) -> MidOutput {
-        self.gen_go_codec_code();
-        MidOutput {
+    pub(crate) fn _gen_code(self) -> Result<MidOutput, FcplugError> {
+        self.gen_go_codec_code()?;
+        if self.config.go_log_bridge {
+            self.go_lib_cdecls
+                .borrow_mut()
+                .push_str("extern void fcplug_go_log(struct Buffer record);\n");
+        }
+        Ok(MidOutput {
+            go_lib_common: self.go_lib_common_code(),
+            go_lib_cgo_common: self.go_lib_cgo_code(),
+            go_lib_fake_common: self.go_lib_fake_code(),
+            go_main_common: self.go_main_common_code(),
             rust_clib_includes: r###"
-typedef int8_t ResultCode;
+typedef int32_t ResultCode;
 
 typedef struct Buffer {
   uint8_t *ptr;
@@ -34,11 +44,18 @@ typedef struct GoFfiResult {
 void free_buffer(struct Buffer buf);
 uintptr_t leak_buffer(struct Buffer buf);
 
+struct RustFfiResult fcplug_init(struct Buffer config);
+struct RustFfiResult fcplug_health(void);
+struct RustFfiResult fcplug_shutdown(uint64_t timeout_ms);
+struct RustFfiResult fcplug_log_init(struct Buffer config);
+void fcplug_log_set_callback(void (*callback)(struct Buffer record));
+void fcplug_log_write(struct Buffer record);
+
 "###
             .to_string(),
             mod_requires: vec![
                 "github.com/andeya/gust@v1.5.2".to_string(),
-                "github.com/bytedance/sonic@latest".to_string(),
+                "github.com/bytedance/sonic@v1.9.1".to_string(),
                 match self.config.idl_type {
                     IdlType::Proto | IdlType::ProtoNoCodec => "google.golang.org/protobuf@v1.26.0",
                     IdlType::Thrift | IdlType::ThriftNoCodec => "github.com/apache/thrift@v0.13.0",
@@ -48,7 +65,7 @@ uintptr_t leak_buffer(struct Buffer buf);
             imports: vec![
                 ImportPkg {
                     in_main: true,
-                    in_lib: false,
+                    in_lib: self.config.go_host,
                     import_path: "github.com/andeya/gust".to_string(),
                     use_code: "var _ gust.EnumResult[any, any]".to_string(),
                 },
@@ -73,6 +90,26 @@ uintptr_t leak_buffer(struct Buffer buf);
                     },
                     IdlType::Thrift | IdlType::ThriftNoCodec => ImportPkg::default(),
                 },
+                if self.config.go_log_bridge {
+                    ImportPkg {
+                        in_main: false,
+                        in_lib: true,
+                        import_path: "log/slog".to_string(),
+                        use_code: "var _ slog.Level".to_string(),
+                    }
+                } else {
+                    ImportPkg::default()
+                },
+                if self.config.go_log_bridge {
+                    ImportPkg {
+                        in_main: false,
+                        in_lib: true,
+                        import_path: "sync/atomic".to_string(),
+                        use_code: "var _ atomic.Bool".to_string(),
+                    }
+                } else {
+                    ImportPkg::default()
+                },
                 ImportPkg {
                     in_main: true,
                     in_lib: false,
@@ -80,55 +117,50 @@ uintptr_t leak_buffer(struct Buffer buf);
                     use_code: format!("var _ {}.ResultCode", self.config.gomod_name),
                 },
             ],
-        }
+        })
     }
-    fn gen_go_codec_code(&self) {
+    fn gen_go_codec_code(&self) -> Result<(), FcplugError> {
+        if self.config.goffi_mock || self.config.in_memory {
+            return Ok(());
+        }
         match self.config.idl_type {
             IdlType::Proto | IdlType::ProtoNoCodec => {
-                deal_output(
+                run_command(
                     Command::new("protoc")
                         .arg(format!(
                             "--proto_path={}",
-                            self.config.target_out_dir.to_str().unwrap()
-                        ))
-                        .arg(format!(
-                            "--go_out={}",
-                            self.config.pkg_dir.to_str().unwrap()
+                            self.config.target_out_dir.display()
                         ))
-                        .arg(self.config.idl_file.as_os_str())
-                        .output(),
-                );
+                        .arg(format!("--go_out={}", self.config.pkg_dir.display()))
+                        .arg(self.config.idl_file.as_os_str()),
+                )?;
             }
             IdlType::Thrift | IdlType::ThriftNoCodec => {
-                deal_output(
+                run_command(
                     Command::new("thriftgo")
                         .arg(format!("-g=go"))
                         .arg(format!(
                             "-o={}",
-                            self.config
-                                .target_out_dir
-                                .join("gen-thrift")
-                                .to_str()
-                                .unwrap()
+                            self.config.target_out_dir.join("gen-thrift").display()
                         ))
-                        .arg(self.config.idl_file.as_os_str())
-                        .output(),
-                );
+                        .arg(self.config.idl_file.as_os_str()),
+                )?;
                 let go_mod_name = &self.config.gomod_name;
-                deal_result(
-                    CODE_IO,
-                    fs::rename(
-                        self.config
-                            .target_out_dir
-                            .join("gen-thrift")
-                            .join(&go_mod_name)
-                            .join(&format!("{go_mod_name}.go")),
-                        self.config
-                            .pkg_dir
-                            .join(&format!("{go_mod_name}.thrift.go")),
-                    ),
-                );
+                let gen_file = self
+                    .config
+                    .target_out_dir
+                    .join("gen-thrift")
+                    .join(&go_mod_name)
+                    .join(&format!("{go_mod_name}.go"));
+                fs::rename(
+                    &gen_file,
+                    self.config
+                        .pkg_dir
+                        .join(&format!("{go_mod_name}.thrift.go")),
+                )
+                .with_path(&gen_file)?;
             }
         };
+        Ok(())
     }
 }
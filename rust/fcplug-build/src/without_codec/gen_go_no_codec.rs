@@ -3,8 +3,8 @@
 
 use std::sync::Arc;
 
-use pilota_build::DefId;
 use pilota_build::rir::{Message, Method, Service};
+use pilota_build::DefId;
 
 use crate::generator::{GoCodegenBackend, GoGeneratorBackend};
 
@@ -22,14 +22,20 @@ impl GoCodegenBackend for GoGeneratorBackend {
             .collect::<Vec<String>>()
             .join("    \n");
         let struct_name = self.struct_name(def_id);
-        format!(r###"
+        format!(
+            r###"
 type {struct_name} struct {{
     {files}
 }}
-        "###)
+        "###
+        )
     }
 
-    fn codegen_rustffi_iface_method(&self, service_def_id: DefId, method: &Arc<Method>) -> Option<(String, String)> {
+    fn codegen_rustffi_iface_method(
+        &self,
+        service_def_id: DefId,
+        method: &Arc<Method>,
+    ) -> Option<(String, String)> {
         // TODO
         None
     }
@@ -39,7 +45,20 @@ type {struct_name} struct {{
         String::new()
     }
 
-    fn codegen_goffi_iface_method(&self, service_def_id: DefId, method: &Arc<Method>) -> Option<String> {
+    fn codegen_rustffi_fake_method(
+        &self,
+        service_def_id: DefId,
+        method: &Arc<Method>,
+    ) -> Option<(String, String)> {
+        // TODO
+        None
+    }
+
+    fn codegen_goffi_iface_method(
+        &self,
+        service_def_id: DefId,
+        method: &Arc<Method>,
+    ) -> Option<String> {
         // TODO
         None
     }
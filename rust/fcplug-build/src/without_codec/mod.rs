@@ -1,14 +1,19 @@
 use crate::generator::{Generator, MidOutput};
+use crate::FcplugError;
 
 mod gen_go_no_codec;
 mod gen_rust_no_codec;
 
 impl Generator {
-    pub(crate) fn _gen_code(self) -> MidOutput {
-        MidOutput {
+    pub(crate) fn _gen_code(self) -> Result<MidOutput, FcplugError> {
+        Ok(MidOutput {
+            go_lib_common: "".to_string(),
+            go_lib_cgo_common: "".to_string(),
+            go_lib_fake_common: "".to_string(),
+            go_main_common: "".to_string(),
             rust_clib_includes: "".to_string(),
             mod_requires: vec![],
             imports: vec![],
-        }
+        })
     }
 }
@@ -0,0 +1,260 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A scratch directory removed on drop
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("fcplug-cli-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn fcplug(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_fcplug"))
+        .current_dir(dir)
+        .args(args)
+        .env_remove("FCPLUG_GO_ROOT")
+        .env_remove("GOROOT")
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+#[test]
+fn test_init() {
+    let tmp = TempDir::new("init");
+    let output = fcplug(
+        &tmp.0,
+        &[
+            "init",
+            "echo-demo",
+            "--thrift",
+            "--go-mod-parent",
+            "github.com/andeya/demo/",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let crate_dir = tmp.0.join("echo-demo");
+    let read = |name: &str| fs::read_to_string(crate_dir.join(name)).unwrap();
+    let manifest = read("Cargo.toml");
+    assert!(manifest.contains("name = \"echo-demo\"\n"));
+    assert!(manifest.contains("crate-type = [\"rlib\", \"staticlib\"]\n"));
+    assert!(manifest.contains("[features]\n"));
+    assert!(manifest.contains("goffi-mock = []\n"));
+    // the pilota of the generated code is the one of the workspace
+    let workspace: toml::Value =
+        fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("../../Cargo.toml"))
+            .unwrap()
+            .parse()
+            .unwrap();
+    let pilota = workspace["workspace"]["dependencies"]["pilota"]
+        .as_str()
+        .unwrap();
+    assert!(
+        manifest.contains(&format!("pilota = \"{pilota}\"\n")),
+        "{manifest}"
+    );
+    assert!(read("echo_demo.thrift").contains("service RustFFI {"));
+    let build_rs = read("build.rs");
+    assert!(build_rs.contains("idl_file: \"./echo_demo.thrift\".into(),"));
+    assert!(build_rs.contains("go_mod_parent: \"github.com/andeya/demo\".into(),"));
+    assert_eq!(read("src/lib.rs"), "pub mod echo_demo_ffi;\n");
+    assert_eq!(
+        read("go.mod"),
+        "module github.com/andeya/demo/echo_demo\n\ngo 1.18\n"
+    );
+
+    // the existing files are kept
+    fs::write(crate_dir.join("src/lib.rs"), "mod custom;\n").unwrap();
+    let output = fcplug(&tmp.0, &["init", "echo-demo"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("skip existing echo-demo/src/lib.rs\n"));
+    assert_eq!(read("src/lib.rs"), "mod custom;\n");
+    // the proto IDL is added beside the thrift one
+    assert!(read("echo_demo.proto").contains("rpc echo_rs (Ping) returns (Pong) {}"));
+}
+
+#[test]
+fn test_init_without_dir() {
+    let tmp = TempDir::new("init-without-dir");
+    let output = fcplug(&tmp.0, &["init"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("init requires one crate directory"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_check() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = TempDir::new("check");
+    let output = fcplug(&tmp.0, &["init", "echo_check"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let crate_dir = tmp.0.join("echo_check");
+    // the formatting is out of the test, which needs no Go toolchain
+    let go_root = tmp.0.join("go");
+    fs::create_dir_all(go_root.join("bin")).unwrap();
+    let gofmt = go_root.join("bin").join("gofmt");
+    fs::write(&gofmt, "#!/bin/sh\nexec cat\n").unwrap();
+    fs::set_permissions(&gofmt, fs::Permissions::from_mode(0o755)).unwrap();
+    let check = || {
+        Command::new(env!("CARGO_BIN_EXE_fcplug"))
+            .current_dir(&crate_dir)
+            .arg("check")
+            .env("FCPLUG_GO_ROOT", &go_root)
+            .output()
+            .unwrap()
+    };
+
+    // the valid IDL is generated, but not written yet
+    let output = check();
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    let out = stdout(&output);
+    assert!(
+        out.contains("stale: src/echo_check_ffi/echo_check_gen.rs\n"),
+        "{out}"
+    );
+    assert!(out.contains("stale: echo_check_gen_fake.go\n"), "{out}");
+    assert!(out.contains("run 'fcplug generate' or 'cargo build' to update them"));
+    // the cargo directives printed by pilota are not in the output
+    assert!(!out.contains("cargo:"), "{out}");
+
+    // the invalid IDL fails the check
+    fs::write(
+        crate_dir.join("echo_check.proto"),
+        "syntax = \"proto3\";\n\nmessage Ping {\n  string msg = 1;\n}\n\nservice Echo {\n  rpc echo (Ping) returns (Ping) {}\n}\n",
+    )
+    .unwrap();
+    let output = check();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(
+        stderr(&output).contains("Service name 'Echo' should end with 'GoFFI' or 'RustFFI'."),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn test_generate_skipped() {
+    use std::time::{Duration, SystemTime};
+
+    let tmp = TempDir::new("generate");
+    let output = fcplug(&tmp.0, &["init", "echo_gen"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let crate_dir = tmp.0.join("echo_gen");
+    fs::create_dir_all(crate_dir.join("cgobin")).unwrap();
+    fs::write(
+        crate_dir.join("cgobin/clib_goffi_impl.go"),
+        "package main\n",
+    )
+    .unwrap();
+    fs::write(
+        crate_dir.join("cgobin/clib_goffi_gen_linux_amd64.go"),
+        "package main\n",
+    )
+    .unwrap();
+    fs::write(crate_dir.join("echo_ext.go"), "package echo_gen\n").unwrap();
+    fs::write(
+        crate_dir.join("echo.pb.go"),
+        "// Code generated by protoc-gen-go. DO NOT EDIT.\n\npackage echo_gen\n",
+    )
+    .unwrap();
+    // the GoFFI is mocked, which needs no Go toolchain
+    let generate = || {
+        Command::new(env!("CARGO_BIN_EXE_fcplug"))
+            .current_dir(&crate_dir)
+            .arg("generate")
+            .env("CARGO_FEATURE_GOFFI_MOCK", "1")
+            .env_remove("FCPLUG_GO_ROOT")
+            .output()
+            .unwrap()
+    };
+    let rust_gen_file = crate_dir.join("src/echo_gen_ffi/echo_gen_gen.rs");
+    let modified = || fs::metadata(&rust_gen_file).unwrap().modified().unwrap();
+
+    let output = generate();
+    assert!(output.status.success(), "{}", stderr(&output));
+    let out = stdout(&output);
+    let watched = |name: &str| {
+        out.contains(&format!(
+            "cargo:rerun-if-changed={}\n",
+            crate_dir.join(name).display()
+        ))
+    };
+    for name in [
+        "echo_gen.proto",
+        "Cargo.toml",
+        "go.mod",
+        "go.sum",
+        "echo_ext.go",
+        "cgobin/clib_goffi_impl.go",
+    ] {
+        assert!(watched(name), "{name} is not watched: {out}");
+    }
+    // the tidy IDL in the target directory is not watched
+    let watched_idl_files = out
+        .lines()
+        .filter(|line| line.starts_with("cargo:rerun-if-changed=") && line.ends_with(".proto"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        watched_idl_files,
+        [format!(
+            "cargo:rerun-if-changed={}",
+            crate_dir.join("echo_gen.proto").display()
+        )]
+    );
+    // neither the directories nor the generated files are watched
+    for name in [
+        "cgobin",
+        "cgobin/clib_goffi_gen_linux_amd64.go",
+        "echo.pb.go",
+    ] {
+        assert!(!watched(name), "{name} is watched: {out}");
+    }
+
+    // the same inputs skip the generation
+    let old = SystemTime::now() - Duration::from_secs(60);
+    fs::File::options()
+        .write(true)
+        .open(&rust_gen_file)
+        .unwrap()
+        .set_modified(old)
+        .unwrap();
+    let output = generate();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(modified(), old);
+
+    // the changed IDL is generated again
+    let idl_file = crate_dir.join("echo_gen.proto");
+    let idl = fs::read_to_string(&idl_file).unwrap();
+    fs::write(
+        &idl_file,
+        idl.replacen("string msg = 1;", "string text = 1;", 1),
+    )
+    .unwrap();
+    let output = generate();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_ne!(modified(), old);
+    assert!(fs::read_to_string(&rust_gen_file)
+        .unwrap()
+        .contains("pub text: "));
+}
@@ -0,0 +1,325 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ABIResult, ResultCode, ResultMsg, RC_DECLARED_ERROR, RC_DECODE, RC_ENCODE, RC_NO_ERROR,
+    RC_UNKNOWN,
+};
+
+/// The category of an [`FfiError`], derived from its code by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Decode,
+    Encode,
+    /// The error declared in the IDL
+    Declared,
+    /// Other framework error, whose code is negative
+    Framework,
+    /// User error, whose code is positive
+    User,
+    Unknown,
+}
+
+impl ErrorKind {
+    pub fn from_code(code: ResultCode) -> Self {
+        match code {
+            RC_DECODE => Self::Decode,
+            RC_ENCODE => Self::Encode,
+            RC_DECLARED_ERROR => Self::Declared,
+            RC_UNKNOWN | RC_NO_ERROR => Self::Unknown,
+            code if code < RC_NO_ERROR => Self::Framework,
+            _ => Self::User,
+        }
+    }
+}
+
+/// The error crossing the FFI boundary.
+///
+/// It is encoded as JSON on the wire, except the declared error (`RC_DECLARED_ERROR`)
+/// whose data is the encoded message declared in the IDL.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FfiError {
+    pub code: ResultCode,
+    pub kind: ErrorKind,
+    pub msg: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cause: Option<Box<FfiError>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub details: BTreeMap<String, String>,
+    /// The encoded declared error
+    #[serde(skip)]
+    pub(crate) payload: Vec<u8>,
+}
+
+impl FfiError {
+    /// `code` should be positive for the user errors, the negative ones are reserved for the framework.
+    pub fn new(code: ResultCode, msg: impl Into<String>) -> Self {
+        Self {
+            code,
+            kind: ErrorKind::from_code(code),
+            msg: msg.into(),
+            cause: None,
+            details: BTreeMap::new(),
+            payload: Vec::new(),
+        }
+    }
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+    pub fn with_cause(mut self, cause: impl Into<FfiError>) -> Self {
+        self.cause = Some(Box::new(cause.into()));
+        self
+    }
+    pub fn with_detail(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.details.insert(key.into(), value.into());
+        self
+    }
+    /// Iterate over the error and its causes
+    pub fn chain(&self) -> impl Iterator<Item = &FfiError> {
+        std::iter::successors(Some(self), |e| e.cause.as_deref())
+    }
+    #[inline]
+    pub(crate) fn declared(payload: Vec<u8>) -> Self {
+        let mut e = Self::new(RC_DECLARED_ERROR, "declared error");
+        e.payload = payload;
+        e
+    }
+    pub(crate) fn into_payload(self) -> Vec<u8> {
+        self.payload
+    }
+    /// Encode the error for the other side
+    pub(crate) fn to_wire(&self) -> Vec<u8> {
+        if self.code == RC_DECLARED_ERROR {
+            return self.payload.clone();
+        }
+        serde_json::to_vec(self).unwrap_or_else(|_| self.msg.clone().into_bytes())
+    }
+    /// Decode the error from the other side, the plain text is kept as the message.
+    pub(crate) fn from_wire(code: ResultCode, data: Vec<u8>) -> Self {
+        if code == RC_DECLARED_ERROR {
+            return Self::declared(data);
+        }
+        match serde_json::from_slice::<FfiError>(&data) {
+            Ok(mut e) => {
+                e.code = code;
+                e
+            }
+            Err(_) => Self::new(code, String::from_utf8_lossy(&data)),
+        }
+    }
+}
+
+/// The message of the error, and `{:#}` appends the messages of its causes
+impl Display for FfiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.msg)?;
+        if f.alternate() {
+            for cause in self.chain().skip(1) {
+                write!(f, ": {}", cause.msg)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FfiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.cause.as_deref().map(|cause| cause as _)
+    }
+}
+
+impl From<ResultMsg> for FfiError {
+    #[inline]
+    fn from(value: ResultMsg) -> Self {
+        Self::new(value.code, value.msg)
+    }
+}
+
+/// The error chain becomes the cause chain, whose `FfiError`s keep their codes,
+/// and the backtrace is kept in the `backtrace` detail in debug builds.
+impl From<anyhow::Error> for FfiError {
+    fn from(value: anyhow::Error) -> Self {
+        let code = value
+            .downcast_ref::<FfiError>()
+            .map_or(RC_UNKNOWN, |e| e.code);
+        Self::from_anyhow(code, value)
+    }
+}
+
+impl From<std::io::Error> for FfiError {
+    #[inline]
+    fn from(value: std::io::Error) -> Self {
+        anyhow::Error::from(value).into()
+    }
+}
+
+impl FfiError {
+    fn from_anyhow(code: ResultCode, err: anyhow::Error) -> Self {
+        let mut chain = err.chain();
+        let mut this = chain
+            .next()
+            .map_or_else(|| Self::new(code, ""), Self::from_cause);
+        if this.code != code {
+            this.code = code;
+            this.kind = ErrorKind::from_code(code);
+        }
+        this.cause = chain.rev().fold(None, |cause, e| {
+            Some(Box::new(FfiError {
+                cause,
+                ..Self::from_cause(e)
+            }))
+        });
+        #[cfg(debug_assertions)]
+        if err.backtrace().status() == std::backtrace::BacktraceStatus::Captured {
+            this.details
+                .insert("backtrace".to_string(), err.backtrace().to_string());
+        }
+        this
+    }
+    /// One error of the chain without its causes, which is `RC_UNKNOWN` unless it is an `FfiError`
+    fn from_cause(err: &(dyn std::error::Error + 'static)) -> Self {
+        match err.downcast_ref::<FfiError>() {
+            Some(e) => FfiError {
+                cause: None,
+                ..e.clone()
+            },
+            None => Self::new(RC_UNKNOWN, err.to_string()),
+        }
+    }
+}
+
+/// Maps the domain error to a stable result code, which should be positive.
+///
+/// ```ignore
+/// impl ErrorCode for OrderError {
+///     fn error_code(&self) -> ResultCode {
+///         match self {
+///             OrderError::NotFound => 1,
+///             OrderError::Expired => 2,
+///         }
+///     }
+/// }
+///
+/// let order = find_order(id).coded()?;
+/// ```
+pub trait ErrorCode {
+    fn error_code(&self) -> ResultCode;
+}
+
+/// Converts the result of the domain error into [`ABIResult`] with its [`ErrorCode`]
+pub trait CodedResult<T> {
+    fn coded(self) -> ABIResult<T>;
+}
+
+impl<T, E> CodedResult<T> for Result<T, E>
+where
+    E: ErrorCode + Into<anyhow::Error>,
+{
+    #[inline]
+    fn coded(self) -> ABIResult<T> {
+        self.map_err(|e| FfiError::from_anyhow(e.error_code(), e.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ABIResult, CodedResult, ErrorCode, ErrorKind, FfiError, ResultCode, RustFfiResult,
+        TypedError, RC_DECODE, RC_UNKNOWN,
+    };
+
+    #[test]
+    fn test_error_source() {
+        let err = FfiError::new(1, "create order")
+            .with_cause(FfiError::new(RC_DECODE, "invalid request"));
+        let source = std::error::Error::source(&err).unwrap();
+        assert_eq!(source.to_string(), "invalid request");
+
+        // the FfiError and the TypedError are std errors, so `?` converts them into anyhow or a boxed error
+        fn into_anyhow() -> anyhow::Result<()> {
+            Err(FfiError::new(1, "create order"))?
+        }
+        fn into_boxed() -> Result<(), Box<dyn std::error::Error>> {
+            Err(TypedError::<String>::Declared("out of stock".to_string()))?
+        }
+        assert_eq!(into_anyhow().unwrap_err().to_string(), "create order");
+        assert_eq!(into_boxed().unwrap_err().to_string(), "out of stock");
+    }
+
+    #[test]
+    fn test_cause_codes() {
+        let inner = FfiError::new(RC_DECODE, "invalid request").with_detail("field", "id");
+        let err = anyhow::Error::new(inner.clone())
+            .context("parse order")
+            .context("create order");
+        let err = FfiError::from(err);
+        // the outermost code is the one of the root FfiError, the others are not FfiErrors
+        assert_eq!(
+            err.chain()
+                .map(|e| (e.code, e.msg.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (RC_DECODE, "create order"),
+                (RC_UNKNOWN, "parse order"),
+                (RC_DECODE, "invalid request")
+            ]
+        );
+        assert_eq!(err.chain().last().unwrap().details, inner.details);
+
+        fn read() -> ABIResult<Vec<u8>> {
+            Ok(std::fs::read("/fcplug/not/found")?)
+        }
+        let err = read().unwrap_err();
+        assert_eq!((err.code, err.kind), (RC_UNKNOWN, ErrorKind::Unknown));
+    }
+
+    #[test]
+    fn test_error_chain() {
+        let err = FfiError::new(1, "create order")
+            .with_detail("order_id", "42")
+            .with_cause(FfiError::new(RC_DECODE, "invalid request"));
+        let mut ret = RustFfiResult::from(ABIResult::<Vec<u8>>::Err(err.clone()));
+        assert_eq!(ret.code, 1);
+        let got = ABIResult::<Vec<u8>>::from(&mut ret).unwrap_err();
+        unsafe { ret.data.mem_free() };
+        assert_eq!(got, err);
+        assert_eq!(got.kind, ErrorKind::User);
+        assert_eq!(got.to_string(), "create order");
+        assert_eq!(format!("{got:#}"), "create order: invalid request");
+        assert_eq!(
+            got.chain().map(|e| e.kind).collect::<Vec<_>>(),
+            [ErrorKind::User, ErrorKind::Decode]
+        );
+    }
+
+    #[test]
+    fn test_error_conversion() {
+        #[derive(Debug)]
+        struct NotFound;
+        impl std::fmt::Display for NotFound {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "not found")
+            }
+        }
+        impl std::error::Error for NotFound {}
+        impl ErrorCode for NotFound {
+            fn error_code(&self) -> ResultCode {
+                404
+            }
+        }
+
+        fn find() -> ABIResult<()> {
+            Err(anyhow::Error::new(NotFound).context("find order"))?
+        }
+        let err = find().unwrap_err();
+        assert_eq!(format!("{err:#}"), "find order: not found");
+        assert_eq!(err.chain().count(), 2);
+
+        let err = Err::<(), _>(NotFound).coded().unwrap_err();
+        assert_eq!((err.code, err.kind), (404, ErrorKind::User));
+    }
+}
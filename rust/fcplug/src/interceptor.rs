@@ -0,0 +1,252 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use crate::lifecycle::InFlight;
+use crate::{ABIResult, Buffer, FfiError, GoFfiResult, RustFfiResult, RC_UNAVAILABLE};
+
+/// The call seen by the interceptors
+#[derive(Debug)]
+pub struct CallInfo<'a> {
+    /// The service name in the IDL, e.g. `RustFFI`
+    pub service: &'static str,
+    /// The method name in the IDL, e.g. `echo_rs`
+    pub method: &'static str,
+    /// The raw request buffers of the message arguments, in the declared order
+    pub args: &'a [Buffer],
+    /// Free for the interceptors to share data with the later ones, e.g. the request id
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl<'a> CallInfo<'a> {
+    fn new(service: &'static str, method: &'static str, args: &'a [Buffer]) -> Self {
+        Self {
+            service,
+            method,
+            args,
+            metadata: BTreeMap::new(),
+        }
+    }
+}
+
+/// The cross-cutting behavior applied to every RustFFI method, such as auth checks, logging and rate limiting.
+///
+/// The methods returning scalars are not intercepted, since they have no `RustFfiResult`.
+pub trait RustFfiInterceptor: Send + Sync + 'static {
+    /// Called before the trait method, in the order of registration.
+    /// Returning an error rejects the call, and it becomes the result.
+    fn before(&self, _call: &mut CallInfo) -> ABIResult<()> {
+        Ok(())
+    }
+    /// Called after the trait method in the reverse order of registration,
+    /// only for the interceptors whose `before` has been called.
+    fn after(&self, _call: &CallInfo, _result: &mut RustFfiResult) {}
+}
+
+/// The cross-cutting behavior wrapping every GoFFI call made from Rust, such as retries, timeouts and logging.
+///
+/// The methods returning scalars are not intercepted, since they have no `GoFfiResult`.
+pub trait GoFfiInterceptor: Send + Sync + 'static {
+    /// Wrap the call, `next` runs the later interceptors and then calls Go.
+    /// It can be called more than once to retry, the request buffers are kept by the caller.
+    fn intercept(
+        &self,
+        call: &mut CallInfo,
+        next: &dyn Fn(&mut CallInfo) -> GoFfiResult,
+    ) -> GoFfiResult;
+}
+
+/// The interceptors in the order of registration, which is cloned by each call instead of holding the lock.
+struct Chain<I: ?Sized>(RwLock<Option<Arc<[Arc<I>]>>>);
+
+impl<I: ?Sized> Chain<I> {
+    const fn new() -> Self {
+        Self(RwLock::new(None))
+    }
+    fn push(&self, interceptor: Arc<I>) {
+        let mut chain = self.0.write().unwrap();
+        let mut interceptors = chain.as_deref().map(<[_]>::to_vec).unwrap_or_default();
+        interceptors.push(interceptor);
+        *chain = Some(interceptors.into());
+    }
+    fn get(&self) -> Option<Arc<[Arc<I>]>> {
+        self.0.read().unwrap().clone()
+    }
+}
+
+static RUST_FFI_INTERCEPTORS: Chain<dyn RustFfiInterceptor> = Chain::new();
+static GO_FFI_INTERCEPTORS: Chain<dyn GoFfiInterceptor> = Chain::new();
+
+#[cfg(test)]
+pub(crate) fn reset() {
+    *RUST_FFI_INTERCEPTORS.0.write().unwrap() = None;
+    *GO_FFI_INTERCEPTORS.0.write().unwrap() = None;
+}
+
+/// Append the interceptor to the chain run by the generated RustFFI exports.
+pub fn add_rust_ffi_interceptor(interceptor: impl RustFfiInterceptor) {
+    RUST_FFI_INTERCEPTORS.push(Arc::new(interceptor));
+}
+
+/// Append the interceptor to the chain run by the generated GoFFI calls.
+pub fn add_go_ffi_interceptor(interceptor: impl GoFfiInterceptor) {
+    GO_FFI_INTERCEPTORS.push(Arc::new(interceptor));
+}
+
+/// Run the RustFFI method `f` through the interceptor chain, called by the generated exports.
+///
+/// The call is counted as in flight, and rejected once `fcplug_shutdown` starts.
+#[doc(hidden)]
+pub fn intercept_rust_ffi(
+    service: &'static str,
+    method: &'static str,
+    args: &[Buffer],
+    f: impl FnOnce() -> RustFfiResult,
+) -> RustFfiResult {
+    let Some(_in_flight) = InFlight::enter() else {
+        return RustFfiResult::from_err(FfiError::new(
+            RC_UNAVAILABLE,
+            "the library is shutting down",
+        ));
+    };
+    let Some(chain) = RUST_FFI_INTERCEPTORS.get() else {
+        return f();
+    };
+    let mut call = CallInfo::new(service, method, args);
+    let mut entered = 0;
+    let mut result = None;
+    for interceptor in chain.iter() {
+        entered += 1;
+        if let Err(err) = interceptor.before(&mut call) {
+            result = Some(RustFfiResult::from_err(err));
+            break;
+        }
+    }
+    let mut result = result.unwrap_or_else(f);
+    for interceptor in chain[..entered].iter().rev() {
+        interceptor.after(&call, &mut result);
+    }
+    result
+}
+
+/// Run the RustFFI method `f` returning a scalar, called by the generated exports.
+///
+/// The call is counted as in flight like [`intercept_rust_ffi`], but not intercepted, since it has no `RustFfiResult`,
+/// and it aborts once `fcplug_shutdown` starts, since the scalar result cannot carry the error.
+#[doc(hidden)]
+pub fn enter_rust_ffi_scalar<T>(f: impl FnOnce() -> T) -> T {
+    let Some(_in_flight) = InFlight::enter() else {
+        return crate::unavailable_scalar(FfiError::new(
+            RC_UNAVAILABLE,
+            "the library is shutting down",
+        ));
+    };
+    f()
+}
+
+/// Run the GoFFI call `f` through the interceptor chain, called by the generated `GoFfiCall` methods.
+#[doc(hidden)]
+pub fn intercept_go_ffi(
+    service: &'static str,
+    method: &'static str,
+    args: &[Buffer],
+    f: impl Fn() -> GoFfiResult,
+) -> GoFfiResult {
+    let Some(chain) = GO_FFI_INTERCEPTORS.get() else {
+        return f();
+    };
+    fn next(
+        chain: &[Arc<dyn GoFfiInterceptor>],
+        call: &mut CallInfo,
+        f: &dyn Fn() -> GoFfiResult,
+    ) -> GoFfiResult {
+        match chain.split_first() {
+            Some((first, rest)) => first.intercept(call, &|call| next(rest, call, f)),
+            None => f(),
+        }
+    }
+    next(&chain, &mut CallInfo::new(service, method, args), &f)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        add_go_ffi_interceptor, add_rust_ffi_interceptor, intercept_go_ffi, intercept_rust_ffi,
+        ABIResult, Buffer, CallInfo, FfiError, GoFfiInterceptor, GoFfiResult, RustFfiInterceptor,
+        RustFfiResult, RC_NO_ERROR,
+    };
+
+    #[test]
+    fn test_interceptor() {
+        let _global_state = crate::isolate_global_state();
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static AFTER: AtomicUsize = AtomicUsize::new(0);
+        struct SizeLimit;
+        impl RustFfiInterceptor for SizeLimit {
+            fn before(&self, call: &mut CallInfo) -> ABIResult<()> {
+                call.metadata.insert("limit".to_string(), "4".to_string());
+                if call.args.iter().any(|buf| buf.len > 4) {
+                    return Err(FfiError::new(413, "request too large"));
+                }
+                Ok(())
+            }
+            fn after(&self, call: &CallInfo, _result: &mut RustFfiResult) {
+                assert_eq!(call.metadata["limit"], "4");
+                AFTER.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        add_rust_ffi_interceptor(SizeLimit);
+
+        let mut small = b"ping".to_vec();
+        let ret = intercept_rust_ffi(
+            "RustFFI",
+            "echo",
+            &[Buffer::from_vec_mut(&mut small)],
+            || RustFfiResult::from_ok(Buffer::null()),
+        );
+        assert_eq!(ret.code, RC_NO_ERROR);
+
+        let mut large = b"ping ping".to_vec();
+        let ret = intercept_rust_ffi(
+            "RustFFI",
+            "echo",
+            &[Buffer::from_vec_mut(&mut large)],
+            || unreachable!("rejected by the interceptor"),
+        );
+        unsafe { ret.data.mem_free() };
+        assert_eq!(ret.code, 413);
+        assert_eq!(AFTER.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_go_ffi_interceptor() {
+        let _global_state = crate::isolate_global_state();
+        use std::cell::Cell;
+        struct Retry;
+        impl GoFfiInterceptor for Retry {
+            fn intercept(
+                &self,
+                call: &mut CallInfo,
+                next: &dyn Fn(&mut CallInfo) -> GoFfiResult,
+            ) -> GoFfiResult {
+                let mut ret = next(call);
+                if ret.code != RC_NO_ERROR && call.method == "flaky" {
+                    ret = next(call);
+                }
+                ret
+            }
+        }
+        add_go_ffi_interceptor(Retry);
+
+        let calls = Cell::new(0);
+        let ret = intercept_go_ffi("GoFFI", "flaky", &[], || {
+            calls.set(calls.get() + 1);
+            if calls.get() == 1 {
+                GoFfiResult::from_err(FfiError::new(503, "unavailable"))
+            } else {
+                GoFfiResult::from_ok(7_i32)
+            }
+        });
+        assert_eq!(calls.get(), 2);
+        assert_eq!(ABIResult::<i32>::from(ret).unwrap(), 7);
+    }
+}
@@ -3,6 +3,7 @@
 #![feature(new_uninit)]
 #![feature(const_trait_impl)]
 
+use std::any::{Any, TypeId};
 use std::convert::Infallible;
 use std::fmt::{Debug, Display, Formatter};
 use std::ops::FromResidual;
@@ -10,8 +11,25 @@ use std::ops::FromResidual;
 #[cfg(debug_assertions)]
 use tracing::error;
 
+pub use error::{CodedResult, ErrorCode, ErrorKind, FfiError};
+pub use interceptor::{
+    add_go_ffi_interceptor, add_rust_ffi_interceptor, CallInfo, GoFfiInterceptor,
+    RustFfiInterceptor,
+};
+#[doc(hidden)]
+pub use interceptor::{enter_rust_ffi_scalar, intercept_go_ffi, intercept_rust_ffi};
+pub use lifecycle::{set_lifecycle, Lifecycle};
+#[doc(hidden)]
+pub use registry::unavailable_scalar;
+pub use registry::{register_rust_ffi, rust_ffi};
+
 mod basic;
+mod error;
+mod interceptor;
+mod lifecycle;
+pub mod log;
 pub mod protobuf;
+mod registry;
 pub mod serde;
 
 #[inline]
@@ -30,12 +48,18 @@ pub extern "C" fn free_buffer(buf: Buffer) {
 #[no_mangle]
 pub extern "C" fn leak_buffer(buf: Buffer) -> usize {
     if let Some(v) = buf.read() {
-        Box::leak(Box::new(v.to_vec())) as *mut Vec<u8> as usize
+        leak_data(v.to_vec())
     } else {
         0
     }
 }
 
+/// Leak the data as the `data_ptr` of GoFfiResult, which keeps its type to check when consumed.
+#[inline]
+fn leak_data<T: 'static>(data: T) -> usize {
+    Box::into_raw(Box::new(Box::new(data) as Box<dyn Any>)) as usize
+}
+
 pub trait FromMessage<M> {
     fn from_message(value: M) -> Self;
     fn try_from_bytes(buf: &mut [u8]) -> ABIResult<Self>
@@ -60,6 +84,11 @@ pub trait IntoMessage<M> {
 
 pub trait ABIMessage<'a>: TryFromBytes<'a> + TryIntoBytes {}
 
+/// Encode by reference with the codec message `M`
+pub trait TryToBytes<M> {
+    fn try_to_bytes(&self) -> ABIResult<Vec<u8>>;
+}
+
 pub trait TryFromBytes<'b>: Debug {
     fn try_from_bytes(buf: &'b mut [u8]) -> ABIResult<Self>
     where
@@ -178,20 +207,98 @@ impl Display for Buffer {
     }
 }
 
-pub type ResultCode = i8;
+/// Negative codes are reserved for the framework, positive codes are free for the users.
+pub type ResultCode = i32;
 
+/// The flat error, prefer [`FfiError`] which it converts into.
 #[derive(Debug)]
 pub struct ResultMsg {
     pub code: ResultCode,
     pub msg: String,
 }
 
-const RC_NO_ERROR: ResultCode = 0;
-const RC_DECODE: ResultCode = -1;
-const RC_ENCODE: ResultCode = -2;
-const RC_UNKNOWN: ResultCode = -128;
+pub const RC_NO_ERROR: ResultCode = 0;
+pub const RC_DECODE: ResultCode = -1;
+pub const RC_ENCODE: ResultCode = -2;
+/// The data is an encoded error declared in the IDL
+pub const RC_DECLARED_ERROR: ResultCode = -3;
+/// The library is not initialized or is shutting down
+pub const RC_UNAVAILABLE: ResultCode = -4;
+pub const RC_UNKNOWN: ResultCode = -128;
+/// The smallest code of the user errors
+pub const RC_USER_MIN: ResultCode = 1;
+
+pub type ABIResult<T> = Result<T, FfiError>;
+
+/// Marker of the error types declared in the IDL, implemented by the generated code.
+pub trait DeclaredError {}
+
+/// Error of the method which declares its error type in the IDL
+#[derive(Debug)]
+pub enum TypedError<E> {
+    /// The error declared in the IDL
+    Declared(E),
+    /// The framework or untyped error
+    Abi(FfiError),
+}
+
+pub type TypedResult<T, E> = Result<T, TypedError<E>>;
+
+impl<E> From<FfiError> for TypedError<E> {
+    #[inline]
+    fn from(value: FfiError) -> Self {
+        Self::Abi(value)
+    }
+}
+
+impl<E> From<ResultMsg> for TypedError<E> {
+    #[inline]
+    fn from(value: ResultMsg) -> Self {
+        Self::Abi(value.into())
+    }
+}
+
+impl<E: DeclaredError> From<E> for TypedError<E> {
+    #[inline]
+    fn from(value: E) -> Self {
+        Self::Declared(value)
+    }
+}
+
+impl<E: Display> Display for TypedError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Declared(e) => Display::fmt(e, f),
+            Self::Abi(e) => Display::fmt(e, f),
+        }
+    }
+}
+
+impl<E: Debug + Display> std::error::Error for TypedError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Declared(_) => None,
+            Self::Abi(e) => std::error::Error::source(e),
+        }
+    }
+}
 
-pub type ABIResult<T> = Result<T, ResultMsg>;
+impl<E> TypedError<E> {
+    #[inline]
+    fn try_from_declared<M>(err: FfiError) -> Self
+    where
+        E: FromMessage<M>,
+        M: for<'a> TryFromBytes<'a>,
+    {
+        if err.code != RC_DECLARED_ERROR {
+            return Self::Abi(err);
+        }
+        match E::try_from_bytes(&mut err.into_payload()) {
+            Ok(e) => Self::Declared(e),
+            Err(e) => Self::Abi(e),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct TBytes<T> {
@@ -281,47 +388,80 @@ pub struct GoFfiResult {
 
 impl GoFfiResult {
     #[inline]
-    pub fn from_ok<T>(data: T) -> Self {
+    pub fn from_ok<T: 'static>(data: T) -> Self {
         Self {
             code: RC_NO_ERROR,
-            data_ptr: Box::leak(Box::new(data)) as *mut T as usize,
+            data_ptr: leak_data(data),
         }
     }
     #[inline]
-    pub(crate) fn from_err(mut ret_msg: ResultMsg) -> Self {
-        let err = ret_msg.msg.to_string();
+    pub fn from_err(mut err: FfiError) -> Self {
         #[cfg(debug_assertions)]
         {
             error!("{}", err);
         }
-        if ret_msg.code == 0 {
-            ret_msg.code = RC_UNKNOWN
+        if err.code == RC_NO_ERROR {
+            err.code = RC_UNKNOWN
         }
         Self {
-            code: ret_msg.code,
-            data_ptr: Box::leak(Box::new(err)) as *mut String as usize,
+            code: err.code,
+            data_ptr: leak_data(err.to_wire()),
         }
     }
+    /// Take the data, which is `Some(Err(..))` if the data is not a `T`.
+    ///
+    /// # Safety
+    ///
+    /// The `data_ptr` must be null or created by `GoFfiResult` or `leak_buffer`.
     #[inline]
-    pub unsafe fn consume_data<T>(&mut self) -> Option<T> {
-        let data_ptr = self.data_ptr as *mut T;
+    pub unsafe fn consume_data<T: 'static>(&mut self) -> Option<Result<T, FfiError>> {
+        let data_ptr = self.data_ptr as *mut Box<dyn Any>;
         if data_ptr.is_null() {
             None
         } else {
-            self.data_ptr = std::ptr::null_mut::<u8>() as usize;
-            Some(*Box::from_raw(data_ptr))
+            self.data_ptr = 0;
+            Some(
+                Box::from_raw(data_ptr)
+                    .downcast::<T>()
+                    .map(|v| *v)
+                    .map_err(|_| {
+                        FfiError::new(
+                            RC_DECODE,
+                            format!("the GoFFI result is not a `{}`", std::any::type_name::<T>()),
+                        )
+                    }),
+            )
+        }
+    }
+}
+
+/// Free the data not consumed, e.g. the result discarded by a retrying interceptor
+impl Drop for GoFfiResult {
+    fn drop(&mut self) {
+        if self.data_ptr != 0 {
+            unsafe { drop(Box::from_raw(self.data_ptr as *mut Box<dyn Any>)) }
         }
     }
 }
 
+impl FromResidual<Result<Infallible, FfiError>> for GoFfiResult {
+    #[inline]
+    fn from_residual(residual: Result<Infallible, FfiError>) -> Self {
+        Self::from_err(residual.unwrap_err())
+    }
+}
+
 impl FromResidual<Result<Infallible, ResultMsg>> for GoFfiResult {
     #[inline]
     fn from_residual(residual: Result<Infallible, ResultMsg>) -> Self {
-        let ResultMsg { code, msg } = residual.unwrap_err();
-        Self {
-            code,
-            data_ptr: Box::leak(Box::new(msg)) as *mut String as usize,
-        }
+        Self::from_err(residual.unwrap_err().into())
+    }
+}
+
+impl From<FfiError> for GoFfiResult {
+    #[inline]
+    fn from(value: FfiError) -> Self {
+        Self::from_err(value)
     }
 }
 
@@ -338,24 +478,60 @@ impl<T: TryIntoBytes> From<ABIResult<T>> for GoFfiResult {
     }
 }
 
-impl<T: Default> From<GoFfiResult> for ABIResult<T> {
+impl GoFfiResult {
+    /// Convert from the error of the method which declares its error type in the IDL,
+    /// `M` is the codec message of the declared error.
+    #[inline]
+    pub fn from_typed_err<E, M>(err: TypedError<E>) -> Self
+    where
+        E: IntoMessage<M>,
+        M: TryIntoBytes,
+    {
+        match err {
+            TypedError::Declared(e) => match e.try_into_bytes() {
+                Ok(v) => Self {
+                    code: RC_DECLARED_ERROR,
+                    data_ptr: leak_data(v),
+                },
+                Err(e) => Self::from_err(e),
+            },
+            TypedError::Abi(e) => Self::from_err(e),
+        }
+    }
+    /// Convert to the result of the method which declares its error type in the IDL,
+    /// `M` is the codec message of the declared error.
+    #[inline]
+    pub fn into_typed<T: Default + 'static, E, M>(self) -> TypedResult<T, E>
+    where
+        E: FromMessage<M>,
+        M: for<'a> TryFromBytes<'a>,
+    {
+        ABIResult::<T>::from(self).map_err(TypedError::try_from_declared::<M>)
+    }
+    /// Convert to the result of the method whose result type is `R`, which is also taken as `Vec<u8>`
+    /// of `R` encoded by the codec message `M`, as the callers did before the result is type-checked.
+    #[inline]
+    pub fn into_result<T: Default + 'static, R: TryToBytes<M> + Default + 'static, M>(
+        self,
+    ) -> ABIResult<T> {
+        if TypeId::of::<T>() != TypeId::of::<Vec<u8>>() {
+            return ABIResult::from(self);
+        }
+        let bytes: Box<dyn Any> = Box::new(ABIResult::<R>::from(self)?.try_to_bytes()?);
+        Ok(*bytes.downcast::<T>().unwrap_or_else(|_| unreachable!()))
+    }
+}
+
+impl<T: Default + 'static> From<GoFfiResult> for ABIResult<T> {
     #[inline]
     fn from(mut value: GoFfiResult) -> Self {
         match value.code {
-            RC_NO_ERROR => {
-                if let Some(v) = unsafe { value.consume_data::<T>() } {
-                    Ok(v)
-                } else {
-                    Ok(T::default())
-                }
-            }
+            RC_NO_ERROR => unsafe { value.consume_data::<T>() }.unwrap_or_else(|| Ok(T::default())),
             code => {
-                let msg = if let Some(v) = unsafe { value.consume_data::<String>() } {
-                    v
-                } else {
-                    String::default()
-                };
-                Err(ResultMsg { code, msg })
+                let data = unsafe { value.consume_data::<Vec<u8>>() }
+                    .and_then(Result::ok)
+                    .unwrap_or_default();
+                Err(FfiError::from_wire(code, data))
             }
         }
     }
@@ -377,18 +553,40 @@ impl RustFfiResult {
         }
     }
     #[inline]
-    pub(crate) fn from_err(mut ret_msg: ResultMsg) -> Self {
+    pub fn from_err(mut err: FfiError) -> Self {
         #[cfg(debug_assertions)]
         {
-            let err = ret_msg.msg.to_string();
             error!("{}", err);
         }
-        if ret_msg.code == 0 {
-            ret_msg.code = RC_UNKNOWN
+        if err.code == RC_NO_ERROR {
+            err.code = RC_UNKNOWN
         }
         Self {
-            code: ret_msg.code,
-            data: Buffer::from_vec(ret_msg.msg.into_bytes()),
+            code: err.code,
+            data: Buffer::from_vec(err.to_wire()),
+        }
+    }
+}
+
+impl RustFfiResult {
+    /// Convert from the result of the method which declares its error type in the IDL,
+    /// `M` is the codec message of the declared error.
+    #[inline]
+    pub fn from_typed<T: TryIntoBytes, E, M>(value: TypedResult<T, E>) -> Self
+    where
+        E: IntoMessage<M>,
+        M: TryIntoBytes,
+    {
+        match value {
+            Err(TypedError::Declared(e)) => match e.try_into_bytes() {
+                Ok(v) => Self {
+                    code: RC_DECLARED_ERROR,
+                    data: Buffer::from_vec(v),
+                },
+                Err(e) => Self::from_err(e),
+            },
+            Err(TypedError::Abi(e)) => Self::from_err(e),
+            Ok(v) => Self::from(ABIResult::Ok(v)),
         }
     }
 }
@@ -402,6 +600,13 @@ impl FromResidual<Result<Infallible, ResultCode>> for RustFfiResult {
     }
 }
 
+impl FromResidual<Result<Infallible, FfiError>> for RustFfiResult {
+    #[inline]
+    fn from_residual(residual: Result<Infallible, FfiError>) -> Self {
+        Self::from_err(residual.unwrap_err())
+    }
+}
+
 impl<T: TryIntoBytes> From<ABIResult<T>> for RustFfiResult {
     #[inline]
     fn from(value: ABIResult<T>) -> Self {
@@ -426,22 +631,36 @@ impl<'a, T: TryFromBytes<'a>> From<&'a mut RustFfiResult> for ABIResult<T> {
                     {
                         error!("{}", msg);
                     }
-                    ResultMsg {
-                        code: RC_DECODE,
-                        msg,
-                    }
+                    FfiError::new(RC_DECODE, msg)
                 }),
-            code => Err(ResultMsg {
+            code => Err(FfiError::from_wire(
                 code,
-                msg: value.data.to_string(),
-            }),
+                value.data.read().unwrap_or_default().to_vec(),
+            )),
         }
     }
 }
 
+/// Serialize the tests using the global registrations, which are reset for each of them
+#[cfg(test)]
+pub(crate) fn isolate_global_state() -> std::sync::MutexGuard<'static, ()> {
+    static GLOBAL_STATE: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let guard = GLOBAL_STATE
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    interceptor::reset();
+    lifecycle::reset();
+    registry::reset();
+    guard
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::Buffer;
+    use crate::serde::JsonMessage;
+    use crate::{
+        ABIResult, Buffer, DeclaredError, FfiError, GoFfiResult, RustFfiArg, RustFfiResult, TBytes,
+        TypedError, RC_DECLARED_ERROR, RC_DECODE,
+    };
 
     #[test]
     fn it_works() {
@@ -459,4 +678,77 @@ mod tests {
         }
         println!("{:?}", buf.read());
     }
+
+    #[test]
+    fn test_declared_error() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct MyError {
+            reason: i32,
+        }
+        impl DeclaredError for MyError {}
+
+        let ret =
+            RustFfiResult::from_typed::<Vec<u8>, _, JsonMessage<_>>(Err(
+                MyError { reason: 1 }.into()
+            ));
+        assert_eq!(ret.code, RC_DECLARED_ERROR);
+        let err = FfiError::from_wire(ret.code, ret.data.read().unwrap().to_vec());
+        unsafe { ret.data.mem_free() };
+        match TypedError::<MyError>::try_from_declared::<JsonMessage<_>>(err) {
+            TypedError::Declared(e) => assert_eq!(e, MyError { reason: 1 }),
+            TypedError::Abi(e) => panic!("{e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_typed_args() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Ping {
+            msg: String,
+        }
+        // the shape of the generated export in the typed RustFFI mode
+        fn echo(req: Buffer) -> RustFfiResult {
+            let req = RustFfiArg::<Ping>::from(req).try_to_object::<JsonMessage<_>>()?;
+            RustFfiResult::from(ABIResult::Ok(req).and_then(TBytes::try_from::<JsonMessage<_>>))
+        }
+
+        let mut req = br#"{"msg":"hi"}"#.to_vec();
+        let mut ret = echo(Buffer::from_vec_mut(&mut req));
+        let got = ABIResult::<JsonMessage<Ping>>::from(&mut ret).unwrap();
+        unsafe { ret.data.mem_free() };
+        assert_eq!(got.0.msg, "hi");
+
+        let mut req = b"not json".to_vec();
+        let ret = echo(Buffer::from_vec_mut(&mut req));
+        unsafe { ret.data.mem_free() };
+        assert_eq!(ret.code, RC_DECODE);
+    }
+
+    #[test]
+    fn test_go_ffi_result() {
+        #[derive(Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Pong {
+            msg: String,
+        }
+        let new_ret = || {
+            GoFfiResult::from_ok(Pong {
+                msg: "hi".to_string(),
+            })
+        };
+
+        let pong = new_ret()
+            .into_result::<Pong, Pong, JsonMessage<_>>()
+            .unwrap();
+        assert_eq!(pong.msg, "hi");
+        // the bytes encoded by the codec, as the callers by `Vec<u8>` got
+        let bytes = new_ret()
+            .into_result::<Vec<u8>, Pong, JsonMessage<_>>()
+            .unwrap();
+        assert_eq!(bytes, br#"{"msg":"hi"}"#);
+        // the other types are not the result
+        let err = new_ret()
+            .into_result::<String, Pong, JsonMessage<_>>()
+            .unwrap_err();
+        assert_eq!(err.code, RC_DECODE);
+    }
 }
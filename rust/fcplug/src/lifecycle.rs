@@ -0,0 +1,300 @@
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::{ABIResult, Buffer, FfiError, RustFfiResult, RC_UNAVAILABLE};
+
+/// The hooks of the library lifecycle, driven by the host through the
+/// `fcplug_init`, `fcplug_health` and `fcplug_shutdown` exports.
+///
+/// ```ignore
+/// struct App;
+///
+/// impl Lifecycle for App {
+///     fn init(&self, config: &[u8]) -> ABIResult<()> {
+///         let config: AppConfig = serde_json::from_slice(config)?;
+///         fcplug::register_rust_ffi(EchoService::new(&config));
+///         start_runtime(config)
+///     }
+///     fn shutdown(&self) -> ABIResult<()> {
+///         stop_runtime();
+///         Ok(())
+///     }
+/// }
+///
+/// // install the hooks when the library is loaded, e.g. by the `ctor` crate
+/// #[ctor::ctor]
+/// fn install_lifecycle() {
+///     fcplug::set_lifecycle(App);
+/// }
+/// ```
+pub trait Lifecycle: Send + Sync + 'static {
+    /// Called once with the config from the host, where the RustFFI instances are registered.
+    fn init(&self, _config: &[u8]) -> ABIResult<()> {
+        Ok(())
+    }
+    /// Called after the library is initialized, the error reports it unhealthy.
+    fn health(&self) -> ABIResult<()> {
+        Ok(())
+    }
+    /// Called after the in-flight calls are drained,
+    /// to stop the background runtimes and flush the logs and metrics.
+    ///
+    /// If it fails, the library keeps rejecting the calls, and it is called again by the next `fcplug_shutdown`.
+    fn shutdown(&self) -> ABIResult<()> {
+        Ok(())
+    }
+}
+
+struct NoopLifecycle;
+
+impl Lifecycle for NoopLifecycle {}
+
+static LIFECYCLE: RwLock<Option<&'static dyn Lifecycle>> = RwLock::new(None);
+
+const UNINIT: u8 = 0;
+const READY: u8 = 1;
+const STOPPING: u8 = 2;
+const STOPPED: u8 = 3;
+
+static STATE: AtomicU8 = AtomicU8::new(UNINIT);
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+/// Serializes the state transitions, so that the hooks are not called concurrently
+static TRANSITION: Mutex<()> = Mutex::new(());
+/// Notified when the last in-flight call leaves during the shutdown
+static DRAINED: Condvar = Condvar::new();
+static DRAIN: Mutex<()> = Mutex::new(());
+
+/// Install the lifecycle hooks, it must be called before `fcplug_init` from the host.
+///
+/// Returns false if the hooks have already been installed.
+pub fn set_lifecycle(lifecycle: impl Lifecycle) -> bool {
+    let mut installed = LIFECYCLE.write().unwrap();
+    if installed.is_some() {
+        return false;
+    }
+    *installed = Some(Box::leak(Box::new(lifecycle)));
+    true
+}
+
+fn lifecycle() -> &'static dyn Lifecycle {
+    *LIFECYCLE.write().unwrap().get_or_insert(&NoopLifecycle)
+}
+
+#[cfg(test)]
+pub(crate) fn reset() {
+    *LIFECYCLE.write().unwrap() = None;
+    STATE.store(UNINIT, Ordering::SeqCst);
+    IN_FLIGHT.store(0, Ordering::SeqCst);
+}
+
+/// The RustFFI call counted as in flight until dropped
+pub(crate) struct InFlight(());
+
+impl InFlight {
+    /// Returns None if the library is shutting down,
+    /// the calls before `fcplug_init` are served for compatibility.
+    pub(crate) fn enter() -> Option<Self> {
+        IN_FLIGHT.fetch_add(1, Ordering::SeqCst);
+        if STATE.load(Ordering::SeqCst) >= STOPPING {
+            Self::leave();
+            return None;
+        }
+        Some(Self(()))
+    }
+
+    fn leave() {
+        if IN_FLIGHT.fetch_sub(1, Ordering::SeqCst) == 1 && STATE.load(Ordering::SeqCst) >= STOPPING
+        {
+            // locked, so that the notification is not lost between the check and the wait of `shutdown`
+            let _drain = DRAIN.lock().unwrap_or_else(|e| e.into_inner());
+            DRAINED.notify_all();
+        }
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        Self::leave();
+    }
+}
+
+fn to_ffi_result(ret: ABIResult<()>) -> RustFfiResult {
+    match ret {
+        Ok(()) => RustFfiResult::from_ok(Buffer::null()),
+        Err(e) => RustFfiResult::from_err(e),
+    }
+}
+
+fn init(config: &[u8]) -> ABIResult<()> {
+    let _transition = TRANSITION.lock().unwrap();
+    match STATE.load(Ordering::SeqCst) {
+        UNINIT => {
+            lifecycle().init(config)?;
+            STATE.store(READY, Ordering::SeqCst);
+            Ok(())
+        }
+        READY => Err(FfiError::new(
+            RC_UNAVAILABLE,
+            "the library is already initialized",
+        )),
+        _ => Err(FfiError::new(
+            RC_UNAVAILABLE,
+            "the library is shutting down",
+        )),
+    }
+}
+
+fn health() -> ABIResult<()> {
+    match STATE.load(Ordering::SeqCst) {
+        UNINIT => Err(FfiError::new(
+            RC_UNAVAILABLE,
+            "the library is not initialized",
+        )),
+        READY => lifecycle().health(),
+        _ => Err(FfiError::new(
+            RC_UNAVAILABLE,
+            "the library is shutting down",
+        )),
+    }
+}
+
+fn shutdown(timeout: Duration) -> ABIResult<()> {
+    let _transition = TRANSITION.lock().unwrap();
+    if STATE.load(Ordering::SeqCst) == STOPPED {
+        return Ok(());
+    }
+    // it stays STOPPING if the drain or the hook fails, so that the calls keep being rejected until the retry
+    STATE.store(STOPPING, Ordering::SeqCst);
+    let deadline = Instant::now() + timeout;
+    let mut drain = DRAIN.lock().unwrap_or_else(|e| e.into_inner());
+    loop {
+        let in_flight = IN_FLIGHT.load(Ordering::SeqCst);
+        if in_flight == 0 {
+            break;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(FfiError::new(
+                RC_UNAVAILABLE,
+                format!("{in_flight} RustFFI calls are still in flight after {timeout:?}"),
+            ));
+        }
+        drain = DRAINED
+            .wait_timeout(drain, deadline - now)
+            .unwrap_or_else(|e| e.into_inner())
+            .0;
+    }
+    drop(drain);
+    lifecycle().shutdown()?;
+    crate::log::shutdown();
+    STATE.store(STOPPED, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Initialize the library with the config, which is passed to [`Lifecycle::init`].
+#[no_mangle]
+pub extern "C" fn fcplug_init(config: Buffer) -> RustFfiResult {
+    to_ffi_result(init(config.read().unwrap_or_default()))
+}
+
+/// Report whether the library is initialized and healthy.
+#[no_mangle]
+pub extern "C" fn fcplug_health() -> RustFfiResult {
+    to_ffi_result(health())
+}
+
+/// Reject the new RustFFI calls, drain the in-flight ones, call [`Lifecycle::shutdown`], and then flush the logs.
+///
+/// If the in-flight calls are not drained within the timeout or the hook fails, the library keeps rejecting
+/// the calls, and it can be called again to retry the drain and the hook.
+#[no_mangle]
+pub extern "C" fn fcplug_shutdown(timeout_ms: u64) -> RustFfiResult {
+    to_ffi_result(shutdown(Duration::from_millis(timeout_ms)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    use super::{fcplug_health, fcplug_init, fcplug_shutdown, InFlight};
+    use crate::{
+        register_rust_ffi, rust_ffi, set_lifecycle, ABIResult, Buffer, FfiError, Lifecycle,
+        RC_NO_ERROR, RC_UNAVAILABLE, RC_UNKNOWN,
+    };
+
+    #[test]
+    fn test_lifecycle() {
+        let _global_state = crate::isolate_global_state();
+        struct App;
+        struct Service;
+        impl Lifecycle for App {
+            fn init(&self, config: &[u8]) -> ABIResult<()> {
+                assert_eq!(config, b"{}");
+                register_rust_ffi(Service);
+                Ok(())
+            }
+        }
+        assert!(set_lifecycle(App));
+        assert_eq!(rust_ffi::<Service>().err().unwrap().code, RC_UNAVAILABLE);
+
+        let mut ret = fcplug_health();
+        let err = ABIResult::<Vec<u8>>::from(&mut ret).unwrap_err();
+        assert_eq!(err.code, RC_UNAVAILABLE);
+        unsafe { ret.data.mem_free() };
+
+        let mut config = b"{}".to_vec();
+        assert_eq!(
+            fcplug_init(Buffer::from_vec_mut(&mut config)).code,
+            RC_NO_ERROR
+        );
+        assert_eq!(fcplug_health().code, RC_NO_ERROR);
+        assert!(rust_ffi::<Service>().is_ok());
+
+        let ret = fcplug_init(Buffer::from_vec_mut(&mut config));
+        assert_eq!(ret.code, RC_UNAVAILABLE);
+        unsafe { ret.data.mem_free() };
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let _global_state = crate::isolate_global_state();
+        static FAIL_ONCE: AtomicBool = AtomicBool::new(true);
+        struct App;
+        impl Lifecycle for App {
+            fn shutdown(&self) -> ABIResult<()> {
+                if FAIL_ONCE.swap(false, Ordering::SeqCst) {
+                    return Err(FfiError::new(RC_UNKNOWN, "the runtime is busy"));
+                }
+                Ok(())
+            }
+        }
+        assert!(set_lifecycle(App));
+        assert_eq!(crate::enter_rust_ffi_scalar(|| 1), 1);
+
+        // the drain times out, and the new calls are rejected until the retry
+        let in_flight = InFlight::enter().unwrap();
+        let ret = fcplug_shutdown(10);
+        assert_eq!(ret.code, RC_UNAVAILABLE);
+        unsafe { ret.data.mem_free() };
+        assert!(InFlight::enter().is_none());
+
+        // the retry is woken up once the last call leaves, and then the hook fails
+        let leave = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            drop(in_flight);
+        });
+        let ret = fcplug_shutdown(60_000);
+        leave.join().unwrap();
+        assert_eq!(ret.code, RC_UNKNOWN);
+        unsafe { ret.data.mem_free() };
+        assert!(InFlight::enter().is_none());
+
+        // the hook is called again by the retry
+        assert_eq!(fcplug_shutdown(0).code, RC_NO_ERROR);
+        assert_eq!(fcplug_shutdown(0).code, RC_NO_ERROR);
+        assert!(InFlight::enter().is_none());
+        assert!(std::panic::catch_unwind(|| crate::enter_rust_ffi_scalar(|| 1)).is_err());
+    }
+}
@@ -1,73 +1,381 @@
-#![allow(dead_code)]
+use std::fmt::Debug;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
 
-use std::{env, io, str::FromStr, sync::Once};
-
-use defer_lite::defer;
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+use tracing::field::{Field, Visit};
 pub use tracing::*;
 use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::time::{FormatTime, SystemTime};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::layer::{Layered, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Layer, Registry};
 
-#[cfg(debug_assertions)]
-const DEBUG_ASSERTIONS: bool = true;
+use crate::{ABIResult, Buffer, FfiError, RustFfiResult, RC_DECODE, RC_UNAVAILABLE};
 
-#[cfg(not(debug_assertions))]
-const DEBUG_ASSERTIONS: bool = false;
+/// The logging config of the Rust library, which is JSON when it comes from Go.
+///
+/// ```ignore
+/// fcplug::log::init(&LogConfig {
+///     filter: "info,my_crate::db=debug".to_string(),
+///     output: LogOutput::File,
+///     format: LogFormat::Json,
+///     ..Default::default()
+/// })?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    /// The level filters per target in the `RUST_LOG` syntax, e.g. `warn,my_crate=debug`.
+    /// It defaults to `RUST_LOG`, or `info` if unset.
+    pub filter: String,
+    pub output: LogOutput,
+    pub format: LogFormat,
+    /// The directory of the log files
+    pub dir: PathBuf,
+    /// The prefix of the log file names, followed by the date of the rotation
+    pub file_prefix: String,
+    pub rotation: LogRotation,
+    /// The number of the rotated files to keep, all are kept if unset
+    pub max_files: Option<usize>,
+}
 
-pub type FileLogGuard = WorkerGuard;
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            filter: std::env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string()),
+            output: LogOutput::Stdout,
+            format: LogFormat::Text,
+            dir: PathBuf::from("./.out/log/"),
+            file_prefix: "fcplug.log".to_string(),
+            rotation: LogRotation::Daily,
+            max_files: None,
+        }
+    }
+}
 
-static ONCE: Once = Once::new();
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogOutput {
+    Stdout,
+    /// The rolling files in `LogConfig::dir`, written by a background thread
+    File,
+    /// The JSON records passed to the callback registered by `fcplug_log_set_callback`,
+    /// e.g. the Go `slog` bridge, the format is always JSON.
+    Callback,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    Text,
+    /// One JSON object per line, with the `timestamp`, `level`, `target`, `spans` and `fields` keys,
+    /// each span has the `name` and the formatted `fields`
+    Json,
+}
 
-pub fn init_log(filelog: Option<bool>) -> Option<FileLogGuard> {
-    if ONCE.is_completed() {
-        return None;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    Daily,
+    Never,
+}
+
+impl From<LogRotation> for Rotation {
+    fn from(value: LogRotation) -> Self {
+        match value {
+            LogRotation::Minutely => Rotation::MINUTELY,
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        }
     }
-    ONCE.call_once(|| {});
-    defer! {info!("logger initialized")}
+}
 
-    // Set the format of the log output,
-    // for example, whether to include the log level,
-    // whether to include the location of the log source,
-    // and set the time format of the log.
-    // via: https://docs.rs/tracing-subscriber/0.3.3/tracing_subscriber/fmt/struct.SubscriberBuilder.html#method.with_timer
-    let format = tracing_subscriber::fmt::format().with_level(true).with_target(true);
+type FilterLayer = reload::Layer<EnvFilter, Registry>;
+type OutputLayer = Box<dyn Layer<Layered<FilterLayer, Registry>> + Send + Sync>;
 
-    // max log level
-    let max_level = env::var("RUST_LOG")
-        .map_or_else(|_| Level::INFO, |s| Level::from_str(&s).unwrap_or(Level::INFO));
+struct Logger {
+    filter: reload::Handle<EnvFilter, Registry>,
+    output: reload::Handle<OutputLayer, Layered<FilterLayer, Registry>>,
+    /// Flushes the file logs when dropped
+    guard: Option<WorkerGuard>,
+}
 
-    // Initialize and set the log format (customize and filter logs)
-    let subscriber_builder =
-        tracing_subscriber::fmt().with_max_level(max_level).event_format(format);
+static LOGGER: Mutex<Option<Logger>> = Mutex::new(None);
 
-    if !filelog.unwrap_or(!DEBUG_ASSERTIONS) {
-        subscriber_builder
-            .with_writer(io::stdout)
-            .init();
-        return None;
+/// Install the global logger, or reconfigure it if already installed by this function.
+///
+/// It fails if another global logger has been installed.
+pub fn init(config: &LogConfig) -> ABIResult<()> {
+    let filter = EnvFilter::try_new(&config.filter)
+        .map_err(|e| FfiError::new(RC_DECODE, format!("invalid log filter: {e}")))?;
+    let (output, guard) = output_layer(config)?;
+    let mut logger = LOGGER.lock().unwrap();
+    match logger.as_mut() {
+        Some(logger) => {
+            logger.filter.reload(filter).map_err(anyhow::Error::from)?;
+            logger.output.reload(output).map_err(anyhow::Error::from)?;
+            logger.guard = guard;
+        }
+        None => {
+            let (filter, filter_handle) = reload::Layer::new(filter);
+            let (output, output_handle) = reload::Layer::new(output);
+            tracing_subscriber::registry()
+                .with(filter)
+                .with(output)
+                .try_init()
+                .map_err(anyhow::Error::from)?;
+            *logger = Some(Logger {
+                filter: filter_handle,
+                output: output_handle,
+                guard,
+            });
+        }
     }
+    Ok(())
+}
 
-    // Use tracing_appender to specify the output destination of the log
-    // via: https://docs.rs/tracing-appender/0.2.0/tracing_appender/
-    let file_appender = tracing_appender::rolling::daily("./.out/log/", "anfs.log");
-    let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
-    subscriber_builder
-        .with_writer(non_blocking)// write to file, will overwrite stdout above
-        .with_ansi(false)// If the log is written to a file, the ansi color output function should be turned off
-        .init();
-    Some(guard)
+/// Flush the file logs, the later logs are written to the file no more.
+pub fn shutdown() {
+    if let Some(logger) = LOGGER.lock().unwrap().as_mut() {
+        logger.guard.take();
+    }
+}
+
+fn output_layer(config: &LogConfig) -> ABIResult<(OutputLayer, Option<WorkerGuard>)> {
+    let (writer, guard) = match config.output {
+        LogOutput::Stdout => (BoxMakeWriter::new(io::stdout), None),
+        LogOutput::File => {
+            let mut builder = RollingFileAppender::builder()
+                .rotation(config.rotation.into())
+                .filename_prefix(&config.file_prefix);
+            if let Some(max_files) = config.max_files {
+                builder = builder.max_log_files(max_files);
+            }
+            let (writer, guard) = tracing_appender::non_blocking(
+                builder.build(&config.dir).map_err(anyhow::Error::from)?,
+            );
+            (BoxMakeWriter::new(writer), Some(guard))
+        }
+        LogOutput::Callback => {
+            let callback = *LOG_CALLBACK.read().unwrap();
+            let Some(callback) = callback else {
+                return Err(FfiError::new(
+                    RC_UNAVAILABLE,
+                    "no log callback is registered by fcplug_log_set_callback",
+                ));
+            };
+            let layer = tracing_subscriber::fmt::layer()
+                .event_format(JsonFormat)
+                .with_ansi(false)
+                .with_writer(move || CallbackWriter(callback))
+                .boxed();
+            return Ok((layer, None));
+        }
+    };
+    let layer = match config.format {
+        LogFormat::Text => tracing_subscriber::fmt::layer()
+            .with_target(true)
+            // the ansi color is for the terminal only
+            .with_ansi(config.output == LogOutput::Stdout)
+            .with_writer(writer)
+            .boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .event_format(JsonFormat)
+            // the span fields are formatted with the ansi color otherwise
+            .with_ansi(false)
+            .with_writer(writer)
+            .boxed(),
+    };
+    Ok((layer, guard))
+}
+
+/// Install or reconfigure the logger with the JSON [`LogConfig`], called by the Go host.
+#[no_mangle]
+pub extern "C" fn fcplug_log_init(config: Buffer) -> RustFfiResult {
+    let ret = serde_json::from_slice::<LogConfig>(config.read().unwrap_or(b"{}"))
+        .map_err(|e| FfiError::new(RC_DECODE, format!("invalid log config: {e}")))
+        .and_then(|config| init(&config));
+    match ret {
+        Ok(()) => RustFfiResult::from_ok(Buffer::null()),
+        Err(e) => RustFfiResult::from_err(e),
+    }
+}
+
+static LOG_CALLBACK: RwLock<Option<extern "C" fn(Buffer)>> = RwLock::new(None);
+
+/// Register the callback of `LogOutput::Callback`, which receives one JSON record per call,
+/// and the buffer is valid during the call only.
+#[no_mangle]
+pub extern "C" fn fcplug_log_set_callback(callback: extern "C" fn(Buffer)) {
+    *LOG_CALLBACK.write().unwrap() = Some(callback);
+}
+
+/// Writes each formatted event to the callback, since the fmt layer writes an event at once.
+struct CallbackWriter(extern "C" fn(Buffer));
+
+impl io::Write for CallbackWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut record = buf.strip_suffix(b"\n").unwrap_or(buf).to_vec();
+        (self.0)(Buffer::from_vec_mut(&mut record));
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The log record from the other side, e.g. the Go `slog` bridge
+#[derive(Deserialize)]
+struct ForeignRecord {
+    /// The `slog` level, which is -4 for debug, 0 for info, 4 for warn and 8 for error
+    level: i64,
+    message: String,
+    #[serde(default)]
+    fields: Map<String, serde_json::Value>,
+}
+
+/// Emit the JSON record from the other side as a tracing event with the target `go`,
+/// whose fields are kept as JSON in the `fields` field.
+#[no_mangle]
+pub extern "C" fn fcplug_log_write(record: Buffer) {
+    let Ok(record) = serde_json::from_slice::<ForeignRecord>(record.read().unwrap_or_default())
+    else {
+        return;
+    };
+    let fields = serde_json::Value::Object(record.fields);
+    macro_rules! go_event {
+        ($level:expr) => {
+            event!(target: "go", $level, fields = %fields, "{}", record.message)
+        };
+    }
+    match record.level {
+        level if level < -4 => go_event!(Level::TRACE),
+        level if level < 0 => go_event!(Level::DEBUG),
+        level if level < 4 => go_event!(Level::INFO),
+        level if level < 8 => go_event!(Level::WARN),
+        _ => go_event!(Level::ERROR),
+    }
+}
+
+/// The JSON lines format, which is written here since the `json` feature of `tracing-subscriber` is not used.
+struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> std::fmt::Result {
+        let mut timestamp = String::new();
+        SystemTime.format_time(&mut Writer::new(&mut timestamp))?;
+        let mut fields = JsonFields(Map::new());
+        event.record(&mut fields);
+        let spans: Vec<serde_json::Value> = ctx
+            .event_scope()
+            .map(|scope| {
+                scope
+                    .from_root()
+                    .map(|span| {
+                        let extensions = span.extensions();
+                        let fields = extensions
+                            .get::<FormattedFields<N>>()
+                            .map(|fields| fields.fields.as_str())
+                            .unwrap_or_default();
+                        serde_json::json!({"name": span.name(), "fields": fields})
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let metadata = event.metadata();
+        let line = serde_json::json!({
+            "timestamp": timestamp,
+            "level": metadata.level().as_str(),
+            "target": metadata.target(),
+            "spans": spans,
+            "fields": fields.0,
+        });
+        writeln!(writer, "{line}")
+    }
+}
+
+struct JsonFields(Map<String, serde_json::Value>);
+
+impl Visit for JsonFields {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{value:?}").into());
+    }
 }
 
 #[test]
 fn test() {
-    use tracing::*;
-
-    #[allow(unused_variables)]
-        //let x = init_log(None);
-        #[allow(unused_variables)]
-        let x = init_log(None);
+    let config = serde_json::from_str::<LogConfig>(r#"{"filter": "debug,fcplug=trace"}"#).unwrap();
+    assert_eq!(config.output, LogOutput::Stdout);
+    init(&config).unwrap();
+    init(&LogConfig {
+        format: LogFormat::Json,
+        ..config.clone()
+    })
+    .unwrap();
 
     let warn_description = "Invalid Input";
     let input = &[0x27, 0x45];
 
     warn!(?input, warning = warn_description);
-    warn!(target: "evmmmmmm",warning2 = warn_description, "Received warning for input: {:?}", input,);
+    warn!(target: "evmmmmmm", warning2 = warn_description, "Received warning for input: {:?}", input);
+
+    static RECORDS: Mutex<Vec<serde_json::Value>> = Mutex::new(Vec::new());
+    extern "C" fn callback(record: Buffer) {
+        let record = serde_json::from_slice(record.read().unwrap()).unwrap();
+        RECORDS.lock().unwrap().push(record);
+    }
+    fcplug_log_set_callback(callback);
+    init(&LogConfig {
+        output: LogOutput::Callback,
+        ..config
+    })
+    .unwrap();
+    info_span!("call", method = "echo").in_scope(|| info!(id = 1, "ok"));
+    let mut go_record = br#"{"level": 4, "message": "slow", "fields": {"ms": 120}}"#.to_vec();
+    fcplug_log_write(Buffer::from_vec_mut(&mut go_record));
+
+    let records = RECORDS.lock().unwrap();
+    assert_eq!(records[0]["fields"]["message"], "ok");
+    assert_eq!(records[0]["fields"]["id"], 1);
+    assert_eq!(records[0]["spans"][0]["name"], "call");
+    assert_eq!(records[0]["spans"][0]["fields"], "method=\"echo\"");
+    assert_eq!(records[1]["target"], "go");
+    assert_eq!(records[1]["level"], "WARN");
+    assert_eq!(records[1]["fields"]["fields"], r#"{"ms":120}"#);
 }
@@ -3,8 +3,8 @@ use std::fmt::Debug;
 pub use pilota::prost::Message;
 
 use crate::{
-    ABIResult, FromMessage, IntoMessage, ResultMsg, TryFromBytes, TryIntoBytes, RC_DECODE,
-    RC_ENCODE,
+    ABIResult, FfiError, FromMessage, IntoMessage, TryFromBytes, TryIntoBytes, TryToBytes,
+    RC_DECODE, RC_ENCODE,
 };
 
 #[derive(Debug)]
@@ -24,6 +24,13 @@ impl<T: Message + Default> IntoMessage<PbMessage<T>> for T {
     }
 }
 
+impl<T: Message + Default> TryToBytes<PbMessage<T>> for T {
+    #[inline]
+    fn try_to_bytes(&self) -> ABIResult<Vec<u8>> {
+        Ok(self.encode_to_vec())
+    }
+}
+
 impl<T: Message + Default> TryFromBytes<'_> for PbMessage<T> {
     #[inline]
     fn try_from_bytes(buf: &mut [u8]) -> ABIResult<Self>
@@ -44,18 +51,12 @@ impl<T: Message> TryIntoBytes for PbMessage<T> {
 }
 
 #[inline]
-fn decode_map_err(e: pilota::prost::DecodeError) -> ResultMsg {
-    ResultMsg {
-        code: RC_DECODE,
-        msg: e.to_string(),
-    }
+fn decode_map_err(e: pilota::prost::DecodeError) -> FfiError {
+    FfiError::new(RC_DECODE, e.to_string())
 }
 
 #[inline]
 #[allow(dead_code)]
-fn encode_map_err(e: pilota::prost::EncodeError) -> ResultMsg {
-    ResultMsg {
-        code: RC_ENCODE,
-        msg: e.to_string(),
-    }
+fn encode_map_err(e: pilota::prost::EncodeError) -> FfiError {
+    FfiError::new(RC_ENCODE, e.to_string())
 }
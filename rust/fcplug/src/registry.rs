@@ -0,0 +1,153 @@
+use std::any::{type_name, Any, TypeId};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+use crate::{ABIResult, FfiError, RC_UNAVAILABLE};
+
+static RUST_FFI_INSTANCES: RwLock<BTreeMap<TypeId, &'static (dyn Any + Send + Sync)>> =
+    RwLock::new(BTreeMap::new());
+
+/// Install the instance which the generated RustFFI exports dispatch to,
+/// e.g. in [`Lifecycle::init`](crate::Lifecycle::init), the calls before it fail with `RC_UNAVAILABLE`.
+///
+/// Returns false if an instance of the type has already been registered.
+pub fn register_rust_ffi<T: Send + Sync + 'static>(instance: T) -> bool {
+    let mut instances = RUST_FFI_INSTANCES.write().unwrap();
+    if instances.contains_key(&TypeId::of::<T>()) {
+        return false;
+    }
+    instances.insert(TypeId::of::<T>(), Box::leak(Box::new(instance)));
+    true
+}
+
+#[cfg(test)]
+pub(crate) fn reset() {
+    RUST_FFI_INSTANCES.write().unwrap().clear();
+}
+
+/// Get the registered RustFFI instance.
+///
+/// Fails with `RC_UNAVAILABLE` if no instance of the type has been registered,
+/// which the generated exports return to the host.
+pub fn rust_ffi<T: Send + Sync + 'static>() -> ABIResult<&'static T> {
+    RUST_FFI_INSTANCES
+        .read()
+        .unwrap()
+        .get(&TypeId::of::<T>())
+        .copied()
+        .and_then(|v| v.downcast_ref::<T>())
+        .ok_or_else(|| {
+            FfiError::new(
+                RC_UNAVAILABLE,
+                format!(
+                    "RustFFI instance of `{}` is not registered, call `fcplug::register_rust_ffi` first",
+                    type_name::<T>()
+                ),
+            )
+        })
+}
+
+/// Fail the call returning a scalar, e.g. to an unregistered RustFFI instance or Go host function,
+/// which has no result to carry the error, so that it is not mistaken for a valid zero value.
+///
+/// It panics, which aborts the process in the generated `extern "C"` functions.
+#[doc(hidden)]
+#[track_caller]
+pub fn unavailable_scalar<T>(err: FfiError) -> T {
+    tracing::error!(code = err.code, "{:#}", err);
+    panic!("{err:#}, the call returning a scalar cannot report the error")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        register_rust_ffi, rust_ffi, unavailable_scalar, ABIResult, Buffer, FfiError, GoFfiResult,
+        RustFfiResult, RC_NO_ERROR, RC_UNAVAILABLE,
+    };
+
+    #[test]
+    fn test_rust_ffi_registry() {
+        let _global_state = crate::isolate_global_state();
+        struct Service(i32);
+        // the generated exports return the error before the registration
+        fn export() -> RustFfiResult {
+            let service = match rust_ffi::<Service>() {
+                Ok(service) => service,
+                Err(err) => return RustFfiResult::from_err(err),
+            };
+            RustFfiResult::from_ok(Buffer::from_vec(service.0.to_le_bytes().to_vec()))
+        }
+        fn scalar_export() -> i32 {
+            match rust_ffi::<Service>() {
+                Ok(service) => service.0,
+                Err(err) => unavailable_scalar(err),
+            }
+        }
+
+        let mut ret = export();
+        let err = ABIResult::<Vec<u8>>::from(&mut ret).unwrap_err();
+        assert_eq!(err.code, RC_UNAVAILABLE);
+        assert!(err.msg.contains("Service` is not registered"));
+        unsafe { ret.data.mem_free() };
+        // the scalar result cannot carry the error, so the call fails instead of returning zero
+        assert!(std::panic::catch_unwind(scalar_export).is_err());
+
+        assert!(register_rust_ffi(Service(7)));
+        assert!(!register_rust_ffi(Service(8)));
+        let mut ret = export();
+        assert_eq!(ret.code, RC_NO_ERROR);
+        assert_eq!(
+            ABIResult::<Vec<u8>>::from(&mut ret).unwrap(),
+            7i32.to_le_bytes()
+        );
+        assert_eq!(scalar_export(), 7);
+    }
+
+    #[test]
+    fn test_unregistered_go_host_fn() {
+        use std::sync::OnceLock;
+        static GOFFI_ECHO_GO: OnceLock<extern "C" fn(Buffer) -> GoFfiResult> = OnceLock::new();
+        static GOFFI_ADD: OnceLock<extern "C" fn(i32) -> i32> = OnceLock::new();
+        // the generated stubs calling Go by the function pointers registered by the Go host
+        fn goffi_echo_go(req: Buffer) -> GoFfiResult {
+            match GOFFI_ECHO_GO.get() {
+                Some(f) => f(req),
+                None => GoFfiResult::from_err(FfiError::new(
+                    RC_UNAVAILABLE,
+                    "GoFFI `goffi_echo_go` is not registered by the Go host",
+                )),
+            }
+        }
+        fn goffi_add(a: i32) -> i32 {
+            match GOFFI_ADD.get() {
+                Some(f) => f(a),
+                None => unavailable_scalar(FfiError::new(
+                    RC_UNAVAILABLE,
+                    "GoFFI `goffi_add` is not registered by the Go host",
+                )),
+            }
+        }
+        extern "C" fn echo_go(_req: Buffer) -> GoFfiResult {
+            GoFfiResult::from_ok(vec![1u8])
+        }
+        extern "C" fn add(a: i32) -> i32 {
+            a + 1
+        }
+
+        let err = ABIResult::<Vec<u8>>::from(goffi_echo_go(Buffer::null())).unwrap_err();
+        assert_eq!(err.code, RC_UNAVAILABLE);
+        assert_eq!(
+            err.msg,
+            "GoFFI `goffi_echo_go` is not registered by the Go host"
+        );
+        assert!(std::panic::catch_unwind(|| goffi_add(1)).is_err());
+
+        GOFFI_ECHO_GO.set(echo_go).unwrap();
+        GOFFI_ADD.set(add).unwrap();
+        assert_eq!(
+            ABIResult::<Vec<u8>>::from(goffi_echo_go(Buffer::null())).unwrap(),
+            [1]
+        );
+        assert_eq!(goffi_add(1), 2);
+    }
+}
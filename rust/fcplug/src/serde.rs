@@ -3,7 +3,8 @@ use std::fmt::Debug;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    ABIResult, FromMessage, IntoMessage, ResultMsg, TryFromBytes, TryIntoBytes, RC_DECODE,
+    ABIResult, FfiError, FromMessage, IntoMessage, TryFromBytes, TryIntoBytes, TryToBytes,
+    RC_DECODE, RC_ENCODE,
 };
 
 #[derive(Debug)]
@@ -29,6 +30,16 @@ where
     }
 }
 
+impl<T> TryToBytes<JsonMessage<T>> for T
+where
+    T: for<'a> Deserialize<'a> + Serialize + Debug,
+{
+    #[inline]
+    fn try_to_bytes(&self) -> ABIResult<Vec<u8>> {
+        serde_json::to_vec(self).map_err(encode_map_err)
+    }
+}
+
 impl<T> TryFromBytes<'_> for JsonMessage<T>
 where
     T: for<'a> Deserialize<'a> + Serialize + Debug,
@@ -55,17 +66,11 @@ where
 }
 
 #[inline]
-fn decode_map_err(e: serde_json::Error) -> ResultMsg {
-    ResultMsg {
-        code: RC_DECODE,
-        msg: e.to_string(),
-    }
+fn decode_map_err(e: serde_json::Error) -> FfiError {
+    FfiError::new(RC_DECODE, e.to_string())
 }
 
 #[inline]
-fn encode_map_err(e: serde_json::Error) -> ResultMsg {
-    ResultMsg {
-        code: RC_DECODE,
-        msg: e.to_string(),
-    }
+fn encode_map_err(e: serde_json::Error) -> FfiError {
+    FfiError::new(RC_ENCODE, e.to_string())
 }
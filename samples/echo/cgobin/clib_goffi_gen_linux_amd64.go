@@ -0,0 +1,41 @@
+// Code generated by fcplug. DO NOT EDIT.
+
+        package main
+
+        /*
+        #cgo CFLAGS: -I../target/debug
+        #cgo LDFLAGS: -ldl -lm
+
+        #include "echo.h"
+        */
+        import "C"
+        import (
+            "reflect"
+            "unsafe"
+
+            
+        )
+
+        // main function is never called by C to.
+        func main() {}
+
+        var (
+            _ reflect.SliceHeader
+            _ unsafe.Pointer
+        )
+        
+
+        
+
+                        var GlobalGoFfi GoFfi = _UnimplementedGoFfi{}
+
+                        type GoFfi interface {
+                            
+                        }
+                        type _UnimplementedGoFfi struct{}
+                        
+
+                        
+
+        
+        
\ No newline at end of file
@@ -0,0 +1,65 @@
+// Code generated by fcplug. DO NOT EDIT.
+
+            //go:build fcplug_fake
+
+            package echo
+
+            import (
+                "context"
+                "errors"
+                "fmt"
+                "reflect"
+                "runtime"
+                "sync"
+                "time"
+                "unsafe"
+
+                
+            )
+
+            var (
+                _ context.Context
+                _ = errors.New
+                _ = fmt.Sprintf
+                _ reflect.SliceHeader
+                _ = runtime.SetFinalizer
+                _ sync.Mutex
+                _ time.Duration
+                _ unsafe.Pointer
+            )
+            
+
+            
+                        var GlobalRustFfi RustFfi = &RustFfiFake{}
+
+                        type RustFfi interface {
+                            
+                        }
+
+                        // RustFfiFake the fake of RustFfi for the Go tests without the Rust library,
+                        // each method calls its stub func and records the call.
+                        type RustFfiFake struct {
+                            mu    sync.Mutex
+                            calls []FakeCall
+                            
+                        }
+
+                        func (f *RustFfiFake) record(method string, args ...any) {
+                            f.mu.Lock()
+                            defer f.mu.Unlock()
+                            f.calls = append(f.calls, FakeCall{Method: method, Args: args})
+                        }
+
+                        // Calls returns the recorded calls
+                        func (f *RustFfiFake) Calls() []FakeCall {
+                            f.mu.Lock()
+                            defer f.mu.Unlock()
+                            return append([]FakeCall(nil), f.calls...)
+                        }
+                        
+                        
+
+            
+
+            
+            
\ No newline at end of file
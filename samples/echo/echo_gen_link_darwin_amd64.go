@@ -0,0 +1,11 @@
+// Code generated by fcplug. DO NOT EDIT.
+
+            //go:build !fcplug_fake && !fcplug_cgobin
+
+            package echo
+
+            /*
+            #cgo LDFLAGS: -Ltarget/debug -lecho -ldl -lm
+            */
+            import "C"
+            
\ No newline at end of file
@@ -0,0 +1,65 @@
+// Code generated by fcplug. DO NOT EDIT.
+
+            //go:build !fcplug_fake
+
+            package echo
+            /*
+            #cgo CFLAGS: -Itarget/debug
+
+            #include "echo.h"
+            
+            */
+            import "C"
+
+            import (
+                "context"
+                "errors"
+                "fmt"
+                "reflect"
+                "runtime"
+                "sync"
+                "time"
+                "unsafe"
+
+                
+            )
+
+            var (
+                _ context.Context
+                _ = errors.New
+                _ = fmt.Sprintf
+                _ reflect.SliceHeader
+                _ = runtime.SetFinalizer
+                _ sync.Mutex
+                _ time.Duration
+                _ unsafe.Pointer
+            )
+            
+
+            
+type A struct {
+    Number    int32    `json:"number"`
+}
+        
+type Pong struct {
+    Msg    string    `json:"msg"`    
+NumberMap    map[int16]A    `json:"number_map"`
+}
+        
+type Ping struct {
+    Msg    string    `json:"msg"`    
+NumberList    []A    `json:"number_list"`    
+NumberSet    []A    `json:"number_set"`
+}
+        
+                        var GlobalRustFfi RustFfi = RustFfiImpl{}
+
+                        type RustFfi interface {
+                            
+                        }
+                        type RustFfiImpl struct{}
+                        
+                        
+
+            
+            
\ No newline at end of file
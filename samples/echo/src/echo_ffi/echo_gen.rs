@@ -35,6 +35,53 @@ pub struct Ping {
     pub number_set: ::std::vec::Vec<A>,
 }
 pub(super) trait RustFfi {}
+
+/// The instance which the RustFFI exports dispatch to, unavailable until it is registered
+#[inline]
+fn ffi_impl() -> ::fcplug::ABIResult<&'static FfiImpl> {
+    Ok(&FfiImpl)
+}
+
+#[cfg(not(feature = "goffi-mock"))]
+#[link(name = "go_echo", kind = "static")]
+extern "C" {
+    fn fcplug_go_init(config: ::fcplug::Buffer) -> ::fcplug::GoFfiResult;
+    fn fcplug_go_health() -> ::fcplug::GoFfiResult;
+    fn fcplug_go_shutdown() -> ::fcplug::GoFfiResult;
+}
+
+/// The lifecycle of the Go side, whose hooks are `GlobalGoLifecycle` in the Go package.
+pub struct GoLifecycle;
+
+#[cfg(not(feature = "goffi-mock"))]
+impl GoLifecycle {
+    pub fn init(config: &[u8]) -> ::fcplug::ABIResult<()> {
+        let mut config = config.to_vec();
+        ::fcplug::ABIResult::from(unsafe {
+            fcplug_go_init(::fcplug::Buffer::from_vec_mut(&mut config))
+        })
+    }
+    pub fn health() -> ::fcplug::ABIResult<()> {
+        ::fcplug::ABIResult::from(unsafe { fcplug_go_health() })
+    }
+    pub fn shutdown() -> ::fcplug::ABIResult<()> {
+        ::fcplug::ABIResult::from(unsafe { fcplug_go_shutdown() })
+    }
+}
+
+/// The Go side is mocked, so its lifecycle does nothing.
+#[cfg(feature = "goffi-mock")]
+impl GoLifecycle {
+    pub fn init(_config: &[u8]) -> ::fcplug::ABIResult<()> {
+        Ok(())
+    }
+    pub fn health() -> ::fcplug::ABIResult<()> {
+        Ok(())
+    }
+    pub fn shutdown() -> ::fcplug::ABIResult<()> {
+        Ok(())
+    }
+}
 trait Ffi: RustFfi + GoFfi + GoFfiCall {}
 
 pub struct FfiImpl;
@@ -7,8 +7,19 @@ fn main() {
         target_crate_dir: None,
         go_root_path: None,
         // go_root_path: Some("/Users/henrylee2cn/.gvm/gos/go1.18.10".into()),
-        go_mod_parent: "github.com/andeya/fcplug/samples",
+        go_mod_parent: "github.com/andeya/fcplug/samples".into(),
         use_goffi_cdylib: false,
         add_clib_to_git: false,
-    });
+        rust_impl_type: None,
+        go_object_path: None,
+        go_host: false,
+        typed_rustffi: false,
+        go_log_bridge: false,
+        go_requires: vec![],
+        go_offline: false,
+        artifact_dir: None,
+        target: None,
+        overrides: vec![],
+    })
+    .unwrap();
 }
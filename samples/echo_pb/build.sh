@@ -1,5 +1,3 @@
 #!/bin/bash
 
 cargo build --release
-cargo build --release
-cargo build --release
@@ -1,86 +1,149 @@
 // Code generated by fcplug. DO NOT EDIT.
 
-package main
-
-/*
-   #cgo CFLAGS: -I../../../target/debug
-   #cgo LDFLAGS: -L../../../target/debug -lecho_pb -ldl -lm
-
-   #include "echo_pb.h"
-*/
-import "C"
-import (
-	"reflect"
-	"unsafe"
-
-	"github.com/andeya/fcplug/samples/echo_pb"
-	"github.com/andeya/gust"
-)
-
-// main function is never called by C to.
-func main() {}
-
-var (
-	_ reflect.SliceHeader
-	_ unsafe.Pointer
-)
-var _ gust.EnumResult[any, any]
+        package main
+
+        /*
+        #cgo CFLAGS: -I../../../target/debug
+        #cgo LDFLAGS: -ldl -lm
+
+        #include "echo_pb.h"
+        */
+        import "C"
+        import (
+            "reflect"
+            "unsafe"
+
+            "github.com/andeya/gust"
+   "github.com/andeya/fcplug/samples/echo_pb"
+   
+        )
+
+        // main function is never called by C to.
+        func main() {}
+
+        var (
+            _ reflect.SliceHeader
+            _ unsafe.Pointer
+        )
+        var _ gust.EnumResult[any, any]
 var _ echo_pb.ResultCode
 
-var GlobalGoFfi GoFfi = _UnimplementedGoFfi{}
-
-type GoFfi interface {
-	EchoGo(req echo_pb.TBytes[echo_pb.Ping]) gust.EnumResult[echo_pb.TBytes[*echo_pb.Pong], ResultMsg]
-}
-type _UnimplementedGoFfi struct{}
-
-func (_UnimplementedGoFfi) EchoGo(req echo_pb.TBytes[echo_pb.Ping]) gust.EnumResult[echo_pb.TBytes[*echo_pb.Pong], ResultMsg] {
-	panic("unimplemented")
-}
-
-type ResultMsg struct {
-	Code echo_pb.ResultCode
-	Msg  string
-}
-
-//go:inline
-func asBuffer[T any](b echo_pb.TBytes[T]) C.struct_Buffer {
-	p, size := b.ForCBuffer()
-	if size == 0 {
-		return C.struct_Buffer{}
-	}
-	return C.struct_Buffer{
-		ptr: (*C.uint8_t)(p),
-		len: C.uintptr_t(size),
-		cap: C.uintptr_t(size),
-	}
-}
-
-//go:inline
-func asBytes[T any](buf C.struct_Buffer) echo_pb.TBytes[T] {
-	if buf.len == 0 {
-		return echo_pb.TBytes[T]{}
-	}
-	return echo_pb.TBytesFromBytes[T](*(*[]byte)(unsafe.Pointer(&reflect.SliceHeader{
-		Data: uintptr(unsafe.Pointer(buf.ptr)),
-		Len:  int(buf.len),
-		Cap:  int(buf.cap),
-	})))
-}
-
-//go:inline
-//export goffi_echo_go
-func goffi_echo_go(req C.struct_Buffer) C.struct_GoFfiResult {
-	if _EchoGo_Ret := GlobalGoFfi.EchoGo(asBytes[echo_pb.Ping](req)); _EchoGo_Ret.IsOk() {
-		return C.goffi_echo_go_set_result(asBuffer(_EchoGo_Ret.Unwrap()))
-	} else {
-		_EchoGo_Ret_Msg := _EchoGo_Ret.UnwrapErr()
-		if _EchoGo_Ret_Msg.Code == echo_pb.RcNoError {
-			_EchoGo_Ret_Msg.Code = echo_pb.RcUnknown
-		}
-		return C.struct_GoFfiResult{
-			code:     C.int8_t(_EchoGo_Ret_Msg.Code),
-			data_ptr: C.leak_buffer(asBuffer(echo_pb.TBytesFromString[string](_EchoGo_Ret_Msg.Msg))),
-		}
-	}
-}
+
+        
+
+                        var GlobalGoFfi GoFfi = _UnimplementedGoFfi{}
+
+                        type GoFfi interface {
+                            EchoGo(req echo_pb.TBytes[echo_pb.Ping]) gust.EnumResult[echo_pb.TBytes[*echo_pb.Pong], ResultMsg]
+
+                        }
+                        type _UnimplementedGoFfi struct{}
+                        func (_UnimplementedGoFfi) EchoGo(req echo_pb.TBytes[echo_pb.Ping]) gust.EnumResult[echo_pb.TBytes[*echo_pb.Pong], ResultMsg] {
+                        panic("unimplemented")
+                    }
+                    
+
+                        
+                //go:inline
+                //export goffi_echo_go
+                func goffi_echo_go(req C.struct_Buffer) C.struct_GoFfiResult {
+                    var _EchoGo_Ret echo_pb.TBytes[*echo_pb.Pong]
+                    _EchoGo_Err := echo_pb.InterceptGoFfi(&echo_pb.GoFfiCallInfo{Service: "GoFFI", Method: "echo_go", Args: []any{asBytes[echo_pb.Ping](req)}}, func() error {
+                        _Ret := GlobalGoFfi.EchoGo(asBytes[echo_pb.Ping](req))
+                        if _Ret.IsOk() {
+                            _EchoGo_Ret = _Ret.Unwrap()
+                            return nil
+                        }
+                        _Ret_Msg := _Ret.UnwrapErr()
+                        return &_Ret_Msg
+                    })
+                    if _EchoGo_Err == nil {
+                        return C.goffi_echo_go_set_result(asBuffer(_EchoGo_Ret))
+                    }
+                    if _EchoGo_Err.Code == echo_pb.RcNoError {
+                        _EchoGo_Err.Code = echo_pb.RcUnknown
+                    }
+                    return C.struct_GoFfiResult{
+                        code:     C.int32_t(_EchoGo_Err.Code),
+                        data_ptr: C.leak_buffer(asBuffer(echo_pb.TBytesFromBytes[string](_EchoGo_Err.Encode()))),
+                    }
+                }
+                
+
+        
+        // ResultMsg the error returned to Rust, e.g. `ResultMsg{Code: 1, Msg: "not found"}`
+        type ResultMsg = echo_pb.FfiError
+
+        // DeclaredError returns a ResultMsg carrying the error declared in the IDL,
+        // e.g. `DeclaredError(echo_pb.TBytesFromPbUnchecked(&echo_pb.MyError{}))`
+        //
+        //go:inline
+        func DeclaredError[T any](e echo_pb.TBytes[T]) ResultMsg {
+            return echo_pb.NewDeclaredError(e)
+        }
+
+        //go:inline
+        func asBuffer[T any](b echo_pb.TBytes[T]) C.struct_Buffer {
+            p, size := b.ForCBuffer()
+            if size == 0 {
+                return C.struct_Buffer{}
+            }
+            return C.struct_Buffer{
+                ptr: (*C.uint8_t)(p),
+                len: C.uintptr_t(size),
+                cap: C.uintptr_t(size),
+            }
+        }
+
+        //go:inline
+        func asBytes[T any](buf C.struct_Buffer) echo_pb.TBytes[T] {
+            if buf.len == 0 {
+                return echo_pb.TBytes[T]{}
+            }
+            return echo_pb.TBytesFromBytes[T](*(*[]byte)(unsafe.Pointer(&reflect.SliceHeader{
+                Data: uintptr(unsafe.Pointer(buf.ptr)),
+                Len:  int(buf.len),
+                Cap:  int(buf.cap),
+            })))
+        }
+
+        
+        //export fcplug_go_init
+        func fcplug_go_init(config C.struct_Buffer) C.struct_GoFfiResult {
+            if echo_pb.GlobalGoLifecycle == nil {
+                return C.struct_GoFfiResult{}
+            }
+            return asGoFfiResult(echo_pb.GlobalGoLifecycle.Init(asBytes[any](config).Bytes()))
+        }
+
+        //export fcplug_go_health
+        func fcplug_go_health() C.struct_GoFfiResult {
+            if echo_pb.GlobalGoLifecycle == nil {
+                return C.struct_GoFfiResult{}
+            }
+            return asGoFfiResult(echo_pb.GlobalGoLifecycle.Health())
+        }
+
+        //export fcplug_go_shutdown
+        func fcplug_go_shutdown() C.struct_GoFfiResult {
+            if echo_pb.GlobalGoLifecycle == nil {
+                return C.struct_GoFfiResult{}
+            }
+            return asGoFfiResult(echo_pb.GlobalGoLifecycle.Shutdown())
+        }
+
+        func asGoFfiResult(err error) C.struct_GoFfiResult {
+            e := echo_pb.AsFfiError(err)
+            if e == nil {
+                return C.struct_GoFfiResult{}
+            }
+            if e.Code == echo_pb.RcNoError {
+                e.Code = echo_pb.RcUnknown
+            }
+            return C.struct_GoFfiResult{
+                code:     C.int32_t(e.Code),
+                data_ptr: C.leak_buffer(asBuffer(echo_pb.TBytesFromBytes[string](e.Encode()))),
+            }
+        }
+        
+        
\ No newline at end of file
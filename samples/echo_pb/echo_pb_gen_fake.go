@@ -0,0 +1,720 @@
+// Code generated by fcplug. DO NOT EDIT.
+
+            //go:build fcplug_fake
+
+            package echo_pb
+
+            import (
+                "context"
+                "errors"
+                "fmt"
+                "reflect"
+                "runtime"
+                "sync"
+                "time"
+                "unsafe"
+
+                "github.com/andeya/gust/valconv"
+   "github.com/bytedance/sonic"
+   "google.golang.org/protobuf/proto"
+   
+            )
+
+            var (
+                _ context.Context
+                _ = errors.New
+                _ = fmt.Sprintf
+                _ reflect.SliceHeader
+                _ = runtime.SetFinalizer
+                _ sync.Mutex
+                _ time.Duration
+                _ unsafe.Pointer
+            )
+            var _ valconv.ReadonlyBytes
+var _ = sonic.Marshal
+var _ = proto.Marshal
+
+
+            
+                        var GlobalRustFfi RustFfi = &RustFfiFake{}
+
+                        type RustFfi interface {
+                            EchoRs(req TBytes[*Ping]) RustFfiResult[Pong]
+
+                        }
+
+                        // RustFfiFake the fake of RustFfi for the Go tests without the Rust library,
+                        // each method calls its stub func and records the call.
+                        type RustFfiFake struct {
+                            mu    sync.Mutex
+                            calls []FakeCall
+                            EchoRsFunc func(req *Ping) (*Pong, error)
+
+                        }
+
+                        func (f *RustFfiFake) record(method string, args ...any) {
+                            f.mu.Lock()
+                            defer f.mu.Unlock()
+                            f.calls = append(f.calls, FakeCall{Method: method, Args: args})
+                        }
+
+                        // Calls returns the recorded calls
+                        func (f *RustFfiFake) Calls() []FakeCall {
+                            f.mu.Lock()
+                            defer f.mu.Unlock()
+                            return append([]FakeCall(nil), f.calls...)
+                        }
+                        
+                func (f *RustFfiFake) EchoRs(req TBytes[*Ping]) RustFfiResult[Pong] {
+                    _req, err := fakeUnmarshal[Ping](req.Bytes())
+                    if err != nil {
+                        return fakeRustFfiResult[Pong](nil, NewFfiError(RcDecode, err.Error()), nil)
+                    }
+                    f.record("EchoRs", _req)
+                    if f.EchoRsFunc == nil {
+                        return fakeRustFfiResult[Pong](nil, NewFfiError(RcUnknown, "RustFfiFake.EchoRs is not stubbed"), nil)
+                    }
+                    ret, err := f.EchoRsFunc(_req)
+                    return fakeRustFfiResult[Pong](ret, err, nil)
+                }
+                
+                        
+            // RustFfiClient the idiomatic client of RustFfi, which encodes the arguments, decodes the result,
+            // and frees the Rust buffer before returning.
+            type RustFfiClient struct{}
+            
+                func (RustFfiClient) EchoRs(ctx context.Context, req *Ping) (*Pong, error) {
+                    if err := ctx.Err(); err != nil {
+                        return nil, err
+                    }
+                    _req, err := TBytesFromPb[*Ping](req)
+                    if err != nil {
+                        return nil, err
+                    }
+                    ret := GlobalRustFfi.EchoRs(_req)
+                    defer ret.Free()
+                    return ret.PbUnmarshal()
+                }
+                
+            
+
+            
+// ResultCode negative codes are reserved for the framework, positive codes are free for the users
+type ResultCode = int32
+
+const (
+	RcNoError ResultCode = 0
+	RcDecode  ResultCode = -1
+	RcEncode  ResultCode = -2
+	// RcDeclaredError the data is an encoded error declared in the IDL
+	RcDeclaredError ResultCode = -3
+	// RcUnavailable the library is not initialized or is shutting down
+	RcUnavailable ResultCode = -4
+	RcUnknown     ResultCode = -128
+	// RcUserMin the smallest code of the user errors
+	RcUserMin ResultCode = 1
+)
+
+// FfiError the error crossing the FFI boundary, encoded as JSON
+type FfiError struct {
+	Code    ResultCode        `json:"code"`
+	Kind    string            `json:"kind"`
+	Msg     string            `json:"msg"`
+	Cause   *FfiError         `json:"cause,omitempty"`
+	Details map[string]string `json:"details,omitempty"`
+}
+
+// The sentinel errors of the framework codes, e.g. `errors.Is(err, ErrDecode)`
+var (
+	ErrDecode   = NewFfiError(RcDecode, "decode error")
+	ErrEncode   = NewFfiError(RcEncode, "encode error")
+	ErrDeclared    = NewFfiError(RcDeclaredError, "declared error")
+	ErrUnavailable = NewFfiError(RcUnavailable, "unavailable")
+	ErrUnknown     = NewFfiError(RcUnknown, "unknown error")
+)
+
+// NewFfiError creates an error whose kind is derived from the code
+//
+//go:inline
+func NewFfiError(code ResultCode, msg string) *FfiError {
+	return &FfiError{Code: code, Kind: errorKind(code), Msg: msg}
+}
+
+func errorKind(code ResultCode) string {
+	switch {
+	case code == RcDecode:
+		return "decode"
+	case code == RcEncode:
+		return "encode"
+	case code == RcDeclaredError:
+		return "declared"
+	case code == RcUnknown || code == RcNoError:
+		return "unknown"
+	case code < RcNoError:
+		return "framework"
+	default:
+		return "user"
+	}
+}
+
+func (e *FfiError) Error() string {
+	if e.Cause != nil {
+		return e.Msg + ": " + e.Cause.Error()
+	}
+	return e.Msg
+}
+
+//go:inline
+func (e *FfiError) Unwrap() error {
+	if e.Cause == nil {
+		return nil
+	}
+	return e.Cause
+}
+
+// Is reports whether the target is an *FfiError with the same code
+//
+//go:inline
+func (e *FfiError) Is(target error) bool {
+	t, ok := target.(*FfiError)
+	return ok && t.Code == e.Code
+}
+
+// Encode encodes the error for Rust, the declared error keeps its encoded message
+func (e FfiError) Encode() []byte {
+	if e.Code == RcDeclaredError {
+		return valconv.StringToReadonlyBytes[string](e.Msg)
+	}
+	if e.Kind == "" {
+		e.Kind = errorKind(e.Code)
+	}
+	b, err := sonic.Marshal(e)
+	if err != nil {
+		return valconv.StringToReadonlyBytes[string](e.Msg)
+	}
+	return b
+}
+
+// NewDeclaredError returns the FfiError carrying the error declared in the IDL,
+// e.g. `NewDeclaredError(TBytesFromPbUnchecked(&MyError{}))`
+//
+//go:inline
+func NewDeclaredError[T any](e TBytes[T]) FfiError {
+	return FfiError{Code: RcDeclaredError, Kind: "declared", Msg: string(e.Bytes())}
+}
+
+func decodeFfiError(code ResultCode, b []byte) *FfiError {
+	var e FfiError
+	if err := sonic.ConfigStd.Unmarshal(b, &e); err != nil {
+		return NewFfiError(code, string(b))
+	}
+	e.Code = code
+	return &e
+}
+
+// GoFfiCallInfo the GoFFI call seen by the interceptors
+type GoFfiCallInfo struct {
+	// Service the service name in the IDL, e.g. `GoFFI`
+	Service string
+	// Method the method name in the IDL, e.g. `echo_go`
+	Method string
+	// Args the arguments from Rust, in the declared order
+	Args []any
+	// Metadata free for the interceptors to share data with the later ones
+	Metadata map[string]string
+}
+
+// GoFfiInterceptor wraps every GoFFI implementation called by Rust, such as auth checks and metrics.
+// It calls `next` to run the later interceptors and then the implementation,
+// the error returned without calling `next` rejects the call.
+type GoFfiInterceptor func(call *GoFfiCallInfo, next func() error) error
+
+var (
+	goFfiInterceptorsMu sync.RWMutex
+	goFfiInterceptors   []GoFfiInterceptor
+)
+
+// AddGoFfiInterceptor appends the interceptor to the chain run by the generated GoFFI exports
+func AddGoFfiInterceptor(interceptor GoFfiInterceptor) {
+	goFfiInterceptorsMu.Lock()
+	defer goFfiInterceptorsMu.Unlock()
+	goFfiInterceptors = append(goFfiInterceptors[:len(goFfiInterceptors):len(goFfiInterceptors)], interceptor)
+}
+
+// InterceptGoFfi runs the GoFFI implementation through the interceptors, called by the generated exports.
+// The returned error is converted by AsFfiError.
+func InterceptGoFfi(call *GoFfiCallInfo, impl func() error) *FfiError {
+	goFfiInterceptorsMu.RLock()
+	interceptors := goFfiInterceptors
+	goFfiInterceptorsMu.RUnlock()
+	next := impl
+	for i := len(interceptors) - 1; i >= 0; i-- {
+		interceptor, inner := interceptors[i], next
+		next = func() error { return interceptor(call, inner) }
+	}
+	return AsFfiError(next())
+}
+
+// AsFfiError converts the error to be sent to Rust, the errors other than *FfiError become RcUnknown
+func AsFfiError(err error) *FfiError {
+	if err == nil {
+		return nil
+	}
+	var e *FfiError
+	if errors.As(err, &e) {
+		return e
+	}
+	return NewFfiError(RcUnknown, err.Error())
+}
+
+// RustLogConfig the logging config of the Rust library, the empty fields keep the defaults of `fcplug::log::LogConfig`
+type RustLogConfig struct {
+	// Filter the level filters per target in the `RUST_LOG` syntax, e.g. `warn,my_crate=debug`
+	Filter string `json:"filter,omitempty"`
+	// Output "stdout" or "file"
+	Output string `json:"output,omitempty"`
+	// Format "text" or "json"
+	Format string `json:"format,omitempty"`
+	// Dir the directory of the log files
+	Dir        string `json:"dir,omitempty"`
+	FilePrefix string `json:"file_prefix,omitempty"`
+	// Rotation "minutely", "hourly", "daily" or "never"
+	Rotation string `json:"rotation,omitempty"`
+	// MaxFiles the number of the rotated files to keep, 0 keeps all
+	MaxFiles int `json:"max_files,omitempty"`
+}
+
+// GoLifecycle the hooks of the Go side, called by Rust through the generated `GoLifecycle` when Rust is the host
+type GoLifecycle interface {
+	// Init is called once with the config from Rust, which must be copied to be kept
+	Init(config []byte) error
+	// Health the error reports the Go side unhealthy
+	Health() error
+	// Shutdown stops the background goroutines and flushes the logs and metrics
+	Shutdown() error
+}
+
+// GlobalGoLifecycle the Go side hooks, nil means nothing to do
+var GlobalGoLifecycle GoLifecycle
+
+// TBytes bytes with type marker
+type TBytes[T any] struct {
+	bytes []byte
+	_nil  *T
+}
+
+// TBytesFromBytes new TBytes from bytes
+//go:inline
+func TBytesFromBytes[T any](bytes []byte) TBytes[T] {
+	return TBytes[T]{bytes: bytes}
+}
+
+// TBytesFromString new TBytes from string
+//go:inline
+func TBytesFromString[T any](s string) TBytes[T] {
+	return TBytes[T]{bytes: valconv.StringToReadonlyBytes[string](s)}
+}
+
+//go:inline
+func TBytesFromPbUnchecked[T proto.Message](obj T) TBytes[T] {
+	tb, _ := TBytesFromPb[T](obj)
+	return tb
+}
+
+//go:inline
+func TBytesFromPb[T proto.Message](obj T) (TBytes[T], error) {
+	var tb TBytes[T]
+	var err error
+	tb.bytes, err = proto.Marshal(obj)
+	if err != nil {
+		return TBytes[T]{}, err
+	}
+	return tb, nil
+}
+
+//go:inline
+func TBytesFromJsonUnchecked[T proto.Message](obj T) TBytes[T] {
+	tb, _ := TBytesFromJson[T](obj)
+	return tb
+}
+
+//go:inline
+func TBytesFromJson[T any](obj T) (TBytes[T], error) {
+	var tb TBytes[T]
+	var err error
+	tb.bytes, err = sonic.Marshal(obj)
+	if err != nil {
+		return TBytes[T]{}, err
+	}
+	return tb, nil
+}
+
+//go:inline
+func (b TBytes[T]) Len() int {
+	return len(b.bytes)
+}
+
+//go:inline
+func (b TBytes[T]) Bytes() []byte {
+	return b.bytes
+}
+
+// PbUnmarshal as protobuf to unmarshal
+// NOTE: maybe reference Rust memory buffer
+//
+//go:inline
+func (b TBytes[T]) PbUnmarshal() (*T, error) {
+	var t T
+	if b.Len() > 0 {
+		err := proto.Unmarshal(b.bytes, any(&t).(proto.Message))
+		if err != nil {
+			return nil, err
+		}
+	}
+	return &t, nil
+}
+
+// PbUnmarshalUnchecked as protobuf to unmarshal
+// NOTE: maybe reference Rust memory buffer
+//
+//go:inline
+func (b TBytes[T]) PbUnmarshalUnchecked() (*T) {
+	var t T
+	if b.Len() > 0 {
+		_= proto.Unmarshal(b.bytes, any(&t).(proto.Message))
+	}
+	return &t
+}
+
+// JsonUnmarshal as json to unmarshal
+// NOTE: maybe reference Rust memory buffer
+//
+//go:inline
+func (b TBytes[T]) JsonUnmarshal() (*T, error) {
+	var t T
+	if b.Len() > 0 {
+		err := sonic.Unmarshal(b.bytes, &t)
+		if err != nil {
+			return nil, err
+		}
+	}
+	return &t, nil
+}
+
+// JsonUnmarshalUnchecked as json to unmarshal
+// NOTE: maybe reference Rust memory buffer
+//
+//go:inline
+func (b TBytes[T]) JsonUnmarshalUnchecked() *T {
+	var t T
+	if b.Len() > 0 {
+		_ = sonic.Unmarshal(b.bytes, &t)
+	}
+	return &t
+}
+
+// Unmarshal unmarshal to object
+// NOTE: maybe reference Rust memory buffer
+//
+//go:inline
+func (b TBytes[T]) Unmarshal(unmarshal func([]byte, any) error) (*T, error) {
+	var t T
+	if b.Len() > 0 {
+		err := unmarshal(b.bytes, &t)
+		if err != nil {
+			return nil, err
+		}
+	}
+	return &t, nil
+}
+
+// UnmarshalUnchecked unmarshal to object
+// NOTE: maybe reference Rust memory buffer
+//
+//go:inline
+func (b TBytes[T]) UnmarshalUnchecked(unmarshal func([]byte, any) error) *T {
+	var t T
+	if b.Len() > 0 {
+		_ = unmarshal(b.bytes, &t)
+	}
+	return &t
+}
+
+//go:inline
+func (b TBytes[T]) ForCBuffer() (unsafe.Pointer, int) {
+	size := len(b.bytes)
+	if size == 0 {
+		return nil, 0
+	}
+	if cap(b.bytes) > size {
+		b.bytes = b.bytes[0:size:size]
+	}
+	return unsafe.Pointer(&b.bytes[0]), size
+}
+
+// RustFfiResult Rust FFI Result for Go
+// NOTE: must call Free method to free rust memory buffer, or use AutoFree!
+type RustFfiResult[T any] struct {
+	CBuffer
+	Code        ResultCode
+	_nil        *T
+	declaredErr func([]byte) error
+}
+
+//go:inline
+func (r RustFfiResult[T]) String() string {
+	return fmt.Sprintf("Code: %d, CBuffer: %s", r.Code, r.CBuffer.AsString())
+}
+
+// AutoFree frees the Rust memory buffer by a finalizer when the returned result is garbage collected,
+// so Free must not be called on it any more.
+// NOTE: the bytes referencing the Rust memory buffer must not outlive the returned result
+func (r RustFfiResult[T]) AutoFree() *RustFfiResult[T] {
+	p := &r
+	runtime.SetFinalizer(p, func(p *RustFfiResult[T]) {
+		p.Free()
+	})
+	return p
+}
+
+//go:inline
+func (r RustFfiResult[T]) IsOk() bool {
+	return r.Code == RcNoError
+}
+
+// AsError as an error
+// NOTE: reference Rust memory buffer
+//
+//go:inline
+func (r RustFfiResult[T]) AsError() error {
+	if r.Code == RcDeclaredError && r.declaredErr != nil {
+		return r.declaredErr(r.AsBytes())
+	}
+	if r.Code != RcNoError {
+		return decodeFfiError(r.Code, r.AsBytes())
+	}
+	return nil
+}
+
+//go:inline
+func (r RustFfiResult[T]) withDeclaredError(unmarshal func([]byte) error) RustFfiResult[T] {
+	r.declaredErr = unmarshal
+	return r
+}
+
+// PbUnmarshal as protobuf to unmarshal
+// NOTE: maybe reference Rust memory buffer
+//
+//go:inline
+func (r RustFfiResult[T]) PbUnmarshal() (*T, error) {
+	if err := r.AsError(); err != nil {
+		return nil, err
+	}
+	var t T
+	if r.Len() > 0 {
+		err := proto.Unmarshal(r.AsBytes(), any(&t).(proto.Message))
+		if err != nil {
+			return nil, err
+		}
+	}
+	return &t, nil
+}
+
+// PbUnmarshalUnchecked as protobuf to unmarshal
+// NOTE: maybe reference Rust memory buffer
+//
+//go:inline
+func (r RustFfiResult[T]) PbUnmarshalUnchecked() *T {
+	if err := r.AsError(); err != nil {
+		return nil
+	}
+	var t T
+	if r.Len() > 0 {
+		_ = proto.Unmarshal(r.AsBytes(), any(&t).(proto.Message))
+	}
+	return &t
+}
+
+// JsonUnmarshal as json to unmarshal
+// NOTE: maybe reference Rust memory buffer
+//
+//go:inline
+func (r RustFfiResult[T]) JsonUnmarshal() (*T, error) {
+	if err := r.AsError(); err != nil {
+		return nil, err
+	}
+	var t T
+	if r.Len() > 0 {
+		err := sonic.Unmarshal(r.AsBytes(), &t)
+		if err != nil {
+			return nil, err
+		}
+	}
+	return &t, nil
+}
+
+// JsonUnmarshalUnchecked as json to unmarshal
+// NOTE: maybe reference Rust memory buffer
+//
+//go:inline
+func (r RustFfiResult[T]) JsonUnmarshalUnchecked() *T {
+	if err := r.AsError(); err != nil {
+		return nil
+	}
+	var t T
+	if r.Len() > 0 {
+		_ = sonic.Unmarshal(r.AsBytes(), &t)
+	}
+	return &t
+}
+
+// Unmarshal unmarshal to object
+// NOTE: maybe reference Rust memory buffer
+//
+//go:inline
+func (r RustFfiResult[T]) Unmarshal(unmarshal func([]byte, any) error) (*T, error) {
+	if err := r.AsError(); err != nil {
+		return nil, err
+	}
+	var t T
+	if r.Len() > 0 {
+		err := unmarshal(r.AsBytes(), &t)
+		if err != nil {
+			return nil, err
+		}
+	}
+	return &t, nil
+}
+
+// UnmarshalUnchecked unmarshal to object
+// NOTE: maybe reference Rust memory buffer
+//
+//go:inline
+func (r RustFfiResult[T]) UnmarshalUnchecked(unmarshal func([]byte, any) error) *T {
+	if err := r.AsError(); err != nil {
+		return nil
+	}
+	var t T
+	if r.Len() > 0 {
+		_ = unmarshal(r.AsBytes(), &t)
+	}
+	return &t
+}
+
+
+// unmarshalDeclaredError unmarshal the error declared in the IDL
+func unmarshalDeclaredError[E any, PE interface {
+	*E
+	error
+}](b []byte) error {
+	var e E
+	if err := proto.Unmarshal(b, any(PE(&e)).(proto.Message)); err != nil {
+		return err
+	}
+	return PE(&e)
+}
+
+
+            
+// CBuffer the result buffer, which is a Go slice in the fake
+type CBuffer struct {
+	buf []byte
+}
+
+// Free does nothing in the fake
+//
+//go:inline
+func (b CBuffer) Free() {}
+
+//go:inline
+func (b CBuffer) Len() int {
+	return len(b.buf)
+}
+
+//go:inline
+func (b CBuffer) AsBytes() []byte {
+	return b.buf
+}
+
+//go:inline
+func (b CBuffer) AsString() string {
+	return string(b.buf)
+}
+
+// InitRust does nothing in the fake
+func InitRust(config []byte) error {
+	return nil
+}
+
+// RustHealth reports healthy in the fake
+func RustHealth() error {
+	return nil
+}
+
+// InitRustLog does nothing in the fake
+func InitRustLog(config RustLogConfig) error {
+	return nil
+}
+
+// ShutdownRust does nothing in the fake
+func ShutdownRust(timeout time.Duration) error {
+	return nil
+}
+
+// FakeCall a call recorded by the fakes
+type FakeCall struct {
+	Method string
+	Args   []any
+}
+
+func fakeMarshal(v any) ([]byte, error) {
+	return proto.Marshal(v.(proto.Message))
+}
+
+func fakeUnmarshal[T any](b []byte) (*T, error) {
+	var t T
+	if len(b) > 0 {
+		if err := proto.Unmarshal(b, any(&t).(proto.Message)); err != nil {
+			return nil, err
+		}
+	}
+	return &t, nil
+}
+
+//go:inline
+func isDeclaredError[E any](err error) bool {
+	_, ok := err.(*E)
+	return ok
+}
+
+// fakeRustFfiResult encodes the result of the stub as Rust does
+func fakeRustFfiResult[T any](ret *T, err error, isDeclared func(error) bool) RustFfiResult[T] {
+	if err != nil {
+		if isDeclared != nil && isDeclared(err) {
+			if b, merr := fakeMarshal(err); merr == nil {
+				return RustFfiResult[T]{CBuffer: CBuffer{buf: b}, Code: RcDeclaredError}
+			} else {
+				err = merr
+			}
+		}
+		var e *FfiError
+		if !errors.As(err, &e) {
+			e = NewFfiError(RcUnknown, err.Error())
+		}
+		code := e.Code
+		if code == RcNoError {
+			code = RcUnknown
+		}
+		return RustFfiResult[T]{CBuffer: CBuffer{buf: e.Encode()}, Code: code}
+	}
+	if ret == nil {
+		return RustFfiResult[T]{}
+	}
+	b, err := fakeMarshal(ret)
+	if err != nil {
+		return RustFfiResult[T]{CBuffer: CBuffer{buf: NewFfiError(RcEncode, err.Error()).Encode()}, Code: RcEncode}
+	}
+	return RustFfiResult[T]{CBuffer: CBuffer{buf: b}}
+}
+
+            
\ No newline at end of file
@@ -56,14 +56,24 @@ impl ::pilota::prost::Message for Ping {
 }
 
 pub(super) trait RustFfi {
-    fn echo_rs(req: ::fcplug::RustFfiArg<Ping>) -> ::fcplug::ABIResult<::fcplug::TBytes<Pong>>;
+    fn echo_rs(
+        &self,
+        req: ::fcplug::RustFfiArg<Ping>,
+    ) -> ::fcplug::ABIResult<::fcplug::TBytes<Pong>>;
 }
 #[no_mangle]
 #[inline]
 pub extern "C" fn rustffi_echo_rs(req: ::fcplug::Buffer) -> ::fcplug::RustFfiResult {
-    ::fcplug::RustFfiResult::from(<FfiImpl as RustFfi>::echo_rs(::fcplug::RustFfiArg::from(
-        req,
-    )))
+    let ffi_impl = match ffi_impl() {
+        Ok(ffi_impl) => ffi_impl,
+        Err(err) => return ::fcplug::RustFfiResult::from_err(err),
+    };
+    ::fcplug::intercept_rust_ffi("RustFFI", "echo_rs", &[req], || {
+        ::fcplug::RustFfiResult::from(<FfiImpl as RustFfi>::echo_rs(
+            ffi_impl,
+            ::fcplug::RustFfiArg::from(req),
+        ))
+    })
 }
 #[derive(
     PartialOrd,
@@ -121,26 +131,131 @@ impl ::pilota::prost::Message for Pong {
 }
 
 pub(super) trait GoFfi {
-    unsafe fn echo_go_set_result(go_ret: ::fcplug::RustFfiArg<Pong>) -> ::fcplug::GoFfiResult;
+    /// Store the result of Go, which is decoded by default.
+    unsafe fn echo_go_set_result(mut go_ret: ::fcplug::RustFfiArg<Pong>) -> ::fcplug::GoFfiResult {
+        ::fcplug::GoFfiResult::from_ok(go_ret.try_to_object::<::fcplug::protobuf::PbMessage<_>>()?)
+    }
 }
 
 pub trait GoFfiCall {
-    unsafe fn echo_go<T: Default>(mut req: ::fcplug::TBytes<Ping>) -> ::fcplug::ABIResult<T> {
-        ::fcplug::ABIResult::from(goffi_echo_go(::fcplug::Buffer::from_vec_mut(
-            &mut req.bytes,
-        )))
+    unsafe fn echo_go<T: Default + 'static>(
+        mut req: ::fcplug::TBytes<Ping>,
+    ) -> ::fcplug::ABIResult<T> {
+        let req = ::fcplug::Buffer::from_vec_mut(&mut req.bytes);
+        ::fcplug::intercept_go_ffi("GoFFI", "echo_go", &[req], || goffi_echo_go(req))
+            .into_result::<T, Pong, ::fcplug::protobuf::PbMessage<_>>()
     }
 }
 
+#[cfg(not(feature = "goffi-mock"))]
 #[link(name = "go_echo_pb", kind = "static")]
 extern "C" {
     fn goffi_echo_go(req: ::fcplug::Buffer) -> ::fcplug::GoFfiResult;
 }
+
+#[cfg(feature = "goffi-mock")]
+static GOFFI_ECHO_GO_MOCK: ::std::sync::RwLock<
+    Option<::std::sync::Arc<dyn Fn(Ping) -> ::fcplug::ABIResult<Pong> + Send + Sync>>,
+> = ::std::sync::RwLock::new(None);
+#[cfg(feature = "goffi-mock")]
+#[inline]
+unsafe fn goffi_echo_go(req: ::fcplug::Buffer) -> ::fcplug::GoFfiResult {
+    let Some(mock) = GOFFI_ECHO_GO_MOCK.read().unwrap().clone() else {
+        return ::fcplug::GoFfiResult::from_err(::fcplug::FfiError::new(::fcplug::RC_UNAVAILABLE, "the GoFFI mock of `GoFfi::echo_go` is not installed, install it by `GoFfiMock::echo_go`"));
+    };
+    match mock(
+        ::fcplug::RustFfiArg::<Ping>::from(req)
+            .try_to_object::<::fcplug::protobuf::PbMessage<_>>()
+            .expect("failed to decode the GoFFI argument `req`"),
+    ) {
+        Ok(ret) => {
+            let mut ret =
+                match ::fcplug::TBytes::<Pong>::try_from::<::fcplug::protobuf::PbMessage<_>>(ret) {
+                    Ok(ret) => ret.bytes,
+                    Err(e) => return e.into(),
+                };
+            goffi_echo_go_set_result(::fcplug::Buffer::from_vec_mut(&mut ret))
+        }
+        Err(e) => e.into(),
+    }
+}
+
+/// The mock of `GoFfi` for the Rust tests without the Go toolchain, enabled by the `goffi-mock` feature.
+/// The mocks are global, so the tests installing different mocks of the same method should not run in parallel.
+#[cfg(feature = "goffi-mock")]
+pub struct GoFfiMock;
+
+#[cfg(feature = "goffi-mock")]
+impl GoFfiMock {
+    /// Install the mock of `echo_go`, which replaces the previous one.
+    pub fn echo_go(f: impl Fn(Ping) -> ::fcplug::ABIResult<Pong> + Send + Sync + 'static) {
+        *GOFFI_ECHO_GO_MOCK.write().unwrap() = Some(::std::sync::Arc::new(f));
+    }
+}
 #[no_mangle]
 #[inline]
 pub extern "C" fn goffi_echo_go_set_result(buf: ::fcplug::Buffer) -> ::fcplug::GoFfiResult {
     unsafe { <FfiImpl as GoFfi>::echo_go_set_result(::fcplug::RustFfiArg::from(buf)) }
 }
+
+/// The safe and typed client of `GoFfi`, which encodes the arguments and decodes the result.
+pub struct GoFfiClient;
+
+impl GoFfiClient {
+    pub fn echo_go(req: &Ping) -> ::fcplug::ABIResult<Pong> {
+        let req = ::fcplug::TBytes::new(
+            ::fcplug::TryToBytes::<::fcplug::protobuf::PbMessage<_>>::try_to_bytes(req)?,
+        );
+        unsafe { <FfiImpl as GoFfiCall>::echo_go::<Pong>(req) }
+    }
+}
+
+/// The instance which the RustFFI exports dispatch to, unavailable until it is registered
+#[inline]
+fn ffi_impl() -> ::fcplug::ABIResult<&'static FfiImpl> {
+    Ok(&FfiImpl)
+}
+
+#[cfg(not(feature = "goffi-mock"))]
+#[link(name = "go_echo_pb", kind = "static")]
+extern "C" {
+    fn fcplug_go_init(config: ::fcplug::Buffer) -> ::fcplug::GoFfiResult;
+    fn fcplug_go_health() -> ::fcplug::GoFfiResult;
+    fn fcplug_go_shutdown() -> ::fcplug::GoFfiResult;
+}
+
+/// The lifecycle of the Go side, whose hooks are `GlobalGoLifecycle` in the Go package.
+pub struct GoLifecycle;
+
+#[cfg(not(feature = "goffi-mock"))]
+impl GoLifecycle {
+    pub fn init(config: &[u8]) -> ::fcplug::ABIResult<()> {
+        let mut config = config.to_vec();
+        ::fcplug::ABIResult::from(unsafe {
+            fcplug_go_init(::fcplug::Buffer::from_vec_mut(&mut config))
+        })
+    }
+    pub fn health() -> ::fcplug::ABIResult<()> {
+        ::fcplug::ABIResult::from(unsafe { fcplug_go_health() })
+    }
+    pub fn shutdown() -> ::fcplug::ABIResult<()> {
+        ::fcplug::ABIResult::from(unsafe { fcplug_go_shutdown() })
+    }
+}
+
+/// The Go side is mocked, so its lifecycle does nothing.
+#[cfg(feature = "goffi-mock")]
+impl GoLifecycle {
+    pub fn init(_config: &[u8]) -> ::fcplug::ABIResult<()> {
+        Ok(())
+    }
+    pub fn health() -> ::fcplug::ABIResult<()> {
+        Ok(())
+    }
+    pub fn shutdown() -> ::fcplug::ABIResult<()> {
+        Ok(())
+    }
+}
 trait Ffi: RustFfi + GoFfi + GoFfiCall {}
 
 pub struct FfiImpl;
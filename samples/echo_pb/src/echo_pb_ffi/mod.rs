@@ -7,7 +7,10 @@ use fcplug::{GoFfiResult, TryIntoTBytes};
 mod echo_pb_gen;
 
 impl RustFfi for FfiImpl {
-    fn echo_rs(mut req: ::fcplug::RustFfiArg<Ping>) -> ::fcplug::ABIResult<::fcplug::TBytes<Pong>> {
+    fn echo_rs(
+        &self,
+        mut req: ::fcplug::RustFfiArg<Ping>,
+    ) -> ::fcplug::ABIResult<::fcplug::TBytes<Pong>> {
         let _req = req.try_to_object::<PbMessage<_>>();
         #[cfg(debug_assertions)]
         println!("rust receive req: {:?}", _req);
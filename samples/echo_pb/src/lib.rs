@@ -4,36 +4,87 @@ extern crate test;
 
 pub mod echo_pb_ffi;
 
-
-#[cfg(test)]
+#[cfg(all(test, not(feature = "goffi-mock")))]
 mod tests {
     use test::Bencher;
 
     use fcplug::protobuf::PbMessage;
     use fcplug::TryIntoTBytes;
 
-    use crate::echo_pb_ffi::{FfiImpl, GoFfiCall, Ping, Pong};
+    use crate::echo_pb_ffi::{FfiImpl, GoFfiCall, GoFfiClient, Ping, Pong};
 
     #[test]
     fn test_call_echo_go() {
         let pong = unsafe {
-            FfiImpl::echo_go::<Pong>(Ping {
-                msg: "this is ping from rust".to_string(),
-            }.try_into_tbytes::<PbMessage<_>>().unwrap())
+            FfiImpl::echo_go::<Pong>(
+                Ping {
+                    msg: "this is ping from rust".to_string(),
+                }
+                .try_into_tbytes::<PbMessage<_>>()
+                .unwrap(),
+            )
         };
         println!("{:?}", pong);
     }
 
+    #[test]
+    fn test_call_echo_go_client() {
+        let pong = GoFfiClient::echo_go(&Ping {
+            msg: "this is ping from rust".to_string(),
+        });
+        println!("{:?}", pong);
+    }
+
     #[bench]
     fn bench_call_echo_go(b: &mut Bencher) {
         let req = Ping {
             msg: "this is ping from rust".to_string(),
         }
-            .try_into_tbytes::<PbMessage<_>>()
-            .unwrap();
+        .try_into_tbytes::<PbMessage<_>>()
+        .unwrap();
         b.iter(|| {
             let pong = unsafe { FfiImpl::echo_go::<Vec<u8>>(req.clone()) };
             let _ = test::black_box(pong);
         });
     }
 }
+
+#[cfg(all(test, feature = "goffi-mock"))]
+mod mock_tests {
+    use fcplug::protobuf::{Message, PbMessage};
+    use fcplug::TryIntoTBytes;
+
+    use crate::echo_pb_ffi::{FfiImpl, GoFfiCall, GoFfiClient, GoFfiMock, Ping, Pong};
+
+    #[test]
+    fn test_call_echo_go_mock() {
+        let err = GoFfiClient::echo_go(&Ping::default()).unwrap_err();
+        assert_eq!(err.code, fcplug::RC_UNAVAILABLE);
+
+        GoFfiMock::echo_go(|req: Ping| {
+            Ok(Pong {
+                msg: format!("mock pong to {}", req.msg),
+            })
+        });
+        let pong = GoFfiClient::echo_go(&Ping {
+            msg: "this is ping from rust".to_string(),
+        })
+        .unwrap();
+        assert_eq!(pong.msg, "mock pong to this is ping from rust");
+        // the unsafe call by `Vec<u8>` gets the encoded result as before
+        let req = Ping {
+            msg: "this is ping from rust".to_string(),
+        }
+        .try_into_tbytes::<PbMessage<_>>()
+        .unwrap();
+        let bytes = unsafe { FfiImpl::echo_go::<Vec<u8>>(req) }.unwrap();
+        assert_eq!(
+            Pong::decode(bytes.as_slice()).unwrap().msg,
+            "mock pong to this is ping from rust"
+        );
+
+        GoFfiMock::echo_go(|_| Err(fcplug::FfiError::new(1, "mock error")));
+        let err = GoFfiClient::echo_go(&Ping::default()).unwrap_err();
+        assert_eq!((err.code, err.msg.as_str()), (1, "mock error"));
+    }
+}
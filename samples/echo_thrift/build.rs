@@ -6,8 +6,20 @@ fn main() {
     generate_code(Config {
         idl_file: "./echo.thrift".into(),
         go_root_path: None,
-        go_mod_parent: "github.com/andeya/fcplug/samples",
+        go_mod_parent: "github.com/andeya/fcplug/samples".into(),
         target_crate_dir: None,
+        use_goffi_cdylib: false,
         add_clib_to_git: false,
-    });
+        rust_impl_type: None,
+        go_object_path: None,
+        go_host: false,
+        typed_rustffi: false,
+        go_log_bridge: false,
+        go_requires: vec![],
+        go_offline: false,
+        artifact_dir: None,
+        target: None,
+        overrides: vec![],
+    })
+    .unwrap();
 }
@@ -0,0 +1,149 @@
+// Code generated by fcplug. DO NOT EDIT.
+
+        package main
+
+        /*
+        #cgo CFLAGS: -I../target/debug
+        #cgo LDFLAGS: -ldl -lm
+
+        #include "echo_thrift.h"
+        */
+        import "C"
+        import (
+            "reflect"
+            "unsafe"
+
+            "github.com/andeya/gust"
+   "github.com/andeya/fcplug/samples/echo_thrift"
+   
+        )
+
+        // main function is never called by C to.
+        func main() {}
+
+        var (
+            _ reflect.SliceHeader
+            _ unsafe.Pointer
+        )
+        var _ gust.EnumResult[any, any]
+var _ echo_thrift.ResultCode
+
+
+        
+
+                        var GlobalGoFfi GoFfi = _UnimplementedGoFfi{}
+
+                        type GoFfi interface {
+                            EchoGo(req echo_thrift.TBytes[echo_thrift.Ping]) gust.EnumResult[echo_thrift.TBytes[*echo_thrift.Pong], ResultMsg]
+
+                        }
+                        type _UnimplementedGoFfi struct{}
+                        func (_UnimplementedGoFfi) EchoGo(req echo_thrift.TBytes[echo_thrift.Ping]) gust.EnumResult[echo_thrift.TBytes[*echo_thrift.Pong], ResultMsg] {
+                        panic("unimplemented")
+                    }
+                    
+
+                        
+                //go:inline
+                //export goffi_echo_go
+                func goffi_echo_go(req C.struct_Buffer) C.struct_GoFfiResult {
+                    var _EchoGo_Ret echo_thrift.TBytes[*echo_thrift.Pong]
+                    _EchoGo_Err := echo_thrift.InterceptGoFfi(&echo_thrift.GoFfiCallInfo{Service: "goFFI", Method: "echo_go", Args: []any{asBytes[echo_thrift.Ping](req)}}, func() error {
+                        _Ret := GlobalGoFfi.EchoGo(asBytes[echo_thrift.Ping](req))
+                        if _Ret.IsOk() {
+                            _EchoGo_Ret = _Ret.Unwrap()
+                            return nil
+                        }
+                        _Ret_Msg := _Ret.UnwrapErr()
+                        return &_Ret_Msg
+                    })
+                    if _EchoGo_Err == nil {
+                        return C.goffi_echo_go_set_result(asBuffer(_EchoGo_Ret))
+                    }
+                    if _EchoGo_Err.Code == echo_thrift.RcNoError {
+                        _EchoGo_Err.Code = echo_thrift.RcUnknown
+                    }
+                    return C.struct_GoFfiResult{
+                        code:     C.int32_t(_EchoGo_Err.Code),
+                        data_ptr: C.leak_buffer(asBuffer(echo_thrift.TBytesFromBytes[string](_EchoGo_Err.Encode()))),
+                    }
+                }
+                
+
+        
+        // ResultMsg the error returned to Rust, e.g. `ResultMsg{Code: 1, Msg: "not found"}`
+        type ResultMsg = echo_thrift.FfiError
+
+        // DeclaredError returns a ResultMsg carrying the error declared in the IDL,
+        // e.g. `DeclaredError(echo_thrift.TBytesFromPbUnchecked(&echo_thrift.MyError{}))`
+        //
+        //go:inline
+        func DeclaredError[T any](e echo_thrift.TBytes[T]) ResultMsg {
+            return echo_thrift.NewDeclaredError(e)
+        }
+
+        //go:inline
+        func asBuffer[T any](b echo_thrift.TBytes[T]) C.struct_Buffer {
+            p, size := b.ForCBuffer()
+            if size == 0 {
+                return C.struct_Buffer{}
+            }
+            return C.struct_Buffer{
+                ptr: (*C.uint8_t)(p),
+                len: C.uintptr_t(size),
+                cap: C.uintptr_t(size),
+            }
+        }
+
+        //go:inline
+        func asBytes[T any](buf C.struct_Buffer) echo_thrift.TBytes[T] {
+            if buf.len == 0 {
+                return echo_thrift.TBytes[T]{}
+            }
+            return echo_thrift.TBytesFromBytes[T](*(*[]byte)(unsafe.Pointer(&reflect.SliceHeader{
+                Data: uintptr(unsafe.Pointer(buf.ptr)),
+                Len:  int(buf.len),
+                Cap:  int(buf.cap),
+            })))
+        }
+
+        
+        //export fcplug_go_init
+        func fcplug_go_init(config C.struct_Buffer) C.struct_GoFfiResult {
+            if echo_thrift.GlobalGoLifecycle == nil {
+                return C.struct_GoFfiResult{}
+            }
+            return asGoFfiResult(echo_thrift.GlobalGoLifecycle.Init(asBytes[any](config).Bytes()))
+        }
+
+        //export fcplug_go_health
+        func fcplug_go_health() C.struct_GoFfiResult {
+            if echo_thrift.GlobalGoLifecycle == nil {
+                return C.struct_GoFfiResult{}
+            }
+            return asGoFfiResult(echo_thrift.GlobalGoLifecycle.Health())
+        }
+
+        //export fcplug_go_shutdown
+        func fcplug_go_shutdown() C.struct_GoFfiResult {
+            if echo_thrift.GlobalGoLifecycle == nil {
+                return C.struct_GoFfiResult{}
+            }
+            return asGoFfiResult(echo_thrift.GlobalGoLifecycle.Shutdown())
+        }
+
+        func asGoFfiResult(err error) C.struct_GoFfiResult {
+            e := echo_thrift.AsFfiError(err)
+            if e == nil {
+                return C.struct_GoFfiResult{}
+            }
+            if e.Code == echo_thrift.RcNoError {
+                e.Code = echo_thrift.RcUnknown
+            }
+            return C.struct_GoFfiResult{
+                code:     C.int32_t(e.Code),
+                data_ptr: C.leak_buffer(asBuffer(echo_thrift.TBytesFromBytes[string](e.Encode()))),
+            }
+        }
+        
+        
\ No newline at end of file
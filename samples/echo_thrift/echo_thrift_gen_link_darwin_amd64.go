@@ -0,0 +1,11 @@
+// Code generated by fcplug. DO NOT EDIT.
+
+            //go:build !fcplug_fake && !fcplug_cgobin
+
+            package echo_thrift
+
+            /*
+            #cgo LDFLAGS: -Ltarget/debug -lecho_thrift -ldl -lm
+            */
+            import "C"
+            
\ No newline at end of file
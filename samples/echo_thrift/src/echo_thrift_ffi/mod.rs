@@ -5,7 +5,10 @@ pub use echo_thrift_gen::*;
 mod echo_thrift_gen;
 
 impl RustFfi for FfiImpl {
-    fn echo_rs(req: ::fcplug::RustFfiArg<Ping>) -> ::fcplug::ABIResult<::fcplug::TBytes<Pong>> {
+    fn echo_rs(
+        &self,
+        req: ::fcplug::RustFfiArg<Ping>,
+    ) -> ::fcplug::ABIResult<::fcplug::TBytes<Pong>> {
         todo!()
     }
 }
@@ -4,23 +4,20 @@ extern crate test;
 
 mod echo_thrift_ffi;
 
-
 #[cfg(test)]
 mod tests {
     use test::Bencher;
 
-    use fcplug::protobuf::PbMessage;
+    use fcplug::serde::JsonMessage;
     use fcplug::TryIntoTBytes;
 
-    use crate::echo_thrift_ffi::{FfiImpl, GoFfiCall, Ping, Pong};
+    use crate::echo_thrift_ffi::{FfiImpl, GoFfiCall, GoFfiClient, Ping, Pong};
 
     #[test]
     fn test_call_echo_go() {
-        let pong = unsafe {
-            FfiImpl::echo_go::<Pong>(Ping {
-                msg: "this is ping from rust".to_string(),
-            }.try_into_tbytes::<PbMessage<_>>().unwrap())
-        };
+        let pong = GoFfiClient::echo_go(&Ping {
+            msg: "this is ping from rust".to_string(),
+        });
         println!("{:?}", pong);
     }
 
@@ -29,10 +26,10 @@ mod tests {
         let req = Ping {
             msg: "this is ping from rust".to_string(),
         }
-            .try_into_tbytes::<PbMessage<_>>()
-            .unwrap();
+        .try_into_tbytes::<JsonMessage<_>>()
+        .unwrap();
         b.iter(|| {
-            let pong = unsafe { FfiImpl::echo_go::<Vec<u8>>(req.clone()) };
+            let pong = unsafe { FfiImpl::echo_go::<Pong>(req.clone()) };
             let _ = test::black_box(pong);
         });
     }